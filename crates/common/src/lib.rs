@@ -1,12 +1,36 @@
 use std::{mem, slice};
+use ultraviolet::Vec3;
 use winit::event::{DeviceEvent, WindowEvent};
 
 pub enum InputEvent<'a> {
     Window(WindowEvent<'a>),
     Device(DeviceEvent),
+    /// Analog movement, like from a gamepad's sticks, over the last frame.
+    Navigate(Navigation),
+    /// The center and radius of a sphere around everything in the world, after atoms were
+    /// added, removed, or moved.
+    Bounds(Option<(Vec3, f32)>),
     BeginningFrame,
 }
 
+/// How far each axis of an analog device was pushed, from -1 to 1, multiplied by how many
+/// seconds it was held there. The camera decides how fast that moves it.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Navigation {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub pan_right: f32,
+    pub pan_up: f32,
+    /// Positive is toward the target.
+    pub dolly: f32,
+}
+
+impl Navigation {
+    pub fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 pub unsafe trait AsBytes {
     fn as_bytes(&self) -> &[u8] {
         unsafe { slice::from_raw_parts(self as *const _ as *const u8, mem::size_of_val(self)) }
@@ -28,6 +52,7 @@ impl_as_bytes!(
     (),
     ultraviolet::Vec2,
     ultraviolet::Vec3,
+    ultraviolet::Vec4,
     ultraviolet::Mat2,
     ultraviolet::Mat3,
     ultraviolet::Mat4,
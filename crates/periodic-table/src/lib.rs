@@ -138,8 +138,128 @@ impl Element {
             None
         }
     }
+
+    pub fn symbol(&self) -> &'static str {
+        SYMBOLS[*self as usize - 1]
+    }
+
+    /// Case-insensitive, so "CL" and "cl" are both chlorine.
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        SYMBOLS
+            .iter()
+            .position(|s| s.eq_ignore_ascii_case(symbol))
+            .and_then(|index| Self::from_atomic_number(index as u8 + 1))
+    }
+
+    /// The row of the periodic table, starting at 1.
+    pub fn period(&self) -> u8 {
+        let n = *self as u8;
+        PERIOD_ENDS
+            .iter()
+            .position(|&end| n <= end)
+            .map(|index| index as u8 + 1)
+            .unwrap_or_else(|| unreachable!("atomic number out of range"))
+    }
+
+    /// The column of the periodic table, from 1 to 18.
+    ///
+    /// Returns `None` for the f-block (cerium to lutetium and thorium to lawrencium), which
+    /// doesn't have groups. Lanthanum and actinium are in group 3.
+    pub fn group(&self) -> Option<u8> {
+        let period = self.period();
+        // The position of this element within its period, starting at 1.
+        let position = *self as u8 - Self::period_start(period) + 1;
+
+        match period {
+            1 if position == 1 => Some(1),
+            1 => Some(18),
+            2 | 3 if position <= 2 => Some(position),
+            2 | 3 => Some(position + 10),
+            4 | 5 => Some(position),
+            _ if position <= 3 => Some(position),
+            _ if position <= 17 => None,
+            _ => Some(position - 14),
+        }
+    }
+
+    /// Where this element goes in the standard 18-column layout, as zero-based
+    /// `(row, column)`.
+    ///
+    /// The f-block is split out below the main table: row 7 holds cerium to lutetium and row 8
+    /// holds thorium to lawrencium, both starting in column 3.
+    pub fn table_position(&self) -> (u8, u8) {
+        let period = self.period();
+        match self.group() {
+            Some(group) => (period - 1, group - 1),
+            None => {
+                let position = *self as u8 - Self::period_start(period) + 1;
+                (period + 1, position - 1)
+            }
+        }
+    }
+
+    /// The single-bond covalent radius in angstroms, from Cordero et al. (2008).
+    ///
+    /// There's no data past curium, so those elements all get 1.5.
+    pub fn covalent_radius(&self) -> f32 {
+        COVALENT_RADII
+            .get(*self as usize - 1)
+            .copied()
+            .unwrap_or(1.5)
+    }
+
+    /// The standard atomic weight, in daltons.
+    pub fn atomic_mass(&self) -> f32 {
+        ATOMIC_MASSES[*self as usize - 1]
+    }
+
+    fn period_start(period: u8) -> u8 {
+        match period {
+            1 => 1,
+            _ => PERIOD_ENDS[period as usize - 2] + 1,
+        }
+    }
 }
 
+/// The atomic number of the last element in each period.
+const PERIOD_ENDS: [u8; 7] = [2, 10, 18, 36, 54, 86, 118];
+
+/// Transition metals use their low-spin radii.
+const COVALENT_RADII: [f32; 96] = [
+    0.31, 0.28, 1.28, 0.96, 0.84, 0.76, 0.71, 0.66, 0.57, 0.58, 1.66, 1.41, 1.21, 1.11, 1.07, 1.05,
+    1.02, 1.06, 2.03, 1.76, 1.70, 1.60, 1.53, 1.39, 1.39, 1.32, 1.26, 1.24, 1.32, 1.22, 1.22, 1.20,
+    1.19, 1.20, 1.20, 1.16, 2.20, 1.95, 1.90, 1.75, 1.64, 1.54, 1.47, 1.46, 1.42, 1.39, 1.45, 1.44,
+    1.42, 1.39, 1.39, 1.38, 1.39, 1.40, 2.44, 2.15, 2.07, 2.04, 2.03, 2.01, 1.99, 1.98, 1.98, 1.96,
+    1.94, 1.92, 1.92, 1.89, 1.90, 1.87, 1.87, 1.75, 1.70, 1.62, 1.51, 1.44, 1.41, 1.36, 1.36, 1.32,
+    1.45, 1.46, 1.48, 1.40, 1.50, 1.50, 2.60, 2.21, 2.15, 2.06, 2.00, 1.96, 1.90, 1.87, 1.80, 1.69,
+];
+
+/// Standard atomic weights. Elements without a stable isotope use the mass number of their
+/// longest-lived one.
+const ATOMIC_MASSES: [f32; 118] = [
+    1.008, 4.0026, 6.94, 9.0122, 10.81, 12.011, 14.007, 15.999, 18.998, 20.180, 22.990, 24.305,
+    26.982, 28.085, 30.974, 32.06, 35.45, 39.948, 39.098, 40.078, 44.956, 47.867, 50.942, 51.996,
+    54.938, 55.845, 58.933, 58.693, 63.546, 65.38, 69.723, 72.630, 74.922, 78.971, 79.904, 83.798,
+    85.468, 87.62, 88.906, 91.224, 92.906, 95.95, 97.0, 101.07, 102.91, 106.42, 107.87, 112.41,
+    114.82, 118.71, 121.76, 127.60, 126.90, 131.29, 132.91, 137.33, 138.91, 140.12, 140.91, 144.24,
+    145.0, 150.36, 151.96, 157.25, 158.93, 162.50, 164.93, 167.26, 168.93, 173.05, 174.97, 178.49,
+    180.95, 183.84, 186.21, 190.23, 192.22, 195.08, 196.97, 200.59, 204.38, 207.2, 208.98, 209.0,
+    210.0, 222.0, 223.0, 226.0, 227.0, 232.04, 231.04, 238.03, 237.0, 244.0, 243.0, 247.0, 247.0,
+    251.0, 252.0, 257.0, 258.0, 259.0, 266.0, 267.0, 268.0, 269.0, 270.0, 277.0, 278.0, 281.0,
+    282.0, 285.0, 286.0, 289.0, 290.0, 293.0, 294.0, 294.0,
+];
+
+const SYMBOLS: [&str; 118] = [
+    "H", "He", "Li", "Be", "B", "C", "N", "O", "F", "Ne", "Na", "Mg", "Al", "Si", "P", "S", "Cl",
+    "Ar", "K", "Ca", "Sc", "Ti", "V", "Cr", "Mn", "Fe", "Co", "Ni", "Cu", "Zn", "Ga", "Ge", "As",
+    "Se", "Br", "Kr", "Rb", "Sr", "Y", "Zr", "Nb", "Mo", "Tc", "Ru", "Rh", "Pd", "Ag", "Cd", "In",
+    "Sn", "Sb", "Te", "I", "Xe", "Cs", "Ba", "La", "Ce", "Pr", "Nd", "Pm", "Sm", "Eu", "Gd", "Tb",
+    "Dy", "Ho", "Er", "Tm", "Yb", "Lu", "Hf", "Ta", "W", "Re", "Os", "Ir", "Pt", "Au", "Hg", "Tl",
+    "Pb", "Bi", "Po", "At", "Rn", "Fr", "Ra", "Ac", "Th", "Pa", "U", "Np", "Pu", "Am", "Cm", "Bk",
+    "Cf", "Es", "Fm", "Md", "No", "Lr", "Rf", "Db", "Sg", "Bh", "Hs", "Mt", "Ds", "Rg", "Cn", "Nh",
+    "Fl", "Mc", "Lv", "Ts", "Og",
+];
+
 pub struct PeriodicTable {
     pub element_reprs: Vec<ElementRepr>,
 }
@@ -190,6 +310,14 @@ impl PeriodicTable {
 
         Self { element_reprs }
     }
+
+    pub fn element_repr(&self, element: Element) -> &ElementRepr {
+        &self.element_reprs[element as usize - 1]
+    }
+
+    pub fn set_element_repr(&mut self, element: Element, repr: ElementRepr) {
+        self.element_reprs[element as usize - 1] = repr;
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -201,3 +329,18 @@ pub struct ElementRepr {
 
 const_assert_eq!(mem::size_of::<ElementRepr>(), 16);
 unsafe impl AsBytes for ElementRepr {}
+
+impl ElementRepr {
+    pub fn new(color: Vec3, radius: f32) -> Self {
+        Self { color, radius }
+    }
+
+    pub fn color(&self) -> Vec3 {
+        self.color
+    }
+
+    /// The van der Waals radius, in angstroms.
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+}
@@ -26,6 +26,13 @@ layout(set = 0, binding = 1) readonly buffer PeriodicTable {
     Element elements[118];
 } periodic_table;
 
+layout(set = 0, binding = 2) uniform Settings {
+    bool color_by_atom;
+    uint clip_mode; // 0 is off, 1 clips one side of the plane, 2 keeps a slab around it
+    float slab_half_thickness;
+    vec4 clip_plane; // in world space: the normal, then the negated distance
+} settings;
+
 struct Atom {
     vec3 pos;
     uint kind;
@@ -37,6 +44,10 @@ layout(set = 1, binding = 1, std430) readonly buffer Atoms {
     Atom atoms[]; // this must be aligned to 16 bytes.
 };
 
+layout(set = 1, binding = 2, std430) readonly buffer AtomColors {
+    vec4 atom_colors[]; // the element color is used if alpha is zero
+};
+
 // struct Bivec {
 //     float xy;
 //     float xz;
@@ -77,11 +88,31 @@ const vec2 vertices[3] = {
 
 void main(void) {
     const Atom atom = atoms[gl_VertexIndex / 3];
+    if ((atom.kind & 0x80) != 0) {
+        // Hidden atoms collapse to a degenerate triangle, which isn't rasterized.
+        gl_Position = vec4(0.0);
+        return;
+    }
     element = periodic_table.elements[atom.kind & 0x7f];
+    if (settings.color_by_atom) {
+        const vec4 atom_color = atom_colors[gl_VertexIndex / 3];
+        if (atom_color.a > 0.0) {
+            element.color = atom_color.rgb;
+        }
+    }
     const vec2 vertex = element.radius * vertices[gl_VertexIndex % 3];
 
     const vec4 position = part_fragment_transform * vec4(atom.pos, 1.0);
 
+    if (settings.clip_mode != 0) {
+        const float side = dot(settings.clip_plane.xyz, position.xyz) + settings.clip_plane.w;
+        if ((settings.clip_mode == 1 && side < 0.0)
+            || (settings.clip_mode == 2 && abs(side) > settings.slab_half_thickness)) {
+            gl_Position = vec4(0.0);
+            return;
+        }
+    }
+
     const vec3 camera_right_worldspace = vec3(camera.view[0][0], camera.view[1][0], camera.view[2][0]);
     const vec3 camera_up_worldspace = vec3(camera.view[0][1], camera.view[1][1], camera.view[2][1]);
     const vec4 position_worldspace = vec4(
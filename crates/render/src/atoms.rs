@@ -2,17 +2,19 @@ use crate::{buffer_vec::BufferVec, FragmentId, GlobalRenderResources};
 use common::AsBytes;
 use periodic_table::Element;
 use std::mem::{self, MaybeUninit};
-use ultraviolet::Vec3;
+use ultraviolet::{Vec3, Vec4};
 
 /// Packed bit field
-/// | 0 .. 7 | ----------- | 7 .. 31 |
-///   ^ atomic number - 1    ^ unspecified
+/// | 0 .. 7 | ----------- | 7 | ---- | 8 .. 31 |
+///   ^ atomic number - 1   ^ hidden   ^ unspecified
 ///
 /// TODO: Try using a buffer as an atom radius lookup table.
 #[derive(Copy, Clone, PartialEq, Eq)]
 #[repr(transparent)]
 pub struct AtomKind(u32);
 impl AtomKind {
+    const HIDDEN: u32 = 1 << 7;
+
     pub fn new(element: Element) -> Self {
         Self(((element as u8 - 1) & 0b1111_111) as u32)
     }
@@ -22,6 +24,24 @@ impl AtomKind {
         Element::from_atomic_number(n)
             .unwrap_or_else(|| unreachable!("invalid atomic number in atom kind"))
     }
+
+    /// Keeps every other field, like visibility.
+    pub fn set_element(&mut self, element: Element) {
+        self.0 = (self.0 & !0b1111_111) | AtomKind::new(element).0;
+    }
+
+    /// Hidden atoms aren't drawn. This is purely a view setting.
+    pub fn is_visible(&self) -> bool {
+        self.0 & Self::HIDDEN == 0
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        if visible {
+            self.0 &= !Self::HIDDEN;
+        } else {
+            self.0 |= Self::HIDDEN;
+        }
+    }
 }
 
 #[derive(Copy, Clone, PartialEq)]
@@ -47,6 +67,8 @@ unsafe impl AsBytes for AtomBufferHeader {}
 pub struct Atoms {
     bind_group: wgpu::BindGroup,
     buffer: BufferVec<AtomBufferHeader, AtomRepr>,
+    // One per atom, only used when coloring by something other than element.
+    colors: wgpu::Buffer,
     number_of_atoms: usize,
 }
 
@@ -63,7 +85,7 @@ impl Atoms {
 
         let buffer = BufferVec::new_with_data(
             &gpu_resources.device,
-            wgpu::BufferUsage::STORAGE,
+            wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::COPY_DST,
             number_of_atoms as u64,
             |header, array| {
                 // header.write(AtomBufferHeader { fragment_id });
@@ -76,24 +98,13 @@ impl Atoms {
             },
         );
 
-        let bind_group = gpu_resources
-            .device
-            .create_bind_group(&wgpu::BindGroupDescriptor {
-                label: None,
-                layout: &gpu_resources.atom_bgl,
-                entries: &[wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Buffer {
-                        buffer: buffer.inner_buffer(),
-                        offset: 0,
-                        size: None,
-                    },
-                }],
-            });
+        let colors = create_colors_buffer(&gpu_resources.device, number_of_atoms);
+        let bind_group = create_bind_group(gpu_resources, &buffer, &colors);
 
         Self {
             bind_group,
             buffer,
+            colors,
             number_of_atoms,
         }
     }
@@ -111,28 +122,32 @@ impl Atoms {
             AtomBufferHeader { fragment_id }.as_bytes(),
         );
 
-        let bind_group = render_resources
-            .device
-            .create_bind_group(&wgpu::BindGroupDescriptor {
-                label: None,
-                layout: &render_resources.atom_bgl,
-                entries: &[wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Buffer {
-                        buffer: buffer.inner_buffer(),
-                        offset: 0,
-                        size: None,
-                    },
-                }],
-            });
+        // The fragment gets recolored once it's spawned.
+        let colors = create_colors_buffer(&render_resources.device, self.number_of_atoms);
+        let bind_group = create_bind_group(render_resources, &buffer, &colors);
 
         Self {
             bind_group,
             buffer,
+            colors,
             number_of_atoms: self.number_of_atoms,
         }
     }
 
+    /// Overwrites the atoms, which must be the same number as before.
+    pub fn write_atoms(&mut self, render_resources: &GlobalRenderResources, atoms: &[AtomRepr]) {
+        assert_eq!(atoms.len(), self.number_of_atoms);
+        self.buffer.write_partial_small(render_resources, 0, atoms);
+    }
+
+    /// There must be a color for every atom.
+    pub fn write_colors(&self, render_resources: &GlobalRenderResources, colors: &[Vec4]) {
+        assert_eq!(colors.len(), self.number_of_atoms);
+        render_resources
+            .queue
+            .write_buffer(&self.colors, 0, colors.as_bytes());
+    }
+
     pub fn bind_group(&self) -> &wgpu::BindGroup {
         &self.bind_group
     }
@@ -141,3 +156,69 @@ impl Atoms {
         self.number_of_atoms
     }
 }
+
+fn create_colors_buffer(device: &wgpu::Device, number_of_atoms: usize) -> wgpu::Buffer {
+    // Buffers are zeroed, which means every atom uses its element color.
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: (number_of_atoms * mem::size_of::<Vec4>()) as u64,
+        usage: wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_bind_group(
+    gpu_resources: &GlobalRenderResources,
+    buffer: &BufferVec<AtomBufferHeader, AtomRepr>,
+    colors: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    gpu_resources
+        .device
+        .create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &gpu_resources.atom_bgl,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer {
+                        buffer: buffer.inner_buffer(),
+                        offset: 0,
+                        size: None,
+                    },
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer {
+                        buffer: colors,
+                        offset: 0,
+                        size: None,
+                    },
+                },
+            ],
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_element_keeps_flags() {
+        let mut kind = AtomKind::new(Element::Carbon);
+        kind.set_visible(false);
+        kind.set_element(Element::Oxygen);
+
+        assert_eq!(kind.element(), Element::Oxygen);
+        assert!(!kind.is_visible());
+    }
+
+    #[test]
+    fn set_visible_keeps_element() {
+        let mut kind = AtomKind::new(Element::Nitrogen);
+        kind.set_visible(false);
+        kind.set_visible(true);
+
+        assert_eq!(kind.element(), Element::Nitrogen);
+        assert!(kind.is_visible());
+    }
+}
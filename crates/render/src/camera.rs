@@ -1,7 +1,7 @@
 use crate::bind_groups::AsBindingResource;
 use common::{AsBytes, InputEvent};
 use std::mem;
-use ultraviolet::Mat4;
+use ultraviolet::{Mat4, Vec3, Vec4};
 use winit::dpi::PhysicalSize;
 
 #[derive(Clone, Default)]
@@ -14,6 +14,67 @@ pub struct CameraRepr {
 
 unsafe impl AsBytes for CameraRepr {}
 
+impl CameraRepr {
+    /// The direction the camera is looking, in world space.
+    pub fn forward(&self) -> Vec3 {
+        // The camera looks down -z in view space.
+        -Vec3::new(
+            self.view.cols[0][2],
+            self.view.cols[1][2],
+            self.view.cols[2][2],
+        )
+    }
+
+    /// The direction that's to the right on screen, in world space.
+    pub fn right(&self) -> Vec3 {
+        Vec3::new(
+            self.view.cols[0][0],
+            self.view.cols[1][0],
+            self.view.cols[2][0],
+        )
+    }
+
+    /// The direction that's up on screen, in world space.
+    pub fn up(&self) -> Vec3 {
+        Vec3::new(
+            self.view.cols[0][1],
+            self.view.cols[1][1],
+            self.view.cols[2][1],
+        )
+    }
+}
+
+/// The planes bounding everything a camera can see, for culling.
+pub(crate) struct Frustum {
+    // Each plane is `(normal, distance)`, with the normal pointing into the frustum.
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from the rows of a projection-view matrix (Gribb and Hartmann).
+    pub fn from_projection_view(m: Mat4) -> Self {
+        let row = |i: usize| Vec4::new(m.cols[0][i], m.cols[1][i], m.cols[2][i], m.cols[3][i]);
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+        let mut planes = [w + x, w - x, w + y, w - y, z, w - z];
+        for plane in &mut planes {
+            let length = plane.xyz().mag();
+            // An infinite far plane has no normal and culls nothing.
+            if length > 0.0 {
+                *plane /= length;
+            }
+        }
+
+        Self { planes }
+    }
+
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.xyz().dot(center) + plane.w >= -radius)
+    }
+}
+
 pub trait Camera {
     fn resize(&mut self, aspect: f32, fov: f32, near: f32);
     fn update(&mut self, event: InputEvent) -> bool;
@@ -97,6 +158,18 @@ impl RenderCamera {
         // });
     }
 
+    /// Returns `None` if no camera is set.
+    pub fn repr(&self) -> Option<CameraRepr> {
+        self.camera.as_ref().map(|camera| camera.repr())
+    }
+
+    /// Returns `None` if no camera is set.
+    pub(crate) fn frustum(&self) -> Option<Frustum> {
+        self.camera
+            .as_ref()
+            .map(|camera| Frustum::from_projection_view(camera.repr().projection_view))
+    }
+
     #[must_use = "returns bool indicating whether a camera is currently set or not"]
     pub(crate) fn upload(&mut self, queue: &wgpu::Queue) -> bool {
         if let Some(camera) = self.camera.as_mut() {
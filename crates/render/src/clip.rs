@@ -0,0 +1,38 @@
+//! Hiding the atoms on one side of a plane, to see inside bulk structures.
+
+use ultraviolet::{Vec3, Vec4};
+
+/// Atoms are clipped whole, by their centers, since a cut billboard would have no inside to show.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ClipPlane {
+    /// Atoms on the side this points toward stay visible. Must be normalized.
+    pub normal: Vec3,
+    /// How far the plane is from the origin along `normal`, in angstroms.
+    pub distance: f32,
+    /// Keeps only the atoms in a slab this thick around the plane, clipping both sides.
+    pub slab: Option<f32>,
+}
+
+impl ClipPlane {
+    /// A plane through `point` that hides everything on the side `direction` points away from.
+    ///
+    /// Passing the camera's forward direction hides everything between the camera and `point`.
+    pub fn facing(direction: Vec3, point: Vec3) -> Self {
+        let normal = direction.normalized();
+        Self {
+            normal,
+            distance: normal.dot(point),
+            slab: None,
+        }
+    }
+
+    /// Moves the plane along its normal.
+    pub fn offset_by(&mut self, delta: f32) {
+        self.distance += delta;
+    }
+
+    /// The plane equation, with the normal in `xyz` and the negated distance in `w`.
+    pub(crate) fn equation(&self) -> Vec4 {
+        Vec4::new(self.normal.x, self.normal.y, self.normal.z, -self.distance)
+    }
+}
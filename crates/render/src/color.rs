@@ -0,0 +1,216 @@
+//! Coloring atoms by something other than their element.
+
+use crate::world::Fragment;
+use ultraviolet::{Vec3, Vec4};
+
+/// Evenly spaced colors that scalars in `0.0..=1.0` are interpolated between.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Colormap {
+    stops: Vec<Vec3>,
+}
+
+impl Colormap {
+    /// Takes linear rgb colors. There must be at least two.
+    pub fn new(stops: Vec<Vec3>) -> Self {
+        assert!(stops.len() >= 2, "a colormap needs at least two colors");
+        Self { stops }
+    }
+
+    /// Negative values are blue and positive values are red, which is the usual way to
+    /// show charges.
+    pub fn blue_white_red() -> Self {
+        Self::new(vec![
+            Vec3::new(0.0, 0.1, 0.8),
+            Vec3::new(1.0, 1.0, 1.0),
+            Vec3::new(0.8, 0.0, 0.0),
+        ])
+    }
+
+    /// Perceptually uniform, from dark purple to yellow, for values that only go one way.
+    pub fn viridis() -> Self {
+        Self::new(vec![
+            Vec3::new(0.0578, 0.0003, 0.0887),
+            Vec3::new(0.0437, 0.0844, 0.2582),
+            Vec3::new(0.0152, 0.2831, 0.2623),
+            Vec3::new(0.1119, 0.5841, 0.1221),
+            Vec3::new(0.9823, 0.7991, 0.0185),
+        ])
+    }
+
+    /// Distinct colors for labeling things, rather than for values. With a range of
+    /// `(0.0, 7.0)`, each whole number from zero to seven gets one of the colors.
+    pub fn categorical() -> Self {
+        Self::new(vec![
+            Vec3::new(0.0137, 0.1845, 0.4564), // blue
+            Vec3::new(1.0, 0.2122, 0.0044),    // orange
+            Vec3::new(0.0252, 0.3515, 0.0252), // green
+            Vec3::new(0.6724, 0.0203, 0.0212), // red
+            Vec3::new(0.2961, 0.1356, 0.5089), // purple
+            Vec3::new(0.2623, 0.0931, 0.0704), // brown
+            Vec3::new(0.7682, 0.1845, 0.5395), // pink
+            Vec3::new(0.0086, 0.5149, 0.624),  // cyan
+        ])
+    }
+
+    pub fn sample(&self, t: f32) -> Vec3 {
+        let t = if t.is_nan() { 0.5 } else { t.max(0.0).min(1.0) };
+        let position = t * (self.stops.len() - 1) as f32;
+        let index = (position as usize).min(self.stops.len() - 2);
+        let fraction = position - index as f32;
+
+        self.stops[index] * (1.0 - fraction) + self.stops[index + 1] * fraction
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColorMode {
+    /// The element colors in the periodic table.
+    Element,
+    /// Maps the scalars set by `Fragment::set_scalars` through a colormap.
+    ///
+    /// Fragments without scalars keep their element colors.
+    Scalar {
+        colormap: Colormap,
+        /// The scalars at each end of the colormap, or `None` to fit every scalar.
+        range: Option<(f32, f32)>,
+    },
+}
+
+impl Default for ColorMode {
+    fn default() -> Self {
+        ColorMode::Element
+    }
+}
+
+impl ColorMode {
+    /// The range that scalars are mapped from, if this colors by scalars.
+    pub(crate) fn scalar_range<'a, I>(&self, fragments: I) -> Option<(f32, f32)>
+    where
+        I: IntoIterator<Item = &'a Fragment>,
+    {
+        match self {
+            ColorMode::Element => None,
+            ColorMode::Scalar {
+                range: Some(range), ..
+            } => Some(*range),
+            ColorMode::Scalar { range: None, .. } => {
+                let (min, max) = fragments
+                    .into_iter()
+                    .filter_map(Fragment::scalars)
+                    .flatten()
+                    .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &scalar| {
+                        (min.min(scalar), max.max(scalar))
+                    });
+
+                if min <= max {
+                    Some((min, max))
+                } else {
+                    Some((0.0, 1.0))
+                }
+            }
+        }
+    }
+
+    /// Per-atom colors for the shader. An alpha of zero means the element color is used.
+    pub(crate) fn fragment_colors(&self, fragment: &Fragment, range: (f32, f32)) -> Vec<Vec4> {
+        let (colormap, scalars) = match (self, fragment.scalars()) {
+            (ColorMode::Scalar { colormap, .. }, Some(scalars)) => (colormap, scalars),
+            _ => return vec![Vec4::zero(); fragment.atoms().len()],
+        };
+
+        let (min, max) = range;
+        scalars
+            .iter()
+            .map(|&scalar| {
+                let t = if max > min {
+                    (scalar - min) / (max - min)
+                } else {
+                    0.5
+                };
+                let color = colormap.sample(t);
+                Vec4::new(color.x, color.y, color.z, 1.0)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atoms::{AtomKind, AtomRepr};
+    use periodic_table::Element;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).mag() < 1.0e-6, "{:?} != {:?}", a, b);
+    }
+
+    fn fragment(count: usize) -> Fragment {
+        Fragment::new((0..count).map(|i| AtomRepr {
+            pos: Vec3::new(i as f32, 0.0, 0.0),
+            kind: AtomKind::new(Element::Carbon),
+        }))
+    }
+
+    #[test]
+    fn sample_interpolates_between_stops() {
+        let colormap = Colormap::blue_white_red();
+
+        assert_close(colormap.sample(0.0), Vec3::new(0.0, 0.1, 0.8));
+        assert_close(colormap.sample(0.5), Vec3::one());
+        assert_close(colormap.sample(1.0), Vec3::new(0.8, 0.0, 0.0));
+        assert_close(colormap.sample(0.25), Vec3::new(0.5, 0.55, 0.9));
+    }
+
+    #[test]
+    fn sample_clamps_out_of_range_values() {
+        let colormap = Colormap::viridis();
+
+        assert_close(colormap.sample(-1.0), colormap.sample(0.0));
+        assert_close(colormap.sample(2.0), colormap.sample(1.0));
+        assert_close(colormap.sample(f32::NAN), colormap.sample(0.5));
+    }
+
+    #[test]
+    fn scalars_map_onto_the_range() {
+        let mut fragment = fragment(3);
+        fragment.set_scalars(vec![10.0, 15.0, 20.0]);
+        let mode = ColorMode::Scalar {
+            colormap: Colormap::blue_white_red(),
+            range: None,
+        };
+
+        let range = mode.scalar_range(Some(&fragment)).unwrap();
+        assert_eq!(range, (10.0, 20.0));
+
+        let colors = mode.fragment_colors(&fragment, range);
+        assert_eq!(colors[0], Vec4::new(0.0, 0.1, 0.8, 1.0));
+        assert_eq!(colors[1], Vec4::new(1.0, 1.0, 1.0, 1.0));
+        assert_eq!(colors[2], Vec4::new(0.8, 0.0, 0.0, 1.0));
+    }
+
+    // The colors are written into the buffer that the fragment was uploaded with, which only
+    // works if there's always exactly one per atom.
+    #[test]
+    fn every_mode_has_a_color_per_atom() {
+        let mut fragment = fragment(5);
+        let scalar = ColorMode::Scalar {
+            colormap: Colormap::categorical(),
+            range: Some((0.0, 7.0)),
+        };
+
+        let colors = ColorMode::Element.fragment_colors(&fragment, (0.0, 1.0));
+        assert_eq!(colors, vec![Vec4::zero(); 5]);
+
+        // Switching before there are any scalars keeps the element colors.
+        let colors = scalar.fragment_colors(&fragment, (0.0, 7.0));
+        assert_eq!(colors, vec![Vec4::zero(); 5]);
+
+        fragment.set_scalars(vec![0.0, 1.0, 2.0, 3.0, 4.0]);
+        let colors = scalar.fragment_colors(&fragment, (0.0, 7.0));
+        assert_eq!(colors.len(), 5);
+        assert!(colors.iter().all(|color| color.w == 1.0));
+
+        let colors = ColorMode::Element.fragment_colors(&fragment, (0.0, 1.0));
+        assert_eq!(colors, vec![Vec4::zero(); 5]);
+    }
+}
@@ -1,6 +1,12 @@
 pub use crate::{
     atoms::{AtomKind, AtomRepr},
     camera::{Camera, CameraRepr, RenderCamera},
+    clip::ClipPlane,
+    color::{ColorMode, Colormap},
+    mesh::{vdw_surface, Mesh},
+    passes::SsaoOptions,
+    stats::FrameStats,
+    utils::BoundingBox,
     world::{Fragment, FragmentId, Part, PartId, World},
 };
 use crate::{bind_groups::AsBindingResource as _, buffer_vec::BufferVec};
@@ -14,11 +20,18 @@ use std::{
 use wgpu::util::DeviceExt as _;
 use winit::{dpi::PhysicalSize, window::Window};
 
+#[macro_use]
+mod shaders;
+
 mod atoms;
 mod bind_groups;
 mod buffer_vec;
 mod camera;
+mod clip;
+mod color;
+mod mesh;
 mod passes;
+pub mod stats;
 mod utils;
 mod world;
 
@@ -54,20 +67,108 @@ pub struct GlobalRenderResources {
 pub struct RenderOptions {
     pub fxaa: Option<()>,         // to be filled out with fxaa configuration options
     pub attempt_gpu_driven: bool, // Will attempt to drive rendering, culling, etc on gpu if supported by the adapter
+    /// `None` turns ambient occlusion off, which is cheaper on slow gpus.
+    pub ssao: Option<SsaoOptions>,
+    /// Prefer the first adapter whose name contains this (case-insensitively).
+    pub gpu: Option<String>,
+}
+
+/// Textures larger than this aren't guaranteed to work on every adapter.
+pub const MAX_TEXTURE_DIMENSION: u32 = 8192;
+
+/// Orders adapters by preference: the one named by `preferred`, then discrete gpus, then the rest.
+///
+/// Enumerated adapters aren't checked against the surface, and on laptops with two gpus the
+/// discrete one often can't present to the window. So when there's a window and no gpu was
+/// asked for, the adapter that wgpu picks for the surface is tried first.
+async fn adapter_candidates(
+    instance: &wgpu::Instance,
+    surface: Option<&wgpu::Surface>,
+    preferred: Option<&str>,
+) -> Vec<wgpu::Adapter> {
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut adapters: Vec<_> = instance
+        .enumerate_adapters(wgpu::BackendBit::PRIMARY)
+        .collect();
+    #[cfg(target_arch = "wasm32")]
+    let mut adapters = Vec::new();
+
+    for adapter in &adapters {
+        let info = adapter.get_info();
+        log::info!(
+            "found {} ({:?}, {:?}) with {:?}",
+            info.name,
+            info.backend,
+            info.device_type,
+            adapter.limits()
+        );
+    }
+
+    let preferred = preferred.map(str::to_lowercase);
+    let matches = |adapter: &wgpu::Adapter| match &preferred {
+        Some(preferred) => adapter.get_info().name.to_lowercase().contains(preferred),
+        None => false,
+    };
+
+    if let Some(preferred) = &preferred {
+        if !adapters.iter().any(matches) {
+            log::warn!("no adapter matches `{}`", preferred);
+        }
+    }
+
+    adapters.sort_by_cached_key(|adapter| {
+        (
+            !matches(adapter),
+            adapter.get_info().device_type != wgpu::DeviceType::DiscreteGpu,
+        )
+    });
+
+    // This is the only option on the web. Otherwise it's a last resort in case none of the
+    // enumerated adapters work, unless it's known to be able to present to the window.
+    if let Some(adapter) = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: surface,
+        })
+        .await
+    {
+        if surface.is_some() && preferred.is_none() {
+            adapters.insert(0, adapter);
+        } else {
+            adapters.push(adapter);
+        }
+    }
+
+    adapters
+}
+
+enum RenderTarget {
+    Window {
+        surface: wgpu::Surface,
+        swap_chain_desc: wgpu::SwapChainDescriptor,
+        swap_chain: wgpu::SwapChain,
+    },
+    /// Used for headless rendering, where there is no window to present to.
+    Texture {
+        texture: wgpu::Texture,
+        view: wgpu::TextureView,
+    },
 }
 
 pub struct Renderer {
-    swap_chain_desc: wgpu::SwapChainDescriptor,
-    swap_chain: wgpu::SwapChain,
-    surface: wgpu::Surface,
+    target: RenderTarget,
     render_resources: Arc<GlobalRenderResources>,
+    adapter_info: wgpu::AdapterInfo,
     size: PhysicalSize<u32>,
 
     periodic_table: PeriodicTable,
     periodic_table_buffer: wgpu::Buffer,
+    // The largest radius of any element, which fragments are padded by when culling.
+    max_atom_radius: f32,
     camera: RenderCamera,
 
     molecular_pass: passes::MolecularPass,
+    ssao_pass: passes::SsaoPass,
     fxaa_pass: passes::FxaaPass,
     blit_pass: passes::BlitPass,
 
@@ -76,51 +177,101 @@ pub struct Renderer {
 
     gpu_driven_rendering: bool,
     options: RenderOptions,
+
+    stats: FrameStats,
+
+    color_mode: ColorMode,
+    color_mode_changed: bool,
+
+    clip_plane: Option<ClipPlane>,
+
+    #[cfg(feature = "hot-reload")]
+    shader_watcher: Option<shaders::ShaderWatcher>,
 }
 
 impl Renderer {
     pub async fn new(
         window: &Window,
         options: RenderOptions,
-    ) -> (Self, Arc<GlobalRenderResources>) {
+    ) -> Result<(Self, Arc<GlobalRenderResources>), String> {
         let size = window.inner_size();
         let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
         let surface = unsafe { instance.create_surface(window) };
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                compatible_surface: Some(&surface),
-            })
-            .await
-            .expect("failed to find an appropriate adapter");
+        Self::new_with_surface(&instance, Some(surface), size, options).await
+    }
 
+    /// Creates a renderer that draws into an offscreen texture instead of a window.
+    ///
+    /// Use `Renderer::read_frame` to retrieve what was rendered. This fails instead of panicking
+    /// if there's no usable gpu, since it's usually run from scripts.
+    pub async fn new_headless(
+        size: PhysicalSize<u32>,
+        options: RenderOptions,
+    ) -> Result<(Self, Arc<GlobalRenderResources>), String> {
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+
+        Self::new_with_surface(&instance, None, size, options).await
+    }
+
+    async fn new_with_surface(
+        instance: &wgpu::Instance,
+        surface: Option<wgpu::Surface>,
+        size: PhysicalSize<u32>,
+        options: RenderOptions,
+    ) -> Result<(Self, Arc<GlobalRenderResources>), String> {
         let gpu_driven_features =
             wgpu::Features::DEVICE_BUFFER_ADDRESS | wgpu::Features::MULTI_DRAW_INDIRECT_COUNT;
-        let gpu_driven_rendering;
-
-        let requested_features =
-            if options.attempt_gpu_driven && adapter.features().contains(gpu_driven_features) {
-                // we can do culling and draw calls directly on gpu
-                // Hopefully massive performance boost
-                gpu_driven_rendering = true;
-                gpu_driven_features
-            } else {
-                gpu_driven_rendering = false;
-                wgpu::Features::empty()
-            };
 
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    features: requested_features,
-                    limits: wgpu::Limits::default(),
-                    shader_validation: true,
-                },
-                None,
-            )
-            .await
-            .expect("failed to create device");
+        let mut selected = None;
+        for adapter in adapter_candidates(instance, surface.as_ref(), options.gpu.as_deref()).await
+        {
+            let gpu_driven_rendering;
+
+            let requested_features =
+                if options.attempt_gpu_driven && adapter.features().contains(gpu_driven_features) {
+                    // we can do culling and draw calls directly on gpu
+                    // Hopefully massive performance boost
+                    gpu_driven_rendering = true;
+                    gpu_driven_features
+                } else {
+                    gpu_driven_rendering = false;
+                    wgpu::Features::empty()
+                };
+
+            let device = adapter
+                .request_device(
+                    &wgpu::DeviceDescriptor {
+                        features: requested_features,
+                        limits: wgpu::Limits::default(),
+                        shader_validation: true,
+                    },
+                    None,
+                )
+                .await;
+
+            match device {
+                Ok((device, queue)) => {
+                    selected = Some((adapter, device, queue, gpu_driven_rendering));
+                    break;
+                }
+                // Try the next one instead.
+                Err(e) => log::warn!(
+                    "failed to create a device on {}: {:?}",
+                    adapter.get_info().name,
+                    e
+                ),
+            }
+        }
+
+        let (adapter, device, queue, gpu_driven_rendering) =
+            selected.ok_or_else(|| "failed to create a device on any adapter".to_string())?;
+        let adapter_info = adapter.get_info();
+        log::info!(
+            "rendering with {} ({:?})",
+            adapter_info.name,
+            adapter_info.backend
+        );
 
         let camera = RenderCamera::new_empty(&device, 0.7, 0.1);
 
@@ -129,31 +280,55 @@ impl Renderer {
         let periodic_table_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
             contents: periodic_table.element_reprs.as_bytes(),
-            usage: wgpu::BufferUsage::STORAGE,
+            usage: wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::COPY_DST,
         });
 
-        let swap_chain_desc = wgpu::SwapChainDescriptor {
-            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
-            format: SWAPCHAIN_FORMAT,
-            width: size.width,
-            height: size.height,
-            present_mode: wgpu::PresentMode::Mailbox,
+        let target = match surface {
+            Some(surface) => {
+                let swap_chain_desc = wgpu::SwapChainDescriptor {
+                    usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+                    format: SWAPCHAIN_FORMAT,
+                    width: size.width,
+                    height: size.height,
+                    present_mode: wgpu::PresentMode::Mailbox,
+                };
+
+                let swap_chain = device.create_swap_chain(&surface, &swap_chain_desc);
+
+                RenderTarget::Window {
+                    surface,
+                    swap_chain_desc,
+                    swap_chain,
+                }
+            }
+            None => RenderTarget::new_texture(&device, size),
         };
 
-        let swap_chain = device.create_swap_chain(&surface, &swap_chain_desc);
-
         let atom_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: None,
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 1,
-                visibility: wgpu::ShaderStage::VERTEX,
-                ty: wgpu::BindingType::StorageBuffer {
-                    dynamic: false,
-                    min_binding_size: None,
-                    readonly: false,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::VERTEX,
+                    ty: wgpu::BindingType::StorageBuffer {
+                        dynamic: false,
+                        min_binding_size: None,
+                        readonly: false,
+                    },
+                    count: None,
+                },
+                // per-atom colors
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::VERTEX,
+                    ty: wgpu::BindingType::StorageBuffer {
+                        dynamic: false,
+                        min_binding_size: None,
+                        readonly: true,
+                    },
+                    count: None,
                 },
-                count: None,
-            }],
+            ],
         });
         let linear_sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
 
@@ -171,26 +346,39 @@ impl Renderer {
             size,
             gpu_driven_rendering,
         );
-        let (fxaa_pass, fxaa_texture) =
-            passes::FxaaPass::new(&render_resources, size, &color_texture);
+        let (ssao_pass, ssao_texture) = passes::SsaoPass::new(
+            &render_resources,
+            size,
+            camera.as_binding_resource(),
+            &color_texture,
+            molecular_pass.depth_texture(),
+            options.ssao.unwrap_or_default(),
+        );
+        let fxaa_input = if options.ssao.is_some() {
+            &ssao_texture
+        } else {
+            &color_texture
+        };
+        let (fxaa_pass, fxaa_texture) = passes::FxaaPass::new(&render_resources, size, fxaa_input);
         let blit_pass = passes::BlitPass::new(&render_resources, &fxaa_texture);
 
         let fragment_transforms =
             BufferVec::new(&render_resources.device, wgpu::BufferUsage::VERTEX, ());
 
-        (
+        Ok((
             Self {
-                swap_chain_desc,
-                swap_chain,
-                surface,
+                target,
                 render_resources: Arc::clone(&render_resources),
+                adapter_info,
                 size,
 
+                max_atom_radius: max_atom_radius(&periodic_table),
                 periodic_table,
                 periodic_table_buffer,
                 camera,
 
                 molecular_pass,
+                ssao_pass,
                 fxaa_pass,
                 blit_pass,
 
@@ -199,32 +387,73 @@ impl Renderer {
 
                 gpu_driven_rendering,
                 options,
+
+                stats: FrameStats::default(),
+
+                color_mode: ColorMode::default(),
+                color_mode_changed: false,
+
+                clip_plane: None,
+
+                #[cfg(feature = "hot-reload")]
+                shader_watcher: shaders::ShaderWatcher::new()
+                    .map_err(|e| log::warn!("shaders won't be reloaded: {}", e))
+                    .ok(),
             },
             render_resources,
-        )
+        ))
     }
 
     pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
         self.size = new_size;
-        self.swap_chain_desc.width = new_size.width;
-        self.swap_chain_desc.height = new_size.height;
 
-        self.swap_chain = self
-            .render_resources
-            .device
-            .create_swap_chain(&self.surface, &self.swap_chain_desc);
+        match &mut self.target {
+            RenderTarget::Window {
+                surface,
+                swap_chain_desc,
+                swap_chain,
+            } => {
+                swap_chain_desc.width = new_size.width;
+                swap_chain_desc.height = new_size.height;
+
+                *swap_chain = self
+                    .render_resources
+                    .device
+                    .create_swap_chain(surface, swap_chain_desc);
+            }
+            RenderTarget::Texture { .. } => {
+                self.target = RenderTarget::new_texture(&self.render_resources.device, new_size);
+            }
+        }
 
-        let (color_texture, _normals_texture) =
+        let (color_texture, depth_texture, _normals_texture) =
             self.molecular_pass.update(&self.render_resources, new_size);
+        let ssao_texture = self.ssao_pass.update(
+            &self.render_resources,
+            self.camera.as_binding_resource(),
+            color_texture,
+            depth_texture,
+            new_size,
+        );
+        let fxaa_input = if self.options.ssao.is_some() {
+            ssao_texture
+        } else {
+            color_texture
+        };
         let fxaa_texture = self
             .fxaa_pass
-            .update(&self.render_resources, color_texture, new_size);
+            .update(&self.render_resources, fxaa_input, new_size);
         self.blit_pass.update(&self.render_resources, fxaa_texture);
 
         self.camera.resize(new_size);
     }
 
     pub fn render(&mut self, world: &mut World, interactions: &Interactions) {
+        let start = instant::Instant::now();
+
+        #[cfg(feature = "hot-reload")]
+        self.reload_shaders();
+
         let mut encoder = self
             .render_resources
             .device
@@ -237,30 +466,66 @@ impl Renderer {
             return;
         }
 
+        // The transforms of removed fragments are left where they are in the buffer, since
+        // nothing else is ever removed from it.
+        for fragment_id in world.removed_fragments.drain(..) {
+            self.per_fragment.remove(&fragment_id);
+        }
         self.upload_new_transforms(&mut encoder, world);
         self.update_transforms(&mut encoder, world);
+        for fragment in world.fragments.values_mut() {
+            fragment.sync_atoms(&self.render_resources);
+        }
+        self.update_colors(world);
 
-        let frame = self
-            .swap_chain
-            .get_current_frame()
-            .map(|mut frame| {
+        let frame = match &mut self.target {
+            RenderTarget::Window { swap_chain, .. } => Some(swap_chain.get_current_frame()),
+            RenderTarget::Texture { .. } => None,
+        };
+        let frame = match frame {
+            Some(Ok(frame)) => {
                 if frame.suboptimal {
-                    // try again
-                    frame = self
-                        .swap_chain
-                        .get_current_frame()
-                        .expect("could not retrieve swapchain on second try");
-                    if frame.suboptimal {
-                        log::warn!("suboptimal swapchain frame");
-                    }
+                    log::warn!("suboptimal swapchain frame");
                 }
-                frame
-            })
-            .expect("failed to get next swapchain");
+                Some(frame)
+            }
+            Some(Err(e)) => {
+                // The transform uploads were already recorded, so they still need to be submitted.
+                self.render_resources.queue.submit(Some(encoder.finish()));
+                self.recover_from_swap_chain_error(e);
+                return;
+            }
+            None => None,
+        };
+        let output = match (&frame, &self.target) {
+            (Some(frame), _) => &frame.output.view,
+            (None, RenderTarget::Texture { view, .. }) => view,
+            (None, RenderTarget::Window { .. }) => unreachable!(),
+        };
+
+        // Fragments of hidden parts, and fragments entirely outside the view, are skipped. This
+        // is conservative: the margin is the largest radius of any element, rather than of each
+        // fragment's own atoms.
+        let frustum = self.camera.frustum();
+        let margin = self.max_atom_radius;
+        let (parts, per_fragment) = (&world.parts, &self.per_fragment);
+        let visible_fragments: Vec<&Fragment> = world
+            .fragments()
+            .filter(
+                |fragment| match (&frustum, per_fragment.get(&fragment.id())) {
+                    (_, Some((part_id, _))) if !parts[part_id].is_visible() => false,
+                    (Some(frustum), Some((part_id, _))) => {
+                        let (center, radius) = fragment.bounding_sphere(&parts[part_id]);
+                        frustum.intersects_sphere(center, radius + margin)
+                    }
+                    _ => true,
+                },
+            )
+            .collect();
 
         self.molecular_pass.run(
             &mut encoder,
-            world.fragments(),
+            visible_fragments.iter().copied(),
             self.fragment_transforms.inner_buffer(),
             &self.per_fragment,
         );
@@ -276,6 +541,12 @@ impl Renderer {
         // }
 
         // run compute passes
+        if self.options.ssao.is_some() {
+            // This is a separate pass, because fxaa samples the texture this writes to.
+            let mut cpass = encoder.begin_compute_pass();
+
+            self.ssao_pass.run(&mut cpass);
+        }
         {
             let mut cpass = encoder.begin_compute_pass();
 
@@ -283,9 +554,52 @@ impl Renderer {
         }
 
         // blit to screen
-        self.blit_pass.run(&mut encoder, &frame.output.view);
+        self.blit_pass.run(&mut encoder, output);
 
         self.render_resources.queue.submit(Some(encoder.finish()));
+
+        let mut frame_stats = stats::Frame::default();
+        for fragment in world.fragments() {
+            frame_stats.fragments += 1;
+            frame_stats.atoms += fragment.atoms().len();
+        }
+        for fragment in visible_fragments {
+            if fragment.gpu_atoms().is_some() {
+                frame_stats.visible_fragments += 1;
+                frame_stats.visible_atoms += fragment.atoms().len();
+            }
+        }
+        frame_stats.encode_time = start.elapsed();
+        self.stats.push(start, frame_stats);
+    }
+
+    /// Skips the current frame either way.
+    fn recover_from_swap_chain_error(&mut self, error: wgpu::SwapChainError) {
+        match error {
+            wgpu::SwapChainError::Timeout => log::warn!("timed out getting the next frame"),
+            wgpu::SwapChainError::Outdated | wgpu::SwapChainError::Lost => {
+                log::info!("recreating the swapchain, because it was {:?}", error);
+                self.resize(self.size);
+            }
+            // There's no recovering from this, so let the crash handler report it.
+            wgpu::SwapChainError::OutOfMemory => panic!("ran out of memory getting the next frame"),
+        }
+    }
+
+    #[cfg(feature = "hot-reload")]
+    fn reload_shaders(&mut self) {
+        let changed = match &mut self.shader_watcher {
+            Some(watcher) => watcher.poll(),
+            None => return,
+        };
+
+        for name in changed {
+            self.molecular_pass
+                .reload_shader(&self.render_resources, &name);
+            self.ssao_pass.reload_shader(&self.render_resources, &name);
+            self.fxaa_pass.reload_shader(&self.render_resources, &name);
+            self.blit_pass.reload_shader(&self.render_resources, &name);
+        }
     }
 
     /// Immediately calls resize on the supplied camera.
@@ -297,18 +611,179 @@ impl Renderer {
         &mut self.camera
     }
 
+    pub fn adapter_info(&self) -> &wgpu::AdapterInfo {
+        &self.adapter_info
+    }
+
+    pub fn color_mode(&self) -> &ColorMode {
+        &self.color_mode
+    }
+
+    pub fn set_color_mode(&mut self, color_mode: ColorMode) {
+        if color_mode != self.color_mode {
+            self.color_mode = color_mode;
+            self.color_mode_changed = true;
+        }
+    }
+
+    pub fn periodic_table(&self) -> &PeriodicTable {
+        &self.periodic_table
+    }
+
+    /// Changes the color and radius of each element.
+    pub fn set_periodic_table(&mut self, periodic_table: PeriodicTable) {
+        self.render_resources.queue.write_buffer(
+            &self.periodic_table_buffer,
+            0,
+            periodic_table.element_reprs.as_bytes(),
+        );
+        self.max_atom_radius = max_atom_radius(&periodic_table);
+        self.periodic_table = periodic_table;
+    }
+
+    pub fn clip_plane(&self) -> Option<&ClipPlane> {
+        self.clip_plane.as_ref()
+    }
+
+    /// Hides the atoms on the clipped side of `clip_plane`, or shows everything with `None`.
+    pub fn set_clip_plane(&mut self, clip_plane: Option<ClipPlane>) {
+        if clip_plane != self.clip_plane {
+            self.molecular_pass
+                .set_clip_plane(&self.render_resources, clip_plane.as_ref());
+            self.clip_plane = clip_plane;
+        }
+    }
+
+    pub fn ssao(&self) -> Option<SsaoOptions> {
+        self.options.ssao
+    }
+
+    /// Changes the ambient occlusion settings, or turns it off with `None`.
+    pub fn set_ssao(&mut self, ssao: Option<SsaoOptions>) {
+        if let Some(options) = ssao {
+            self.ssao_pass.set_options(&self.render_resources, options);
+        }
+
+        let toggled = ssao.is_some() != self.options.ssao.is_some();
+        self.options.ssao = ssao;
+
+        // Fxaa reads from whichever pass ran last.
+        if toggled {
+            let fxaa_input = if ssao.is_some() {
+                self.ssao_pass.texture()
+            } else {
+                self.molecular_pass.color_texture()
+            };
+            let fxaa_texture = self
+                .fxaa_pass
+                .update(&self.render_resources, fxaa_input, self.size);
+            self.blit_pass.update(&self.render_resources, fxaa_texture);
+        }
+    }
+
+    pub fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    pub fn stats_mut(&mut self) -> &mut FrameStats {
+        &mut self.stats
+    }
+
+    /// Reads back the most recently rendered frame as tightly packed, 8-bit RGBA pixels.
+    ///
+    /// Returns `None` if this renderer presents to a window.
+    pub async fn read_frame(&self) -> Option<Vec<u8>> {
+        let texture = match &self.target {
+            RenderTarget::Texture { texture, .. } => texture,
+            RenderTarget::Window { .. } => return None,
+        };
+
+        let device = &self.render_resources.device;
+        let (width, height) = (self.size.width, self.size.height);
+
+        let unpadded_bytes_per_row = width * 4;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = (unpadded_bytes_per_row + align - 1) / align * align;
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: padded_bytes_per_row as u64 * height as u64,
+            usage: wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        encoder.copy_texture_to_buffer(
+            wgpu::TextureCopyView {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::BufferCopyView {
+                buffer: &buffer,
+                layout: wgpu::TextureDataLayout {
+                    offset: 0,
+                    bytes_per_row: padded_bytes_per_row,
+                    rows_per_image: height,
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+        );
+
+        self.render_resources.queue.submit(Some(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let map_future = slice.map_async(wgpu::MapMode::Read);
+        device.poll(wgpu::Maintain::Wait);
+        map_future.await.ok()?;
+
+        let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+        {
+            let mapped = slice.get_mapped_range();
+            for row in mapped.chunks(padded_bytes_per_row as usize) {
+                // The color texture is BGRA.
+                for bgra in row[..unpadded_bytes_per_row as usize].chunks(4) {
+                    pixels.extend_from_slice(&[bgra[2], bgra[1], bgra[0], bgra[3]]);
+                }
+            }
+        }
+        buffer.unmap();
+
+        Some(pixels)
+    }
+
     // pub fn update_render_config(&mut self, enabled: bool) {
 
     // }
 }
 
+impl RenderTarget {
+    fn new_texture(device: &wgpu::Device, size: PhysicalSize<u32>) -> Self {
+        let texture = Renderer::create_texture(
+            device,
+            size,
+            SWAPCHAIN_FORMAT,
+            wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::COPY_SRC,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        RenderTarget::Texture { texture, view }
+    }
+}
+
 impl Renderer {
     fn upload_new_transforms(&mut self, encoder: &mut wgpu::CommandEncoder, world: &mut World) {
         if world.added_parts.len() + world.added_fragments.len() == 0 {
             return;
         }
 
-        let (parts, fragments) = (&world.parts, &world.fragments);
+        let (parts, fragments) = (&world.parts, &mut world.fragments);
 
         let added_fragments = world.added_fragments.drain(..).chain(
             world
@@ -333,7 +808,10 @@ impl Renderer {
                 transform_index += 1;
 
                 let part = &parts[&part_id];
-                let fragment = &fragments[&fragment_id];
+                let fragment = &mut fragments[&fragment_id];
+
+                // Fragments are uploaded lazily, the first time they're drawn.
+                fragment.upload(&self.render_resources);
 
                 let offset = part.offset() + fragment.offset();
                 let rotation = part.rotation() * fragment.rotation();
@@ -352,6 +830,32 @@ impl Renderer {
                 .push_small(&self.render_resources, encoder, &transforms[..]);
     }
 
+    fn update_colors(&mut self, world: &mut World) {
+        if !self.color_mode_changed && !world.fragments.values().any(|f| f.colors_outdated) {
+            return;
+        }
+
+        // An auto-fit range depends on every fragment, so they're all recolored together.
+        let range = self
+            .color_mode
+            .scalar_range(world.fragments.values())
+            .unwrap_or((0.0, 1.0));
+
+        for fragment in world.fragments.values_mut() {
+            if let Some(atoms) = fragment.gpu_atoms() {
+                let colors = self.color_mode.fragment_colors(fragment, range);
+                atoms.write_colors(&self.render_resources, &colors);
+                fragment.colors_outdated = false;
+            }
+        }
+
+        self.molecular_pass.set_color_by_atom(
+            &self.render_resources,
+            self.color_mode != ColorMode::Element,
+        );
+        self.color_mode_changed = false;
+    }
+
     fn update_transforms(&mut self, _encoder: &mut wgpu::CommandEncoder, world: &mut World) {
         if world.modified_parts.len() + world.modified_fragments.len() == 0 {
             return;
@@ -457,3 +961,13 @@ impl Renderer {
         })
     }
 }
+
+/// Fragments are culled against a bounding sphere around the centers of their atoms, padded by
+/// this.
+fn max_atom_radius(periodic_table: &PeriodicTable) -> f32 {
+    periodic_table
+        .element_reprs
+        .iter()
+        .map(|repr| repr.radius())
+        .fold(0.0, f32::max)
+}
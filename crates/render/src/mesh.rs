@@ -0,0 +1,369 @@
+//! Triangle meshes generated from atoms, for exporting.
+
+use crate::{atoms::AtomRepr, utils::BoundingBox};
+use periodic_table::PeriodicTable;
+use std::collections::HashMap;
+use ultraviolet::Vec3;
+
+/// An indexed triangle mesh.
+///
+/// Triangles are wound counter-clockwise when viewed from outside.
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub indices: Vec<u32>,
+}
+
+impl Mesh {
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn triangles(&self) -> impl ExactSizeIterator<Item = [Vec3; 3]> + '_ {
+        self.indices.chunks_exact(3).map(move |triangle| {
+            [
+                self.positions[triangle[0] as usize],
+                self.positions[triangle[1] as usize],
+                self.positions[triangle[2] as usize],
+            ]
+        })
+    }
+}
+
+/// Meshes the union of the van der Waals spheres of `atoms`.
+///
+/// `resolution` is the size of a grid cell in angstroms, so smaller is smoother and slower.
+/// This is marching tetrahedra rather than marching cubes: every cell is split into six
+/// tetrahedra along its main diagonal, which doesn't need any case tables and always
+/// produces a closed mesh.
+pub fn vdw_surface<I>(atoms: I, periodic_table: &PeriodicTable, resolution: f32) -> Mesh
+where
+    I: IntoIterator<Item = AtomRepr>,
+{
+    assert!(resolution > 0.0, "the resolution must be positive");
+
+    let spheres: Vec<(Vec3, f32)> = atoms
+        .into_iter()
+        .map(|atom| {
+            let radius = periodic_table.element_repr(atom.kind.element()).radius();
+            (atom.pos, radius)
+        })
+        .collect();
+
+    if spheres.is_empty() {
+        return Mesh::default();
+    }
+
+    let grid = Grid::new(&spheres, resolution);
+    grid.polygonize()
+}
+
+// The six tetrahedra of a cell, as corner indices. Corner `i` is offset by
+// `(i & 1, (i >> 1) & 1, (i >> 2) & 1)` cells. Neighboring cells split their shared
+// faces along the same diagonal, which is what keeps the mesh closed.
+const TETRAHEDRA: [[usize; 4]; 6] = [
+    [0, 1, 3, 7],
+    [0, 1, 5, 7],
+    [0, 2, 3, 7],
+    [0, 2, 6, 7],
+    [0, 4, 5, 7],
+    [0, 4, 6, 7],
+];
+
+/// Samples of the distance to the surface of the nearest sphere, negative inside.
+struct Grid {
+    origin: Vec3,
+    resolution: f32,
+    dims: [usize; 3],
+    values: Vec<f32>,
+}
+
+impl Grid {
+    fn new(spheres: &[(Vec3, f32)], resolution: f32) -> Self {
+        // Only samples this close to a sphere are computed: everything further away just
+        // needs to be positive. This has to be more than the longest edge of a tetrahedron
+        // (the cell diagonal) so that both ends of every edge crossing the surface are exact.
+        let margin = 2.0 * resolution;
+
+        let mut bounding_box = BoundingBox::default();
+        for &(center, radius) in spheres {
+            let extent = Vec3::broadcast(radius + margin);
+            bounding_box.enclose_point(center - extent);
+            bounding_box.enclose_point(center + extent);
+        }
+
+        let size = bounding_box.size();
+        let dims = [
+            (size.x / resolution).ceil() as usize + 1,
+            (size.y / resolution).ceil() as usize + 1,
+            (size.z / resolution).ceil() as usize + 1,
+        ];
+
+        let mut grid = Self {
+            origin: bounding_box.min,
+            resolution,
+            dims,
+            values: vec![margin; dims[0] * dims[1] * dims[2]],
+        };
+
+        for &(center, radius) in spheres {
+            let reach = radius + margin;
+            let (min, max) = (
+                grid.cell_of(center - Vec3::broadcast(reach)),
+                grid.cell_of(center + Vec3::broadcast(reach)),
+            );
+
+            for z in min[2]..=(max[2] + 1).min(dims[2] - 1) {
+                for y in min[1]..=(max[1] + 1).min(dims[1] - 1) {
+                    for x in min[0]..=(max[0] + 1).min(dims[0] - 1) {
+                        let distance = (grid.position(x, y, z) - center).mag() - radius;
+                        let index = grid.index(x, y, z);
+                        grid.values[index] = grid.values[index].min(distance);
+                    }
+                }
+            }
+        }
+
+        grid
+    }
+
+    fn cell_of(&self, point: Vec3) -> [usize; 3] {
+        let cell = (point - self.origin) / self.resolution;
+        [
+            (cell.x.max(0.0) as usize).min(self.dims[0] - 1),
+            (cell.y.max(0.0) as usize).min(self.dims[1] - 1),
+            (cell.z.max(0.0) as usize).min(self.dims[2] - 1),
+        ]
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        x + self.dims[0] * (y + self.dims[1] * z)
+    }
+
+    fn position(&self, x: usize, y: usize, z: usize) -> Vec3 {
+        self.origin + Vec3::new(x as f32, y as f32, z as f32) * self.resolution
+    }
+
+    fn polygonize(&self) -> Mesh {
+        let mut builder = MeshBuilder::default();
+        let [nx, ny, nz] = self.dims;
+
+        for z in 0..nz - 1 {
+            for y in 0..ny - 1 {
+                for x in 0..nx - 1 {
+                    let corners: [(usize, Vec3, f32); 8] = {
+                        let corner = |i: usize| {
+                            let (x, y, z) = (x + (i & 1), y + ((i >> 1) & 1), z + ((i >> 2) & 1));
+                            let index = self.index(x, y, z);
+                            (index, self.position(x, y, z), self.values[index])
+                        };
+                        [
+                            corner(0),
+                            corner(1),
+                            corner(2),
+                            corner(3),
+                            corner(4),
+                            corner(5),
+                            corner(6),
+                            corner(7),
+                        ]
+                    };
+
+                    // Cells entirely on one side of the surface are by far the most common.
+                    let inside = corners.iter().filter(|(_, _, value)| *value < 0.0).count();
+                    if inside == 0 || inside == 8 {
+                        continue;
+                    }
+
+                    for tetrahedron in &TETRAHEDRA {
+                        builder.tetrahedron([
+                            corners[tetrahedron[0]],
+                            corners[tetrahedron[1]],
+                            corners[tetrahedron[2]],
+                            corners[tetrahedron[3]],
+                        ]);
+                    }
+                }
+            }
+        }
+
+        builder.finish()
+    }
+}
+
+#[derive(Default)]
+struct MeshBuilder {
+    mesh: Mesh,
+    // Vertices are shared between every triangle on the same grid edge.
+    edge_vertices: HashMap<(usize, usize), u32>,
+}
+
+impl MeshBuilder {
+    /// Each corner is `(grid index, position, value)`.
+    fn tetrahedron(&mut self, corners: [(usize, Vec3, f32); 4]) {
+        let (inside, outside): (Vec<_>, Vec<_>) = corners
+            .iter()
+            .copied()
+            .partition(|(_, _, value)| *value < 0.0);
+
+        let centroid = |corners: &[(usize, Vec3, f32)]| {
+            corners
+                .iter()
+                .fold(Vec3::zero(), |sum, (_, pos, _)| sum + *pos)
+                / corners.len() as f32
+        };
+
+        if inside.is_empty() || outside.is_empty() {
+            return;
+        }
+        // Triangles are flipped to face away from the inside corners.
+        let outward = centroid(&outside) - centroid(&inside);
+
+        match inside.len() {
+            1 | 3 => {
+                // One corner is cut off from the other three.
+                let (lone, others) = if inside.len() == 1 {
+                    (inside[0], &outside)
+                } else {
+                    (outside[0], &inside)
+                };
+                let a = self.edge_vertex(lone, others[0]);
+                let b = self.edge_vertex(lone, others[1]);
+                let c = self.edge_vertex(lone, others[2]);
+
+                self.triangle([a, b, c], outward);
+            }
+            2 => {
+                // The surface crosses four edges and forms a quad.
+                let a = self.edge_vertex(inside[0], outside[0]);
+                let b = self.edge_vertex(inside[0], outside[1]);
+                let c = self.edge_vertex(inside[1], outside[1]);
+                let d = self.edge_vertex(inside[1], outside[0]);
+
+                self.triangle([a, b, c], outward);
+                self.triangle([a, c, d], outward);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn edge_vertex(&mut self, a: (usize, Vec3, f32), b: (usize, Vec3, f32)) -> u32 {
+        let key = (a.0.min(b.0), a.0.max(b.0));
+        let positions = &mut self.mesh.positions;
+
+        *self.edge_vertices.entry(key).or_insert_with(|| {
+            let (a, b) = if a.0 < b.0 { (a, b) } else { (b, a) };
+            let t = a.2 / (a.2 - b.2);
+            positions.push(a.1 + (b.1 - a.1) * t);
+            positions.len() as u32 - 1
+        })
+    }
+
+    fn triangle(&mut self, [a, b, c]: [u32; 3], outward: Vec3) {
+        let positions = &self.mesh.positions;
+        let (pa, pb, pc) = (
+            positions[a as usize],
+            positions[b as usize],
+            positions[c as usize],
+        );
+
+        if (pb - pa).cross(pc - pa).dot(outward) < 0.0 {
+            self.mesh.indices.extend_from_slice(&[a, c, b]);
+        } else {
+            self.mesh.indices.extend_from_slice(&[a, b, c]);
+        }
+    }
+
+    fn finish(mut self) -> Mesh {
+        // Area-weighted vertex normals.
+        let mut normals = vec![Vec3::zero(); self.mesh.positions.len()];
+        for triangle in self.mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [
+                triangle[0] as usize,
+                triangle[1] as usize,
+                triangle[2] as usize,
+            ];
+            let positions = &self.mesh.positions;
+            let normal = (positions[b] - positions[a]).cross(positions[c] - positions[a]);
+            normals[a] += normal;
+            normals[b] += normal;
+            normals[c] += normal;
+        }
+
+        for normal in &mut normals {
+            if normal.mag_sq() > 0.0 {
+                normal.normalize();
+            }
+        }
+
+        self.mesh.normals = normals;
+        self.mesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atoms::AtomKind;
+    use periodic_table::Element;
+
+    fn carbon(x: f32) -> AtomRepr {
+        AtomRepr {
+            pos: Vec3::new(x, 0.0, 0.0),
+            kind: AtomKind::new(Element::Carbon),
+        }
+    }
+
+    /// Every edge has to be shared by exactly two triangles, which go along it in opposite
+    /// directions if they're wound the same way.
+    fn assert_watertight(mesh: &Mesh) {
+        assert!(!mesh.is_empty());
+
+        let mut edges = HashMap::new();
+        for triangle in mesh.indices.chunks_exact(3) {
+            for i in 0..3 {
+                let edge = (triangle[i], triangle[(i + 1) % 3]);
+                *edges.entry(edge).or_insert(0) += 1;
+            }
+        }
+
+        for (&(a, b), &count) in &edges {
+            assert_eq!(count, 1, "edge {}-{} is used more than once", a, b);
+            assert_eq!(
+                edges.get(&(b, a)),
+                Some(&1),
+                "edge {}-{} is only used by one triangle",
+                a,
+                b
+            );
+        }
+    }
+
+    #[test]
+    fn one_atom_is_watertight() {
+        let mesh = vdw_surface(vec![carbon(0.0)], &PeriodicTable::new(), 0.3);
+        assert_watertight(&mesh);
+    }
+
+    #[test]
+    fn overlapping_atoms_are_watertight() {
+        let mesh = vdw_surface(vec![carbon(0.0), carbon(1.5)], &PeriodicTable::new(), 0.3);
+        assert_watertight(&mesh);
+    }
+
+    #[test]
+    fn triangles_face_outward() {
+        let mesh = vdw_surface(vec![carbon(0.0)], &PeriodicTable::new(), 0.3);
+
+        for [a, b, c] in mesh.triangles() {
+            let normal = (b - a).cross(c - a);
+            assert!(normal.dot((a + b + c) / 3.0) >= 0.0);
+        }
+    }
+
+    #[test]
+    fn no_atoms_is_empty() {
+        assert!(vdw_surface(None, &PeriodicTable::new(), 0.3).is_empty());
+    }
+}
@@ -1,7 +1,8 @@
-use crate::{include_spirv, GlobalRenderResources, SWAPCHAIN_FORMAT};
+use crate::{GlobalRenderResources, SWAPCHAIN_FORMAT};
 
 pub struct BlitPass {
     bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
     pipeline: wgpu::RenderPipeline,
     render_bundle: wgpu::RenderBundle,
 }
@@ -9,17 +10,19 @@ pub struct BlitPass {
 impl BlitPass {
     pub fn new(render_resources: &GlobalRenderResources, input: &wgpu::TextureView) -> Self {
         let bind_group_layout = create_bind_group_layout(&render_resources.device);
-        let pipeline = create_blit_pipeline(&render_resources.device, &bind_group_layout);
-        let render_bundle = create_blit_render_bundle(
+        let bind_group = create_blit_bind_group(
             &render_resources.device,
             &bind_group_layout,
             &render_resources.linear_sampler,
             input,
-            &pipeline,
         );
+        let pipeline = create_blit_pipeline(&render_resources.device, &bind_group_layout);
+        let render_bundle =
+            create_blit_render_bundle(&render_resources.device, &bind_group, &pipeline);
 
         Self {
             bind_group_layout,
+            bind_group,
             pipeline,
             render_bundle,
         }
@@ -47,13 +50,26 @@ impl BlitPass {
     }
 
     pub fn update(&mut self, render_resources: &GlobalRenderResources, input: &wgpu::TextureView) {
-        self.render_bundle = create_blit_render_bundle(
+        self.bind_group = create_blit_bind_group(
             &render_resources.device,
             &self.bind_group_layout,
             &render_resources.linear_sampler,
             input,
-            &self.pipeline,
         );
+        self.render_bundle =
+            create_blit_render_bundle(&render_resources.device, &self.bind_group, &self.pipeline);
+    }
+
+    /// Rebuilds the pipeline if it uses the shader called `name`.
+    pub fn reload_shader(&mut self, render_resources: &GlobalRenderResources, name: &str) {
+        if name.starts_with("blit.") {
+            self.pipeline = create_blit_pipeline(&render_resources.device, &self.bind_group_layout);
+            self.render_bundle = create_blit_render_bundle(
+                &render_resources.device,
+                &self.bind_group,
+                &self.pipeline,
+            );
+        }
     }
 }
 
@@ -91,8 +107,8 @@ fn create_blit_pipeline(
         push_constant_ranges: &[],
     });
 
-    let vert_shader = device.create_shader_module(include_spirv!("blit.vert"));
-    let frag_shader = device.create_shader_module(include_spirv!("blit.frag"));
+    let vert_shader = shader!(device, "blit.vert");
+    let frag_shader = shader!(device, "blit.frag");
 
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: None,
@@ -120,14 +136,13 @@ fn create_blit_pipeline(
     })
 }
 
-fn create_blit_render_bundle(
+fn create_blit_bind_group(
     device: &wgpu::Device,
     bind_group_layout: &wgpu::BindGroupLayout,
     linear_sampler: &wgpu::Sampler,
     input_texture: &wgpu::TextureView,
-    blit_pipeline: &wgpu::RenderPipeline,
-) -> wgpu::RenderBundle {
-    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
         label: None,
         layout: bind_group_layout,
         entries: &[
@@ -140,8 +155,14 @@ fn create_blit_render_bundle(
                 resource: wgpu::BindingResource::TextureView(&input_texture),
             },
         ],
-    });
+    })
+}
 
+fn create_blit_render_bundle(
+    device: &wgpu::Device,
+    bind_group: &wgpu::BindGroup,
+    blit_pipeline: &wgpu::RenderPipeline,
+) -> wgpu::RenderBundle {
     let mut encoder = device.create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
         label: None,
         color_formats: &[SWAPCHAIN_FORMAT],
@@ -150,7 +171,7 @@ fn create_blit_render_bundle(
     });
 
     encoder.set_pipeline(blit_pipeline);
-    encoder.set_bind_group(0, &bind_group, &[]);
+    encoder.set_bind_group(0, bind_group, &[]);
     encoder.draw(0..3, 0..1);
     encoder.finish(&wgpu::RenderBundleDescriptor { label: None })
 }
@@ -1,4 +1,4 @@
-use crate::{include_spirv, GlobalRenderResources, Renderer, STORAGE_TEXTURE_FORMAT};
+use crate::{GlobalRenderResources, Renderer, STORAGE_TEXTURE_FORMAT};
 use winit::dpi::PhysicalSize;
 
 pub struct FxaaPass {
@@ -44,6 +44,13 @@ impl FxaaPass {
         cpass.dispatch(self.size.0, self.size.1, 1);
     }
 
+    /// Rebuilds the pipeline if it uses the shader called `name`.
+    pub fn reload_shader(&mut self, render_resources: &GlobalRenderResources, name: &str) {
+        if name == "fxaa.comp" {
+            self.pipeline = create_fxaa_pipeline(&render_resources.device, &self.bind_group_layout);
+        }
+    }
+
     pub fn update(
         &mut self,
         render_resources: &GlobalRenderResources,
@@ -120,7 +127,7 @@ fn create_fxaa_pipeline(
         push_constant_ranges: &[],
     });
 
-    let shader = device.create_shader_module(include_spirv!("fxaa.comp"));
+    let shader = shader!(device, "fxaa.comp");
 
     device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
         label: None,
@@ -1,7 +1,9 @@
 mod blit;
 mod fxaa;
 mod molecular;
+mod ssao;
 
 pub use blit::BlitPass;
 pub use fxaa::FxaaPass;
 pub use molecular::MolecularPass;
+pub use ssao::{SsaoOptions, SsaoPass};
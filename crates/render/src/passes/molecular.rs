@@ -1,15 +1,22 @@
 use crate::{
-    include_spirv, BufferVec, Fragment, FragmentId, GlobalRenderResources, PartId, Renderer,
+    BufferVec, ClipPlane, Fragment, FragmentId, GlobalRenderResources, PartId, Renderer,
     SWAPCHAIN_FORMAT,
 };
+use common::AsBytes;
 use std::{collections::HashMap, convert::TryInto as _, mem};
+use ultraviolet::Vec4;
+use wgpu::util::DeviceExt as _;
 use winit::dpi::PhysicalSize;
 
 // Renders atoms
 pub struct MolecularPass {
+    top_level_bgl: wgpu::BindGroupLayout,
     pipeline: wgpu::RenderPipeline,
     top_level_bg: wgpu::BindGroup,
 
+    settings: Settings,
+    settings_buffer: wgpu::Buffer,
+
     color_texture: wgpu::TextureView,
     depth_texture: wgpu::TextureView,
     // stencil_texture: wgpu::TextureView,
@@ -19,6 +26,19 @@ pub struct MolecularPass {
     driven: Driven,
 }
 
+/// Must match `Settings` in the billboard shaders.
+#[derive(Copy, Clone, Default)]
+#[repr(C, align(16))]
+struct Settings {
+    color_by_atom: u32, // bool
+    clip_mode: u32,     // 0 is off, 1 clips one side of the plane, 2 keeps a slab around it
+    slab_half_thickness: f32,
+    _padding: u32,
+    clip_plane: Vec4,
+}
+
+unsafe impl AsBytes for Settings {}
+
 #[repr(C)]
 struct DrawIndirect {
     vertex_count: u32,   // The number of vertices to draw.
@@ -50,11 +70,21 @@ impl MolecularPass {
             &top_level_bgl,
             &render_resources.atom_bgl,
         );
+        let settings = Settings::default();
+        let settings_buffer =
+            render_resources
+                .device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: None,
+                    contents: settings.as_bytes(),
+                    usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+                });
         let top_level_bg = create_top_level_bg(
             &render_resources.device,
             &top_level_bgl,
             camera_binding_resource,
             periodic_table_buffer,
+            &settings_buffer,
         );
 
         let color_texture = create_color_texture(&render_resources.device, size);
@@ -65,9 +95,13 @@ impl MolecularPass {
 
         (
             Self {
+                top_level_bgl,
                 pipeline,
                 top_level_bg,
 
+                settings,
+                settings_buffer,
+
                 color_texture: color_texture.create_view(&wgpu::TextureViewDescriptor::default()),
                 depth_texture,
                 normals_texture,
@@ -77,18 +111,74 @@ impl MolecularPass {
         )
     }
 
-    // Returns `(color texture, normals texture)`
+    // Returns `(color texture, depth texture, normals texture)`
     pub fn update(
         &mut self,
         render_resources: &GlobalRenderResources,
         size: PhysicalSize<u32>,
-    ) -> (&wgpu::TextureView, &wgpu::TextureView) {
+    ) -> (&wgpu::TextureView, &wgpu::TextureView, &wgpu::TextureView) {
         self.color_texture = create_color_texture(&render_resources.device, size)
             .create_view(&wgpu::TextureViewDescriptor::default());
         self.depth_texture = create_depth_texture(&render_resources.device, size);
         self.normals_texture = create_normals_texture(&render_resources.device, size);
 
-        (&self.color_texture, &self.normals_texture)
+        (
+            &self.color_texture,
+            &self.depth_texture,
+            &self.normals_texture,
+        )
+    }
+
+    pub fn color_texture(&self) -> &wgpu::TextureView {
+        &self.color_texture
+    }
+
+    pub fn depth_texture(&self) -> &wgpu::TextureView {
+        &self.depth_texture
+    }
+
+    /// Uses the per-atom colors instead of the element colors.
+    pub fn set_color_by_atom(&mut self, render_resources: &GlobalRenderResources, enabled: bool) {
+        self.settings.color_by_atom = enabled as u32;
+        self.write_settings(render_resources);
+    }
+
+    pub fn set_clip_plane(
+        &mut self,
+        render_resources: &GlobalRenderResources,
+        clip_plane: Option<&ClipPlane>,
+    ) {
+        match clip_plane {
+            Some(clip_plane) => {
+                self.settings.clip_plane = clip_plane.equation();
+                match clip_plane.slab {
+                    Some(thickness) => {
+                        self.settings.clip_mode = 2;
+                        self.settings.slab_half_thickness = thickness / 2.0;
+                    }
+                    None => self.settings.clip_mode = 1,
+                }
+            }
+            None => self.settings.clip_mode = 0,
+        }
+        self.write_settings(render_resources);
+    }
+
+    fn write_settings(&self, render_resources: &GlobalRenderResources) {
+        render_resources
+            .queue
+            .write_buffer(&self.settings_buffer, 0, self.settings.as_bytes());
+    }
+
+    /// Rebuilds the pipeline if it uses the shader called `name`.
+    pub fn reload_shader(&mut self, render_resources: &GlobalRenderResources, name: &str) {
+        if name.starts_with("billboard.") {
+            self.pipeline = create_render_pipeline(
+                &render_resources.device,
+                &self.top_level_bgl,
+                &render_resources.atom_bgl,
+            );
+        }
     }
 
     // TODO: figure out how to multithread this
@@ -140,6 +230,11 @@ impl MolecularPass {
 
         // TODO: Try instancing?
         for fragment in fragments {
+            let atoms = match fragment.gpu_atoms() {
+                Some(atoms) => atoms,
+                None => continue,
+            };
+
             let transform_offset =
                 per_fragment[&fragment.id()].1 * (mem::size_of::<ultraviolet::Mat4>() as u64);
 
@@ -150,8 +245,8 @@ impl MolecularPass {
                 ),
             );
 
-            rpass.set_bind_group(1, &fragment.atoms().bind_group(), &[]);
-            rpass.draw(0..(fragment.atoms().len() * 3).try_into().unwrap(), 0..1)
+            rpass.set_bind_group(1, &atoms.bind_group(), &[]);
+            rpass.draw(0..(atoms.len() * 3).try_into().unwrap(), 0..1)
         }
     }
 }
@@ -181,6 +276,16 @@ fn create_top_level_bgl(device: &wgpu::Device) -> wgpu::BindGroupLayout {
                 },
                 count: None,
             },
+            // settings
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::UniformBuffer {
+                    dynamic: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
         ],
     })
 }
@@ -190,6 +295,7 @@ fn create_top_level_bg(
     top_level_bgl: &wgpu::BindGroupLayout,
     camera_binding_resource: wgpu::BindingResource,
     periodic_table_buffer: &wgpu::Buffer,
+    settings_buffer: &wgpu::Buffer,
 ) -> wgpu::BindGroup {
     device.create_bind_group(&wgpu::BindGroupDescriptor {
         label: None,
@@ -209,6 +315,15 @@ fn create_top_level_bg(
                     size: None,
                 },
             },
+            // settings
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Buffer {
+                    buffer: settings_buffer,
+                    offset: 0,
+                    size: None,
+                },
+            },
         ],
     })
 }
@@ -224,8 +339,8 @@ fn create_render_pipeline(
         push_constant_ranges: &[],
     });
 
-    let atom_vert_shader = device.create_shader_module(include_spirv!("billboard.vert"));
-    let atom_frag_shader = device.create_shader_module(include_spirv!("billboard.frag"));
+    let atom_vert_shader = shader!(device, "billboard.vert");
+    let atom_frag_shader = shader!(device, "billboard.frag");
 
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: None,
@@ -284,7 +399,8 @@ fn create_depth_texture(device: &wgpu::Device, size: PhysicalSize<u32>) -> wgpu:
         device,
         size,
         wgpu::TextureFormat::Depth32Float,
-        wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+        // Sampled by ambient occlusion.
+        wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
     )
     .create_view(&wgpu::TextureViewDescriptor::default())
 }
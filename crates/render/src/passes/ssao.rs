@@ -0,0 +1,275 @@
+use crate::{GlobalRenderResources, Renderer, STORAGE_TEXTURE_FORMAT};
+use common::AsBytes;
+use wgpu::util::DeviceExt as _;
+use winit::dpi::PhysicalSize;
+
+/// Screen-space ambient occlusion, which darkens the crevices between atoms.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SsaoOptions {
+    /// How far apart, in angstroms, atoms can still occlude each other.
+    pub radius: f32,
+    /// Zero has no effect, and one lets fully occluded pixels go black.
+    pub intensity: f32,
+}
+
+impl Default for SsaoOptions {
+    fn default() -> Self {
+        Self {
+            radius: 2.0,
+            intensity: 1.0,
+        }
+    }
+}
+
+/// Must match `Settings` in `ssao.comp`.
+#[derive(Copy, Clone)]
+#[repr(C, align(16))]
+struct Settings {
+    radius: f32,
+    intensity: f32,
+}
+
+unsafe impl AsBytes for Settings {}
+
+impl From<SsaoOptions> for Settings {
+    fn from(options: SsaoOptions) -> Self {
+        Self {
+            radius: options.radius,
+            intensity: options.intensity,
+        }
+    }
+}
+
+pub struct SsaoPass {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    settings_buffer: wgpu::Buffer,
+    texture: wgpu::TextureView,
+    size: (u32, u32),
+}
+
+impl SsaoPass {
+    /// Returns the pass and the view of its output texture.
+    pub fn new(
+        render_resources: &GlobalRenderResources,
+        size: PhysicalSize<u32>,
+        camera_binding_resource: wgpu::BindingResource,
+        color: &wgpu::TextureView,
+        depth: &wgpu::TextureView,
+        options: SsaoOptions,
+    ) -> (Self, wgpu::TextureView) {
+        let og_texture = create_ssao_texture(&render_resources.device, size);
+        let bind_group_layout = create_bind_group_layout(&render_resources.device);
+
+        let settings_buffer =
+            render_resources
+                .device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: None,
+                    contents: Settings::from(options).as_bytes(),
+                    usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+                });
+
+        let texture = og_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        (
+            Self {
+                pipeline: create_ssao_pipeline(&render_resources.device, &bind_group_layout),
+                bind_group: create_ssao_bind_group(
+                    render_resources,
+                    &bind_group_layout,
+                    camera_binding_resource,
+                    &settings_buffer,
+                    color,
+                    depth,
+                    &texture,
+                ),
+                bind_group_layout,
+                settings_buffer,
+                texture,
+                size: ((size.width + 7) / 8, (size.height + 7) / 8),
+            },
+            og_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+        )
+    }
+
+    pub fn run<'a>(&'a self, cpass: &mut wgpu::ComputePass<'a>) {
+        cpass.set_pipeline(&self.pipeline);
+        cpass.set_bind_group(0, &self.bind_group, &[]);
+        cpass.dispatch(self.size.0, self.size.1, 1);
+    }
+
+    pub fn set_options(&self, render_resources: &GlobalRenderResources, options: SsaoOptions) {
+        render_resources.queue.write_buffer(
+            &self.settings_buffer,
+            0,
+            Settings::from(options).as_bytes(),
+        );
+    }
+
+    /// Rebuilds the pipeline if it uses the shader called `name`.
+    pub fn reload_shader(&mut self, render_resources: &GlobalRenderResources, name: &str) {
+        if name == "ssao.comp" {
+            self.pipeline = create_ssao_pipeline(&render_resources.device, &self.bind_group_layout);
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        render_resources: &GlobalRenderResources,
+        camera_binding_resource: wgpu::BindingResource,
+        color: &wgpu::TextureView,
+        depth: &wgpu::TextureView,
+        size: PhysicalSize<u32>,
+    ) -> &wgpu::TextureView {
+        self.texture = create_ssao_texture(&render_resources.device, size)
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        self.bind_group = create_ssao_bind_group(
+            render_resources,
+            &self.bind_group_layout,
+            camera_binding_resource,
+            &self.settings_buffer,
+            color,
+            depth,
+            &self.texture,
+        );
+        self.size = ((size.width + 7) / 8, (size.height + 7) / 8);
+
+        &self.texture
+    }
+
+    pub fn texture(&self) -> &wgpu::TextureView {
+        &self.texture
+    }
+}
+
+fn create_ssao_texture(device: &wgpu::Device, size: PhysicalSize<u32>) -> wgpu::Texture {
+    Renderer::create_texture(
+        device,
+        size,
+        STORAGE_TEXTURE_FORMAT,
+        wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::STORAGE,
+    )
+}
+
+fn create_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    let sampled_texture = |binding| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStage::COMPUTE,
+        ty: wgpu::BindingType::SampledTexture {
+            dimension: wgpu::TextureViewDimension::D2,
+            component_type: wgpu::TextureComponentType::Float,
+            multisampled: false,
+        },
+        count: None,
+    };
+    let uniform_buffer = |binding| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStage::COMPUTE,
+        ty: wgpu::BindingType::UniformBuffer {
+            dynamic: false,
+            min_binding_size: None,
+        },
+        count: None,
+    };
+
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: None,
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::COMPUTE,
+                ty: wgpu::BindingType::Sampler { comparison: false },
+                count: None,
+            },
+            // color
+            sampled_texture(1),
+            // depth
+            sampled_texture(2),
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStage::COMPUTE,
+                ty: wgpu::BindingType::StorageTexture {
+                    dimension: wgpu::TextureViewDimension::D2,
+                    format: STORAGE_TEXTURE_FORMAT,
+                    readonly: false,
+                },
+                count: None,
+            },
+            // camera
+            uniform_buffer(4),
+            // settings
+            uniform_buffer(5),
+        ],
+    })
+}
+
+fn create_ssao_pipeline(
+    device: &wgpu::Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+) -> wgpu::ComputePipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    let shader = shader!(device, "ssao.comp");
+
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: Some(&layout),
+        compute_stage: wgpu::ProgrammableStageDescriptor {
+            module: &shader,
+            entry_point: "main",
+        },
+    })
+}
+
+fn create_ssao_bind_group(
+    render_resources: &GlobalRenderResources,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    camera_binding_resource: wgpu::BindingResource,
+    settings_buffer: &wgpu::Buffer,
+    color: &wgpu::TextureView,
+    depth: &wgpu::TextureView,
+    output: &wgpu::TextureView,
+) -> wgpu::BindGroup {
+    render_resources
+        .device
+        .create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(&render_resources.linear_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(color),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(depth),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(output),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: camera_binding_resource,
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::Buffer {
+                        buffer: settings_buffer,
+                        offset: 0,
+                        size: None,
+                    },
+                },
+            ],
+        })
+}
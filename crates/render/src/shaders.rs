@@ -0,0 +1,137 @@
+//! Shader modules.
+//!
+//! With the `hot-reload` feature, the shader sources are watched and recompiled while the app
+//! is running, and each pass rebuilds the pipelines that use a shader when it changes.
+//! Otherwise, the spirv that `build.rs` compiled is embedded in the binary.
+
+/// Creates a shader module, preferring a version that was recompiled at runtime.
+macro_rules! shader {
+    ($device:expr, $name:literal) => {
+        $crate::shaders::create_module($device, $name, $crate::include_spirv!($name))
+    };
+}
+
+pub(crate) fn create_module(
+    device: &wgpu::Device,
+    name: &str,
+    embedded: wgpu::ShaderModuleSource,
+) -> wgpu::ShaderModule {
+    #[cfg(feature = "hot-reload")]
+    {
+        if let Some(spirv) = hot_reload::recompiled(name) {
+            return device.create_shader_module(wgpu::ShaderModuleSource::SpirV(spirv.into()));
+        }
+    }
+    #[cfg(not(feature = "hot-reload"))]
+    let _ = name;
+
+    device.create_shader_module(embedded)
+}
+
+#[cfg(feature = "hot-reload")]
+pub(crate) use hot_reload::ShaderWatcher;
+
+#[cfg(feature = "hot-reload")]
+mod hot_reload {
+    use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher as _};
+    use parking_lot::{const_mutex, Mutex};
+    use std::{collections::HashMap, fs, path::Path, sync::mpsc, time::Duration};
+
+    const SHADER_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders");
+
+    // The most recent successful compilation of each shader, by file name.
+    static RECOMPILED: Mutex<Option<HashMap<String, Vec<u32>>>> = const_mutex(None);
+
+    pub fn recompiled(name: &str) -> Option<Vec<u32>> {
+        RECOMPILED.lock().as_ref()?.get(name).cloned()
+    }
+
+    pub struct ShaderWatcher {
+        _watcher: RecommendedWatcher,
+        events: mpsc::Receiver<DebouncedEvent>,
+        compiler: shaderc::Compiler,
+    }
+
+    impl ShaderWatcher {
+        pub fn new() -> Result<Self, String> {
+            let (sender, events) = mpsc::channel();
+            let mut watcher = notify::watcher(sender, Duration::from_millis(100))
+                .map_err(|e| format!("failed to create a shader watcher: {}", e))?;
+            watcher
+                .watch(SHADER_DIR, RecursiveMode::Recursive)
+                .map_err(|e| format!("failed to watch `{}`: {}", SHADER_DIR, e))?;
+
+            let compiler = shaderc::Compiler::new()
+                .ok_or_else(|| "failed to initialize glsl compiler".to_string())?;
+
+            log::info!("watching `{}` for shader changes", SHADER_DIR);
+
+            Ok(Self {
+                _watcher: watcher,
+                events,
+                compiler,
+            })
+        }
+
+        /// Recompiles every shader that changed since the last call, and returns the names of
+        /// the ones that compiled. Shaders that fail to compile keep their previous version.
+        pub fn poll(&mut self) -> Vec<String> {
+            let mut changed = Vec::new();
+
+            for event in self.events.try_iter() {
+                let path = match event {
+                    DebouncedEvent::Write(path) | DebouncedEvent::Create(path) => path,
+                    _ => continue,
+                };
+
+                if let Some(name) = self.recompile(&path) {
+                    if !changed.contains(&name) {
+                        changed.push(name);
+                    }
+                }
+            }
+
+            changed
+        }
+
+        fn recompile(&mut self, path: &Path) -> Option<String> {
+            let kind = match path.extension()?.to_str()? {
+                "vert" => shaderc::ShaderKind::Vertex,
+                "frag" => shaderc::ShaderKind::Fragment,
+                "comp" => shaderc::ShaderKind::Compute,
+                _ => return None,
+            };
+            let name = path.file_name()?.to_str()?.to_string();
+
+            let source = match fs::read_to_string(path) {
+                Ok(source) => source,
+                Err(e) => {
+                    log::error!("failed to read `{}`: {}", path.display(), e);
+                    return None;
+                }
+            };
+
+            match self
+                .compiler
+                .compile_into_spirv(&source, kind, &name, "main", None)
+            {
+                Ok(artifact) => {
+                    log::info!("reloaded {}", name);
+                    RECOMPILED
+                        .lock()
+                        .get_or_insert_with(HashMap::new)
+                        .insert(name.clone(), artifact.as_binary().to_vec());
+                    Some(name)
+                }
+                Err(e) => {
+                    log::error!(
+                        "failed to compile {}, keeping the old version:\n{}",
+                        name,
+                        e
+                    );
+                    None
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Per-frame timings and counts.
+//!
+//! Only cpu times are measured: gpu pass timings need timestamp queries, which this version of
+//! wgpu doesn't expose yet.
+
+use instant::Instant;
+use std::{collections::VecDeque, fmt, io, time::Duration};
+
+/// How many frames the rolling averages cover.
+const HISTORY: usize = 240;
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Frame {
+    /// The time since the previous frame started.
+    pub frame_time: Duration,
+    /// The time spent recording and submitting commands.
+    pub encode_time: Duration,
+    pub fragments: usize,
+    pub atoms: usize,
+    /// Fragments that were actually drawn.
+    pub visible_fragments: usize,
+    pub visible_atoms: usize,
+}
+
+#[derive(Default)]
+pub struct FrameStats {
+    recent: VecDeque<Frame>,
+    // Every frame since recording started, for dumping to csv.
+    recording: Option<Vec<Frame>>,
+    last_start: Option<Instant>,
+}
+
+impl FrameStats {
+    pub(crate) fn push(&mut self, start: Instant, mut frame: Frame) {
+        frame.frame_time = self
+            .last_start
+            .map(|last_start| start - last_start)
+            .unwrap_or_default();
+        self.last_start = Some(start);
+
+        if self.recent.len() == HISTORY {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(frame);
+
+        if let Some(recording) = &mut self.recording {
+            recording.push(frame);
+        }
+    }
+
+    pub fn last(&self) -> Option<&Frame> {
+        self.recent.back()
+    }
+
+    pub fn summary(&self) -> Summary {
+        let durations = |f: fn(&Frame) -> Duration| -> Vec<Duration> {
+            let mut durations: Vec<_> = self.recent.iter().map(f).collect();
+            durations.sort();
+            durations
+        };
+
+        Summary {
+            frames: self.recent.len(),
+            frame_time: Timing::new(&durations(|frame| frame.frame_time)),
+            encode_time: Timing::new(&durations(|frame| frame.encode_time)),
+        }
+    }
+
+    /// Starts keeping every frame (instead of just the recent ones) for `write_csv`.
+    pub fn start_recording(&mut self) {
+        self.recording = Some(Vec::new());
+    }
+
+    /// Writes the recorded frames, one per row, with times in milliseconds.
+    pub fn write_csv<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(
+            writer,
+            "frame,frame_time_ms,encode_time_ms,fragments,atoms,visible_fragments,visible_atoms"
+        )?;
+
+        for (index, frame) in self.recording.iter().flatten().enumerate() {
+            writeln!(
+                writer,
+                "{},{:.3},{:.3},{},{},{},{}",
+                index,
+                frame.frame_time.as_secs_f64() * 1000.0,
+                frame.encode_time.as_secs_f64() * 1000.0,
+                frame.fragments,
+                frame.atoms,
+                frame.visible_fragments,
+                frame.visible_atoms,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Rolling statistics over the recent frames.
+#[derive(Debug, Copy, Clone)]
+pub struct Summary {
+    pub frames: usize,
+    pub frame_time: Timing,
+    pub encode_time: Timing,
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Timing {
+    pub mean: Duration,
+    pub p95: Duration,
+}
+
+impl Timing {
+    /// `durations` must be sorted.
+    fn new(durations: &[Duration]) -> Self {
+        if durations.is_empty() {
+            return Self::default();
+        }
+
+        let total: Duration = durations.iter().sum();
+        let p95 = (durations.len() * 95 + 99) / 100 - 1;
+
+        Self {
+            mean: total / durations.len() as u32,
+            p95: durations[p95],
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+
+        write!(
+            f,
+            "over {} frames: frame time {:.2}ms (p95 {:.2}ms), encode time {:.2}ms (p95 {:.2}ms)",
+            self.frames,
+            ms(self.frame_time.mean),
+            ms(self.frame_time.p95),
+            ms(self.encode_time.mean),
+            ms(self.encode_time.p95),
+        )
+    }
+}
@@ -6,7 +6,54 @@ pub struct BoundingBox {
     pub max: Vec3,
 }
 
+/// The default bounding box is inverted (`min` is positive infinity and `max`
+/// is negative infinity), so it contains nothing and is the identity for `union`.
+impl Default for BoundingBox {
+    fn default() -> Self {
+        Self {
+            min: Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            max: Vec3::new(-f32::INFINITY, -f32::INFINITY, -f32::INFINITY),
+        }
+    }
+}
+
 impl BoundingBox {
+    pub fn from_points<I>(points: I) -> Self
+    where
+        I: IntoIterator<Item = Vec3>,
+    {
+        let mut bounding_box = Self::default();
+        for point in points {
+            bounding_box.enclose_point(point);
+        }
+        bounding_box
+    }
+
+    /// Returns true if this box doesn't contain any points,
+    /// like the default (inverted) box.
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    /// Not meaningful for an empty box.
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) / 2.0
+    }
+
+    /// Returns zero for an empty box.
+    pub fn size(&self) -> Vec3 {
+        if self.is_empty() {
+            Vec3::zero()
+        } else {
+            self.max - self.min
+        }
+    }
+
+    pub fn enclose_point(&mut self, point: Vec3) {
+        self.min = self.min.min_by_component(point);
+        self.max = self.max.max_by_component(point);
+    }
+
     pub fn union(&self, other: &Self) -> Self {
         Self {
             min: Vec3::new(
@@ -22,6 +69,65 @@ impl BoundingBox {
         }
     }
 
+    /// Returns the overlapping region of both boxes, or `None` if they're disjoint.
+    ///
+    /// Boxes that only touch produce a box with zero volume.
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let intersection = Self {
+            min: self.min.max_by_component(other.min),
+            max: self.max.min_by_component(other.max),
+        };
+
+        if intersection.is_empty() {
+            None
+        } else {
+            Some(intersection)
+        }
+    }
+
+    /// Returns the distance along `dir` (in multiples of its length)
+    /// to the nearest intersection with this box, or zero if `origin` is inside it.
+    ///
+    /// Uses the slab method.
+    pub fn intersect_ray(&self, origin: Vec3, dir: Vec3) -> Option<f32> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let mut t_near = 0.0f32;
+        let mut t_far = f32::INFINITY;
+
+        for (origin, dir, min, max) in [
+            (origin.x, dir.x, self.min.x, self.max.x),
+            (origin.y, dir.y, self.min.y, self.max.y),
+            (origin.z, dir.z, self.min.z, self.max.z),
+        ]
+        .iter()
+        .copied()
+        {
+            if dir == 0.0 {
+                // The ray is parallel to this slab, so it either never enters it or is always in it.
+                if origin < min || origin > max {
+                    return None;
+                }
+            } else {
+                let inv_dir = 1.0 / dir;
+                let t0 = (min - origin) * inv_dir;
+                let t1 = (max - origin) * inv_dir;
+                let (t0, t1) = if t0 <= t1 { (t0, t1) } else { (t1, t0) };
+
+                t_near = t_near.max(t0);
+                t_far = t_far.min(t1);
+
+                if t_near > t_far {
+                    return None;
+                }
+            }
+        }
+
+        Some(t_near)
+    }
+
     pub fn contains(&self, point: Vec3) -> bool {
         self.min.x <= point.x
             && point.x <= self.max.x
@@ -31,3 +137,176 @@ impl BoundingBox {
             && point.z <= self.max.z
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_is_empty() {
+        let bounding_box = BoundingBox::default();
+
+        assert!(bounding_box.is_empty());
+        assert_eq!(bounding_box.size(), Vec3::zero());
+        assert!(!bounding_box.contains(Vec3::zero()));
+    }
+
+    #[test]
+    fn no_points_is_empty() {
+        assert!(BoundingBox::from_points(None).is_empty());
+    }
+
+    #[test]
+    fn one_point_has_no_size() {
+        let point = Vec3::new(1.0, -2.0, 3.0);
+        let bounding_box = BoundingBox::from_points(Some(point));
+
+        assert!(!bounding_box.is_empty());
+        assert_eq!(bounding_box.center(), point);
+        assert_eq!(bounding_box.size(), Vec3::zero());
+        assert!(bounding_box.contains(point));
+    }
+
+    // Fragments used to build their boxes from the x coordinates alone.
+    #[test]
+    fn from_points_uses_every_axis() {
+        let bounding_box = BoundingBox::from_points(vec![
+            Vec3::new(0.0, -3.0, 1.0),
+            Vec3::new(0.0, 4.0, -5.0),
+            Vec3::new(0.0, 1.0, 6.0),
+        ]);
+
+        assert_eq!(bounding_box.min, Vec3::new(0.0, -3.0, -5.0));
+        assert_eq!(bounding_box.max, Vec3::new(0.0, 4.0, 6.0));
+        assert_eq!(bounding_box.center(), Vec3::new(0.0, 0.5, 0.5));
+        assert_eq!(bounding_box.size(), Vec3::new(0.0, 7.0, 11.0));
+    }
+
+    #[test]
+    fn enclose_point_grows_the_box() {
+        let mut bounding_box = BoundingBox::from_points(Some(Vec3::zero()));
+        bounding_box.enclose_point(Vec3::new(2.0, -1.0, 0.5));
+        bounding_box.enclose_point(Vec3::new(1.0, 1.0, 1.0));
+
+        assert_eq!(bounding_box.min, Vec3::new(0.0, -1.0, 0.0));
+        assert_eq!(bounding_box.max, Vec3::new(2.0, 1.0, 1.0));
+
+        // Points that are already inside don't change it.
+        bounding_box.enclose_point(Vec3::new(1.0, 0.0, 0.5));
+        assert_eq!(bounding_box.min, Vec3::new(0.0, -1.0, 0.0));
+        assert_eq!(bounding_box.max, Vec3::new(2.0, 1.0, 1.0));
+    }
+
+    fn unit_box() -> BoundingBox {
+        BoundingBox::from_points(vec![Vec3::zero(), Vec3::one()])
+    }
+
+    #[test]
+    fn ray_hits_the_nearest_face() {
+        let t = unit_box().intersect_ray(Vec3::new(-2.0, 0.5, 0.5), Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(t, Some(2.0));
+
+        // The distance is in multiples of the direction's length.
+        let t = unit_box().intersect_ray(Vec3::new(-2.0, 0.5, 0.5), Vec3::new(4.0, 0.0, 0.0));
+        assert_eq!(t, Some(0.5));
+
+        let t = unit_box()
+            .intersect_ray(Vec3::new(2.0, 2.0, 2.0), Vec3::new(-1.0, -1.0, -1.0))
+            .unwrap();
+        assert!((t - 1.0).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn ray_misses() {
+        let bounding_box = unit_box();
+
+        // Pointing the wrong way.
+        assert_eq!(
+            bounding_box.intersect_ray(Vec3::new(-2.0, 0.5, 0.5), Vec3::new(-1.0, 0.0, 0.0)),
+            None
+        );
+        // Passing beside it.
+        assert_eq!(
+            bounding_box.intersect_ray(Vec3::new(-2.0, 0.5, 0.5), Vec3::new(1.0, 1.0, 0.0)),
+            None
+        );
+        assert_eq!(
+            BoundingBox::default().intersect_ray(Vec3::zero(), Vec3::new(1.0, 0.0, 0.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn ray_from_inside_hits_at_zero() {
+        let t = unit_box().intersect_ray(Vec3::new(0.5, 0.5, 0.5), Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(t, Some(0.0));
+    }
+
+    #[test]
+    fn ray_parallel_to_an_axis() {
+        let bounding_box = unit_box();
+
+        // Parallel to the y and z slabs, and inside both of them.
+        assert_eq!(
+            bounding_box.intersect_ray(Vec3::new(-1.0, 0.5, 0.5), Vec3::new(1.0, 0.0, 0.0)),
+            Some(1.0)
+        );
+        // Parallel to the z slab and outside of it.
+        assert_eq!(
+            bounding_box.intersect_ray(Vec3::new(-1.0, 0.5, 2.0), Vec3::new(1.0, 0.0, 0.0)),
+            None
+        );
+        // Along an edge of the box, which still counts.
+        assert_eq!(
+            bounding_box.intersect_ray(Vec3::new(-1.0, 1.0, 1.0), Vec3::new(1.0, 0.0, 0.0)),
+            Some(1.0)
+        );
+    }
+
+    fn cube(min: f32, max: f32) -> BoundingBox {
+        BoundingBox::from_points(vec![Vec3::new(min, min, min), Vec3::new(max, max, max)])
+    }
+
+    #[test]
+    fn overlapping_boxes_intersect() {
+        let intersection = cube(0.0, 2.0).intersection(&cube(1.0, 3.0)).unwrap();
+
+        assert_eq!(intersection.min, Vec3::one());
+        assert_eq!(intersection.max, Vec3::new(2.0, 2.0, 2.0));
+
+        // A box inside another one is the whole intersection.
+        let intersection = cube(0.0, 3.0).intersection(&cube(1.0, 2.0)).unwrap();
+        assert_eq!(intersection.min, Vec3::one());
+        assert_eq!(intersection.max, Vec3::new(2.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn disjoint_boxes_dont_intersect() {
+        assert!(cube(0.0, 1.0).intersection(&cube(2.0, 3.0)).is_none());
+
+        // Overlapping along two axes isn't enough.
+        let shifted =
+            BoundingBox::from_points(vec![Vec3::new(0.5, 0.5, 2.0), Vec3::new(1.5, 1.5, 3.0)]);
+        assert!(cube(0.0, 1.0).intersection(&shifted).is_none());
+    }
+
+    #[test]
+    fn touching_boxes_intersect_in_a_face() {
+        let other =
+            BoundingBox::from_points(vec![Vec3::new(1.0, 0.0, 0.0), Vec3::new(2.0, 1.0, 1.0)]);
+        let intersection = cube(0.0, 1.0).intersection(&other).unwrap();
+
+        assert_eq!(intersection.min, Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(intersection.max, Vec3::one());
+        assert_eq!(intersection.size(), Vec3::new(0.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn empty_boxes_dont_intersect() {
+        let empty = BoundingBox::default();
+
+        assert!(empty.intersection(&cube(0.0, 1.0)).is_none());
+        assert!(cube(0.0, 1.0).intersection(&empty).is_none());
+        assert!(empty.intersection(&empty).is_none());
+    }
+}
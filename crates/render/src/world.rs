@@ -1,10 +1,12 @@
 use crate::{
     atoms::{AtomRepr, Atoms},
+    mesh::{self, Mesh},
     utils::BoundingBox,
-    GlobalRenderResources,
+    GlobalRenderResources, Interactions,
 };
 use common::AsBytes;
 use indexmap::IndexMap;
+use periodic_table::{Element, PeriodicTable};
 use std::{
     ops::{Deref, DerefMut},
     sync::atomic::{AtomicU64, Ordering},
@@ -39,7 +41,15 @@ declare_id!(FragmentId, PartId);
 
 pub struct Fragment {
     id: FragmentId,
-    atoms: Atoms,
+    atoms: Vec<AtomRepr>,
+    // Not allocated until the fragment is drawn, and never allocated for an empty fragment.
+    gpu_atoms: Option<Atoms>,
+    // Per-atom values that atoms can be colored by.
+    scalars: Option<Vec<f32>>,
+    // Set when the gpu colors don't match the renderer's color mode anymore.
+    pub(crate) colors_outdated: bool,
+    // Set when the atoms changed after they were uploaded.
+    atoms_outdated: bool,
 
     bounding_box: BoundingBox,
     center: Vec3, // not sure what type of center yet (median, initial atom, etc)
@@ -48,40 +58,30 @@ pub struct Fragment {
 }
 
 impl Fragment {
-    pub fn from_atoms<I>(gpu_resources: &GlobalRenderResources, atoms: I) -> Self
+    /// Creates a fragment without touching the gpu.
+    ///
+    /// The atoms are uploaded by the renderer the first time
+    /// the fragment is drawn.
+    pub fn new<I>(atoms: I) -> Self
     where
         I: IntoIterator<Item = AtomRepr>,
-        I::IntoIter: ExactSizeIterator,
     {
-        let mut point_sum = Vec3::zero();
-        let mut max_point = Vec3::new(-f32::INFINITY, -f32::INFINITY, -f32::INFINITY);
-        let mut min_point = Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
-
-        let fragment_id = FragmentId::new();
-
-        let atoms = Atoms::new(
-            gpu_resources,
-            fragment_id,
-            atoms.into_iter().inspect(|atom| {
-                point_sum += atom.pos;
-                max_point.x = atom.pos.x.max(max_point.x);
-                max_point.y = atom.pos.x.max(max_point.y);
-                max_point.z = atom.pos.x.max(max_point.z);
-                min_point.x = atom.pos.x.min(min_point.x);
-                min_point.y = atom.pos.x.min(min_point.y);
-                min_point.z = atom.pos.x.min(min_point.z);
-            }),
-        );
+        let atoms: Vec<_> = atoms.into_iter().collect();
 
-        let center = point_sum / atoms.len() as f32;
-        let bounding_box = BoundingBox {
-            min: min_point,
-            max: max_point,
+        let bounding_box = BoundingBox::from_points(atoms.iter().map(|atom| atom.pos));
+        let center = if atoms.is_empty() {
+            Vec3::zero()
+        } else {
+            atoms.iter().fold(Vec3::zero(), |sum, atom| sum + atom.pos) / atoms.len() as f32
         };
 
         Self {
-            id: fragment_id,
+            id: FragmentId::new(),
             atoms,
+            gpu_atoms: None,
+            scalars: None,
+            colors_outdated: true,
+            atoms_outdated: false,
 
             bounding_box,
             center,
@@ -90,14 +90,132 @@ impl Fragment {
         }
     }
 
+    /// Creates a fragment and uploads its atoms to the gpu immediately.
+    pub fn from_atoms<I>(gpu_resources: &GlobalRenderResources, atoms: I) -> Self
+    where
+        I: IntoIterator<Item = AtomRepr>,
+    {
+        let mut fragment = Self::new(atoms);
+        fragment.upload(gpu_resources);
+        fragment
+    }
+
     pub fn id(&self) -> FragmentId {
         self.id
     }
 
-    pub fn atoms(&self) -> &Atoms {
+    pub fn atoms(&self) -> &[AtomRepr] {
         &self.atoms
     }
 
+    /// Returns `None` if the atoms haven't been uploaded yet or if there are no atoms.
+    pub(crate) fn gpu_atoms(&self) -> Option<&Atoms> {
+        self.gpu_atoms.as_ref()
+    }
+
+    /// Uploads the atoms to the gpu if that hasn't been done yet.
+    pub(crate) fn upload(&mut self, gpu_resources: &GlobalRenderResources) {
+        if self.gpu_atoms.is_none() && !self.atoms.is_empty() {
+            self.gpu_atoms = Some(Atoms::new(
+                gpu_resources,
+                self.id,
+                self.atoms.iter().copied(),
+            ));
+            self.colors_outdated = true;
+            self.atoms_outdated = false;
+        }
+    }
+
+    /// Writes the atoms to the gpu again if they changed since they were uploaded.
+    pub(crate) fn sync_atoms(&mut self, gpu_resources: &GlobalRenderResources) {
+        if let (true, Some(gpu_atoms)) = (self.atoms_outdated, &mut self.gpu_atoms) {
+            gpu_atoms.write_atoms(gpu_resources, &self.atoms);
+            self.atoms_outdated = false;
+        }
+    }
+
+    /// Changes the element of the atom at `index`, which updates its color and radius the
+    /// next time the fragment is drawn.
+    pub fn set_element(&mut self, index: usize, element: Element) {
+        let kind = &mut self.atoms[index].kind;
+        if kind.element() != element {
+            kind.set_element(element);
+            self.atoms_outdated = true;
+        }
+    }
+
+    /// Moves the atoms, in the same order as `atoms`, which are relative to the fragment.
+    pub fn set_positions<I>(&mut self, positions: I)
+    where
+        I: IntoIterator<Item = Vec3>,
+    {
+        let mut count = 0;
+        for (atom, pos) in self.atoms.iter_mut().zip(positions) {
+            atom.pos = pos;
+            count += 1;
+        }
+        assert_eq!(
+            count,
+            self.atoms.len(),
+            "there must be a position for every atom"
+        );
+
+        self.bounding_box = BoundingBox::from_points(self.atoms.iter().map(|atom| atom.pos));
+        self.atoms_outdated = true;
+    }
+
+    /// Hides every atom that `predicate` returns true for.
+    ///
+    /// Visibility only affects drawing: hidden atoms are still part of the fragment.
+    pub fn hide_where<F>(&mut self, mut predicate: F)
+    where
+        F: FnMut(&AtomRepr) -> bool,
+    {
+        for atom in &mut self.atoms {
+            if atom.kind.is_visible() && predicate(atom) {
+                atom.kind.set_visible(false);
+                self.atoms_outdated = true;
+            }
+        }
+    }
+
+    pub fn hide_element(&mut self, element: Element) {
+        self.hide_where(|atom| atom.kind.element() == element);
+    }
+
+    pub fn hide_all(&mut self) {
+        self.hide_where(|_| true);
+    }
+
+    pub fn show_all(&mut self) {
+        for atom in &mut self.atoms {
+            if !atom.kind.is_visible() {
+                atom.kind.set_visible(true);
+                self.atoms_outdated = true;
+            }
+        }
+    }
+
+    pub fn scalars(&self) -> Option<&[f32]> {
+        self.scalars.as_deref()
+    }
+
+    /// Sets a value for each atom, which `ColorMode::Scalar` colors them by.
+    pub fn set_scalars(&mut self, scalars: Vec<f32>) {
+        assert_eq!(
+            scalars.len(),
+            self.atoms.len(),
+            "there must be a scalar for every atom"
+        );
+        self.scalars = Some(scalars);
+        self.colors_outdated = true;
+    }
+
+    pub fn clear_scalars(&mut self) {
+        self.scalars = None;
+        self.colors_outdated = true;
+    }
+
     pub fn offset(&self) -> Vec3 {
         self.offset
     }
@@ -106,11 +224,30 @@ impl Fragment {
         self.rotation
     }
 
+    /// A sphere in world space around the centers of every atom.
+    ///
+    /// Not meaningful for an empty fragment.
+    pub(crate) fn bounding_sphere(&self, part: &Part) -> (Vec3, f32) {
+        let offset = part.offset + self.offset;
+        let rotation = part.rotation * self.rotation;
+
+        let center = rotation * self.bounding_box.center() + offset;
+        (center, self.bounding_box.size().mag() / 2.0)
+    }
+
     pub fn copy_new(&self, render_resources: &GlobalRenderResources) -> Self {
         let id = FragmentId::new();
         Self {
             id,
-            atoms: self.atoms.copy_new(render_resources, id),
+            atoms: self.atoms.clone(),
+            gpu_atoms: self
+                .gpu_atoms
+                .as_ref()
+                .map(|atoms| atoms.copy_new(render_resources, id)),
+            scalars: self.scalars.clone(),
+            colors_outdated: true,
+            // The copy is made from the gpu atoms, so it has to be synced just like the original.
+            atoms_outdated: self.atoms_outdated,
             ..*self
         }
     }
@@ -124,6 +261,7 @@ pub struct Part {
     center: Vec3,
     offset: Vec3,
     rotation: Rotor3,
+    visible: bool,
 }
 
 impl Part {
@@ -133,10 +271,7 @@ impl Part {
         I: IntoIterator<Item = Fragment>,
         I::IntoIter: ExactSizeIterator,
     {
-        let mut bounding_box = BoundingBox {
-            min: Vec3::new(-f32::INFINITY, -f32::INFINITY, -f32::INFINITY),
-            max: Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
-        };
+        let mut bounding_box = BoundingBox::default();
         let mut center = Vec3::zero();
         let part_id = PartId::new();
 
@@ -164,6 +299,7 @@ impl Part {
             center,
             offset: Vec3::zero(),
             rotation: Rotor3::default(),
+            visible: true,
         }
     }
 
@@ -171,6 +307,10 @@ impl Part {
         &self.name
     }
 
+    pub fn set_name<S: ToString>(&mut self, name: S) {
+        self.name = name.to_string();
+    }
+
     pub fn id(&self) -> PartId {
         self.id
     }
@@ -187,6 +327,15 @@ impl Part {
         self.rotation
     }
 
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Hiding a part skips drawing it entirely, without changing which of its atoms are hidden.
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
     pub fn offset_by(&mut self, x: f32, y: f32, z: f32) {
         self.offset += Vec3::new(x, y, z);
     }
@@ -195,6 +344,12 @@ impl Part {
         self.offset = Vec3::new(x, y, z) - self.center;
     }
 
+    /// Rotates the whole part around `pivot`, which is in world space.
+    pub fn rotate_about(&mut self, rotation: Rotor3, pivot: Vec3) {
+        self.rotation = rotation * self.rotation;
+        self.offset = rotation * (self.offset - pivot) + pivot;
+    }
+
     /// Takes angles in degrees.
     pub fn rotate_by(&mut self, roll: f32, pitch: f32, yaw: f32) {
         self.rotation =
@@ -213,6 +368,11 @@ pub struct World {
     pub(crate) added_fragments: Vec<(PartId, FragmentId)>,
     pub(crate) modified_parts: Vec<PartId>,
     pub(crate) modified_fragments: Vec<FragmentId>,
+    pub(crate) removed_fragments: Vec<FragmentId>,
+
+    // Bumped by anything that might add, remove, or move atoms.
+    changes: u64,
+    saved_changes: u64,
 }
 
 impl World {
@@ -225,9 +385,29 @@ impl World {
             added_fragments: Vec::new(),
             modified_parts: Vec::new(),
             modified_fragments: Vec::new(),
+            removed_fragments: Vec::new(),
+
+            changes: 0,
+            saved_changes: 0,
         }
     }
 
+    /// Changes whenever atoms might have been added, removed, or moved, so anything worked out
+    /// from them can tell when it's out of date.
+    pub fn change_count(&self) -> u64 {
+        self.changes
+    }
+
+    /// Whether anything has changed since the world was created or last saved.
+    pub fn is_dirty(&self) -> bool {
+        self.changes != self.saved_changes
+    }
+
+    /// Call this once the world has been written out, so it isn't dirty anymore.
+    pub fn mark_saved(&mut self) {
+        self.saved_changes = self.changes;
+    }
+
     // pub fn split_empty(&self) -> Self {
     //     Self::new(Arc::clone(&self.shared_render))
     // }
@@ -247,9 +427,9 @@ impl World {
             let fragment = &self.fragments[fragment_id];
             let fragment = fragment.copy_new(render_resources);
 
+            // These are uploaded along with the new part, so they aren't added on their own.
             let id = fragment.id;
             self.fragments.insert(id, fragment);
-            self.added_fragments.push((part_id, id));
 
             fragments.push(id);
         }
@@ -268,6 +448,7 @@ impl World {
         let id = part.id;
         assert!(self.parts.insert(id, part).is_none());
         self.added_parts.push(id);
+        self.changes += 1;
         id
     }
 
@@ -275,9 +456,31 @@ impl World {
         let id = fragment.id;
         self.fragments.insert(id, fragment);
         self.added_fragments.push((part_id, id));
+        self.changes += 1;
         id
     }
 
+    /// Removes a part and all of its fragments.
+    pub fn remove_part(&mut self, part_id: PartId) -> Option<Part> {
+        let part = self.parts.shift_remove(&part_id)?;
+        self.changes += 1;
+
+        for fragment_id in &part.fragments {
+            self.fragments.shift_remove(fragment_id);
+            self.removed_fragments.push(*fragment_id);
+        }
+
+        // Anything that hasn't been uploaded yet doesn't need to be anymore.
+        self.added_parts.retain(|&id| id != part_id);
+        self.added_fragments.retain(|&(id, _)| id != part_id);
+        self.modified_parts.retain(|&id| id != part_id);
+        let fragments = &part.fragments;
+        self.modified_fragments
+            .retain(|fragment_id| !fragments.contains(fragment_id));
+
+        Some(part)
+    }
+
     pub fn merge(&mut self, other: World) {
         self.parts.extend(other.parts);
         self.fragments.extend(other.fragments);
@@ -286,11 +489,26 @@ impl World {
         self.added_fragments.extend(other.added_fragments);
         self.modified_parts.extend(other.modified_parts);
         self.modified_fragments.extend(other.modified_fragments);
+        self.removed_fragments.extend(other.removed_fragments);
+        self.changes += 1;
+    }
+
+    pub fn part(&self, id: PartId) -> Option<&Part> {
+        self.parts.get(&id)
+    }
+
+    /// Shows or hides a part. Unlike going through `part_mut`, this isn't counted as a change,
+    /// since it doesn't touch any atoms.
+    pub fn set_part_visible(&mut self, id: PartId, visible: bool) {
+        if let Some(part) = self.parts.get_mut(&id) {
+            part.set_visible(visible);
+        }
     }
 
     pub fn part_mut(&mut self, id: PartId) -> &mut Part {
         let part = &mut self.parts[&id];
         self.modified_parts.push(id);
+        self.changes += 1;
         part
     }
 
@@ -307,6 +525,7 @@ impl World {
     }
 
     pub fn parts_mut(&mut self) -> impl ExactSizeIterator<Item = &mut Part> {
+        self.changes += 1;
         self.parts.values_mut()
     }
 
@@ -315,6 +534,180 @@ impl World {
     }
 
     pub fn fragments_mut(&mut self) -> impl ExactSizeIterator<Item = &mut Fragment> {
+        self.changes += 1;
         self.fragments.values_mut()
     }
+
+    /// Hides every atom in the selected fragments.
+    pub fn hide_selection(&mut self, interactions: &Interactions) {
+        for fragment_id in &interactions.selected_fragments {
+            if let Some(fragment) = self.fragments.get_mut(fragment_id) {
+                fragment.hide_all();
+            }
+        }
+    }
+
+    pub fn hide_element(&mut self, element: Element) {
+        for fragment in self.fragments.values_mut() {
+            fragment.hide_element(element);
+        }
+    }
+
+    pub fn show_all(&mut self) {
+        for fragment in self.fragments.values_mut() {
+            fragment.show_all();
+        }
+    }
+
+    /// Iterates over every atom, transformed into world space.
+    ///
+    /// This includes hidden atoms. Every atom in a hidden part counts as hidden.
+    pub fn world_atoms(&self) -> impl Iterator<Item = AtomRepr> + '_ {
+        self.parts
+            .values()
+            .flat_map(move |part| self.part_atoms(part))
+    }
+
+    /// Iterates over the atoms in one part, the same way as `world_atoms`.
+    pub fn part_atoms<'a>(&'a self, part: &'a Part) -> impl Iterator<Item = AtomRepr> + 'a {
+        part.fragments.iter().flat_map(move |fragment_id| {
+            let fragment = &self.fragments[fragment_id];
+
+            let offset = part.offset + fragment.offset;
+            let rotation = part.rotation * fragment.rotation;
+
+            fragment.atoms.iter().map(move |atom| {
+                let mut kind = atom.kind;
+                if !part.visible {
+                    kind.set_visible(false);
+                }
+
+                AtomRepr {
+                    pos: rotation * atom.pos + offset,
+                    kind,
+                }
+            })
+        })
+    }
+
+    /// Moves every atom, in the same order as `world_atoms`. Takes positions in world space.
+    pub fn set_world_positions(&mut self, positions: &[Vec3]) {
+        let mut positions = positions.iter();
+        self.changes += 1;
+
+        for part in self.parts.values() {
+            for fragment_id in &part.fragments {
+                let fragment = &mut self.fragments[fragment_id];
+
+                let offset = part.offset + fragment.offset;
+                let inverse_rotation = (part.rotation * fragment.rotation).reversed();
+                let local: Vec<_> = positions
+                    .by_ref()
+                    .take(fragment.atoms.len())
+                    .map(|&pos| inverse_rotation * (pos - offset))
+                    .collect();
+
+                fragment.set_positions(local);
+            }
+        }
+
+        assert!(
+            positions.next().is_none(),
+            "there must be a position for every atom"
+        );
+    }
+
+    /// Changes the element of one atom, which is indexed in the same order as `world_atoms`.
+    pub fn set_world_element(&mut self, index: usize, element: Element) {
+        let mut index = index;
+
+        for part in self.parts.values() {
+            for fragment_id in &part.fragments {
+                let fragment = &mut self.fragments[fragment_id];
+                if index < fragment.atoms.len() {
+                    fragment.set_element(index, element);
+                    self.changes += 1;
+                    return;
+                }
+                index -= fragment.atoms.len();
+            }
+        }
+
+        panic!("there's no atom at that index");
+    }
+
+    /// Sets a scalar for every atom, in the same order as `world_atoms`.
+    pub fn set_world_scalars(&mut self, scalars: &[f32]) {
+        let mut scalars = scalars.iter().copied();
+
+        for part in self.parts.values() {
+            for fragment_id in &part.fragments {
+                let fragment = &mut self.fragments[fragment_id];
+                let count = fragment.atoms.len();
+                fragment.set_scalars(scalars.by_ref().take(count).collect());
+            }
+        }
+
+        assert!(
+            scalars.next().is_none(),
+            "there must be a scalar for every atom"
+        );
+    }
+
+    pub fn clear_scalars(&mut self) {
+        for fragment in self.fragments.values_mut() {
+            fragment.clear_scalars();
+        }
+    }
+
+    /// Meshes the union of the van der Waals spheres of every atom.
+    ///
+    /// `resolution` is the grid spacing in angstroms.
+    pub fn vdw_surface_mesh(&self, periodic_table: &PeriodicTable, resolution: f32) -> Mesh {
+        mesh::vdw_surface(self.world_atoms(), periodic_table, resolution)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atoms::AtomKind;
+
+    fn world_with_part() -> (World, PartId) {
+        let mut world = World::new();
+        let atoms = vec![AtomRepr {
+            pos: Vec3::zero(),
+            kind: AtomKind::new(Element::Carbon),
+        }];
+        let part = Part::from_fragments(&mut world, "Carbon", vec![Fragment::new(atoms)]);
+        let id = part.id();
+        world.spawn_part(part);
+        (world, id)
+    }
+
+    #[test]
+    fn new_world_is_clean() {
+        assert!(!World::new().is_dirty());
+    }
+
+    #[test]
+    fn saving_marks_the_world_clean() {
+        let (mut world, id) = world_with_part();
+        assert!(world.is_dirty());
+
+        world.mark_saved();
+        assert!(!world.is_dirty());
+
+        world.part_mut(id).offset_by(1.0, 0.0, 0.0);
+        assert!(world.is_dirty());
+    }
+
+    #[test]
+    fn hiding_parts_is_not_a_change() {
+        let (mut world, id) = world_with_part();
+        world.mark_saved();
+
+        world.set_part_visible(id, false);
+        assert!(!world.is_dirty());
+    }
 }
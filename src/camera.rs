@@ -1,13 +1,36 @@
-use common::InputEvent;
-use render::{Camera, CameraRepr};
+use common::{InputEvent, Navigation};
+use instant::Instant;
+use render::{BoundingBox, Camera, CameraRepr, World};
+use std::time::Duration;
 use ultraviolet::{projection, Mat4, Vec3};
 use winit::{
     dpi::LogicalPosition,
-    event::{DeviceEvent, ElementState, MouseButton, MouseScrollDelta, WindowEvent},
+    event::{
+        DeviceEvent, ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta,
+        VirtualKeyCode, WindowEvent,
+    },
 };
 
 const PI: f32 = std::f32::consts::PI;
 
+/// How long snapping to a standard view or a bookmark takes.
+const TRANSITION_TIME: Duration = Duration::from_millis(200);
+/// How far one press of an arrow key orbits.
+const ORBIT_STEP: f32 = PI / 36.0;
+/// How far one press of WASD pans, as a fraction of the distance to the target.
+const PAN_STEP: f32 = 0.05;
+/// How much one press of + or - dollies, as a fraction of the distance to the target.
+const DOLLY_STEP: f32 = 0.1;
+const MAX_PITCH: f32 = (PI / 2.0) - 0.001;
+/// How fast a stick pushed all the way orbits, in radians per second.
+const ORBIT_SPEED: f32 = PI / 2.0;
+/// How fast a stick pushed all the way pans, as a fraction of the distance to the target per
+/// second.
+const PAN_SPEED: f32 = 0.5;
+/// How fast a trigger pulled all the way dollies. The distance changes by a factor of e every
+/// second at this speed.
+const DOLLY_SPEED: f32 = 1.0;
+
 #[inline]
 fn clamp(mut x: f32, min: f32, max: f32) -> f32 {
     assert!(min <= max);
@@ -20,14 +43,86 @@ fn clamp(mut x: f32, min: f32, max: f32) -> f32 {
     x
 }
 
+/// Everything needed to restore where the camera is looking from.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct View {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+    pub target: Vec3,
+}
+
+impl View {
+    fn eye(&self) -> Vec3 {
+        self.target
+            + self.distance
+                * Vec3::new(
+                    self.yaw.sin() * self.pitch.cos(),
+                    self.yaw.cos() * self.pitch.cos(),
+                    self.pitch.sin(),
+                )
+    }
+
+    /// Yaw goes the short way around.
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        let mut dyaw = (other.yaw - self.yaw) % (PI * 2.0);
+        if dyaw > PI {
+            dyaw -= PI * 2.0;
+        } else if dyaw < -PI {
+            dyaw += PI * 2.0;
+        }
+
+        Self {
+            yaw: self.yaw + dyaw * t,
+            pitch: self.pitch + (other.pitch - self.pitch) * t,
+            distance: self.distance + (other.distance - self.distance) * t,
+            target: self.target + (other.target - self.target) * t,
+        }
+    }
+}
+
+/// How analog devices move the camera.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct NavigationSettings {
+    /// Scales every speed.
+    pub sensitivity: f32,
+    /// Pushing up orbits down, like a flight stick.
+    pub invert_y: bool,
+}
+
+impl Default for NavigationSettings {
+    fn default() -> Self {
+        Self {
+            sensitivity: 1.0,
+            invert_y: false,
+        }
+    }
+}
+
+struct Transition {
+    from: View,
+    to: View,
+    start: Instant,
+}
+
 pub struct ArcballCamera {
     camera: CameraRepr,
 
     mouse_button_pressed: bool,
-    yaw: f32,
-    pitch: f32,
-    distance: f32,
+    view: View,
     speed: f32,
+
+    fov: f32,
+    aspect: f32,
+    modifiers: ModifiersState,
+    // A sphere around the structure, for zooming to fit.
+    bounds: Option<(Vec3, f32)>,
+    transition: Option<Transition>,
+    /// Saved with Ctrl+Shift+1..9 and restored with Ctrl+1..9.
+    bookmarks: [Option<View>; 9],
+    navigation: NavigationSettings,
+    // Zooms to fit the bounds right away, once the field of view is known.
+    fit_on_resize: bool,
 }
 
 impl ArcballCamera {
@@ -35,26 +130,229 @@ impl ArcballCamera {
         Self {
             camera: CameraRepr::default(),
             mouse_button_pressed: false,
-            yaw: 0.0,
-            pitch: 0.0,
-            distance,
+            view: View {
+                yaw: 0.0,
+                pitch: 0.0,
+                distance,
+                target: Vec3::zero(),
+            },
             speed,
+
+            fov: PI / 2.0,
+            aspect: 1.0,
+            modifiers: ModifiersState::empty(),
+            bounds: None,
+            transition: None,
+            bookmarks: [None; 9],
+            navigation: NavigationSettings::default(),
+            fit_on_resize: false,
         }
     }
 
+    /// Lets Home zoom to fit a sphere with this center and radius.
+    pub fn with_bounds(mut self, center: Vec3, radius: f32) -> Self {
+        self.bounds = Some((center, radius));
+        self
+    }
+
+    /// Starts out zoomed to fit the bounds, instead of at the distance it was created with.
+    pub fn zoomed_to_fit(mut self) -> Self {
+        self.fit_on_resize = true;
+        self
+    }
+
+    pub fn with_navigation(mut self, navigation: NavigationSettings) -> Self {
+        self.navigation = navigation;
+        self
+    }
+
     fn add_yaw(&mut self, dyaw: f32) {
-        self.yaw = (self.yaw + dyaw) % (PI * 2.0);
+        self.view.yaw = (self.view.yaw + dyaw) % (PI * 2.0);
     }
 
     fn add_pitch(&mut self, dpitch: f32) {
-        self.pitch = clamp(self.pitch + dpitch, (-PI / 2.0) + 0.001, (PI / 2.0) - 0.001);
+        self.view.pitch = clamp(self.view.pitch + dpitch, -MAX_PITCH, MAX_PITCH);
+    }
+
+    /// Moves the target within the plane facing the camera, by fractions of the distance to
+    /// it.
+    fn pan(&mut self, right: f32, up: f32) {
+        let forward = (self.view.target - self.view.eye()).normalized();
+        let right_axis = forward.cross(Vec3::unit_z()).normalized();
+        let up_axis = right_axis.cross(forward);
+
+        self.view.target += (right_axis * right + up_axis * up) * self.view.distance;
+    }
+
+    /// Returns true if the view changed.
+    fn navigate(&mut self, navigation: Navigation) -> bool {
+        if navigation.is_zero() {
+            return false;
+        }
+
+        let speed = self.navigation.sensitivity;
+        // The sticks work like dragging with the mouse, so pushing up pitches the view down.
+        let pitch = if self.navigation.invert_y {
+            navigation.pitch
+        } else {
+            -navigation.pitch
+        };
+
+        self.add_yaw(navigation.yaw * ORBIT_SPEED * speed);
+        self.add_pitch(pitch * ORBIT_SPEED * speed);
+        self.pan(
+            navigation.pan_right * PAN_SPEED * speed,
+            navigation.pan_up * PAN_SPEED * speed,
+        );
+        self.view.distance =
+            (self.view.distance * (-navigation.dolly * DOLLY_SPEED * speed).exp()).max(0.001);
+
+        self.transition = None;
+        true
+    }
+
+    fn animate_to(&mut self, to: View) {
+        self.transition = Some(Transition {
+            from: self.view,
+            to,
+            start: Instant::now(),
+        });
+    }
+
+    fn zoom_to_fit(&mut self) {
+        if let Some(view) = self.fitted_view() {
+            self.animate_to(view);
+        }
+    }
+
+    fn fitted_view(&self) -> Option<View> {
+        let (center, radius) = self.bounds?;
+        // The narrower of the two fields of view has to fit the sphere.
+        let half_fov = (self.fov / 2.0)
+            .min(((self.fov / 2.0).tan() * self.aspect).atan())
+            .max(0.01);
+
+        Some(View {
+            target: center,
+            distance: radius / half_fov.sin(),
+            ..self.view
+        })
+    }
+
+    /// Looks along one of the axes: front, back, right, left, top, and bottom.
+    fn standard_view(&mut self, index: usize) {
+        let (yaw, pitch) = match index {
+            0 => (PI, 0.0),
+            1 => (0.0, 0.0),
+            2 => (PI / 2.0, 0.0),
+            3 => (-PI / 2.0, 0.0),
+            4 => (0.0, MAX_PITCH),
+            _ => (0.0, -MAX_PITCH),
+        };
+
+        self.animate_to(View {
+            yaw,
+            pitch,
+            ..self.view
+        });
+    }
+
+    /// Returns true if the view changed.
+    fn key_pressed(&mut self, key: VirtualKeyCode) -> bool {
+        let number = match key {
+            VirtualKeyCode::Key1 => Some(0),
+            VirtualKeyCode::Key2 => Some(1),
+            VirtualKeyCode::Key3 => Some(2),
+            VirtualKeyCode::Key4 => Some(3),
+            VirtualKeyCode::Key5 => Some(4),
+            VirtualKeyCode::Key6 => Some(5),
+            VirtualKeyCode::Key7 => Some(6),
+            VirtualKeyCode::Key8 => Some(7),
+            VirtualKeyCode::Key9 => Some(8),
+            _ => None,
+        };
+
+        if let Some(number) = number {
+            if self.modifiers.ctrl() && self.modifiers.shift() {
+                self.bookmarks[number] = Some(self.view);
+                log::info!("saved the view as bookmark {}", number + 1);
+                return false;
+            } else if self.modifiers.ctrl() {
+                match self.bookmarks[number] {
+                    Some(view) => self.animate_to(view),
+                    None => log::info!("bookmark {} hasn't been saved", number + 1),
+                }
+                return false;
+            } else if number < 6 {
+                self.standard_view(number);
+                return false;
+            }
+        }
+
+        // The rest of the ctrl shortcuts belong to the app, like ctrl-D for duplicating.
+        if self.modifiers.ctrl() {
+            return false;
+        }
+
+        match key {
+            VirtualKeyCode::Left => self.add_yaw(-ORBIT_STEP),
+            VirtualKeyCode::Right => self.add_yaw(ORBIT_STEP),
+            VirtualKeyCode::Up => self.add_pitch(ORBIT_STEP),
+            VirtualKeyCode::Down => self.add_pitch(-ORBIT_STEP),
+            VirtualKeyCode::A => self.pan(-PAN_STEP, 0.0),
+            VirtualKeyCode::D => self.pan(PAN_STEP, 0.0),
+            VirtualKeyCode::W => self.pan(0.0, PAN_STEP),
+            VirtualKeyCode::S => self.pan(0.0, -PAN_STEP),
+            VirtualKeyCode::Equals | VirtualKeyCode::Add => {
+                self.view.distance = (self.view.distance * (1.0 - DOLLY_STEP)).max(0.001);
+            }
+            VirtualKeyCode::Minus | VirtualKeyCode::Subtract => {
+                self.view.distance *= 1.0 + DOLLY_STEP;
+            }
+            VirtualKeyCode::Home => {
+                self.zoom_to_fit();
+                return false;
+            }
+            _ => return false,
+        }
+
+        // Manual input always wins over a transition.
+        self.transition = None;
+        true
+    }
+
+    /// Returns true while a transition is running.
+    fn advance_transition(&mut self) -> bool {
+        let transition = match &self.transition {
+            Some(transition) => transition,
+            None => return false,
+        };
+
+        let t = transition.start.elapsed().as_secs_f32() / TRANSITION_TIME.as_secs_f32();
+        if t >= 1.0 {
+            self.view = transition.to;
+            self.transition = None;
+        } else {
+            let t = t * t * (3.0 - 2.0 * t); // smoothstep
+            self.view = transition.from.lerp(&transition.to, t);
+        }
+        true
     }
 }
 
 impl Camera for ArcballCamera {
     fn resize(&mut self, aspect: f32, fov: f32, near: f32) {
+        self.fov = fov;
+        self.aspect = aspect;
         self.camera.projection =
             projection::perspective_reversed_infinite_z_wgpu_dx_gl(fov, aspect, near);
+
+        if self.fit_on_resize {
+            self.fit_on_resize = false;
+            if let Some(view) = self.fitted_view() {
+                self.view = view;
+            }
+        }
     }
 
     fn update(&mut self, event: InputEvent) -> bool {
@@ -63,12 +361,15 @@ impl Camera for ArcballCamera {
                 WindowEvent::MouseWheel { delta, .. } => {
                     match delta {
                         MouseScrollDelta::LineDelta(_, delta) => {
-                            self.distance = (self.distance - delta * self.speed * 10.0).max(0.001);
+                            self.view.distance =
+                                (self.view.distance - delta * self.speed * 10.0).max(0.001);
                         }
                         MouseScrollDelta::PixelDelta(LogicalPosition { y, .. }) => {
-                            self.distance = (self.distance - y as f32 * self.speed).max(0.001);
+                            self.view.distance =
+                                (self.view.distance - y as f32 * self.speed).max(0.001);
                         }
                     }
+                    self.transition = None;
                     true
                 }
                 WindowEvent::MouseInput { state, button, .. } => {
@@ -85,6 +386,19 @@ impl Camera for ArcballCamera {
                     self.mouse_button_pressed = false;
                     false
                 }
+                WindowEvent::ModifiersChanged(modifiers) => {
+                    self.modifiers = modifiers;
+                    false
+                }
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(key),
+                            ..
+                        },
+                    ..
+                } => self.key_pressed(key),
                 _ => false,
             },
             InputEvent::Device(event) => match event {
@@ -92,6 +406,7 @@ impl Camera for ArcballCamera {
                     if self.mouse_button_pressed {
                         self.add_yaw(x as f32 / 200.0);
                         self.add_pitch(y as f32 / 200.0);
+                        self.transition = None;
                         true
                     } else {
                         false
@@ -99,20 +414,20 @@ impl Camera for ArcballCamera {
                 }
                 _ => false,
             },
-            InputEvent::BeginningFrame => false,
+            InputEvent::Navigate(navigation) => self.navigate(navigation),
+            InputEvent::Bounds(bounds) => {
+                self.bounds = bounds;
+                false
+            }
+            InputEvent::BeginningFrame => self.advance_transition(),
         }
     }
 
     fn finalize(&mut self) {
-        let eye = self.distance
-            * Vec3::new(
-                self.yaw.sin() * self.pitch.cos(),
-                self.yaw.cos() * self.pitch.cos(),
-                self.pitch.sin(),
-            );
+        let eye = self.view.eye();
 
         // let eye = self.rotor * (self.distance * Vec3::unit_z());
-        self.camera.view = Mat4::look_at(eye, Vec3::zero(), Vec3::unit_z());
+        self.camera.view = Mat4::look_at(eye, self.view.target, Vec3::unit_z());
         self.camera.projection_view = self.camera.projection * self.camera.view;
     }
 
@@ -120,3 +435,159 @@ impl Camera for ArcballCamera {
         self.camera.clone()
     }
 }
+
+/// A sphere around every atom, padded enough to include their radii.
+pub fn bounding_sphere(world: &World) -> Option<(Vec3, f32)> {
+    let bounding_box = BoundingBox::from_points(world.world_atoms().map(|atom| atom.pos));
+    if bounding_box.is_empty() {
+        return None;
+    }
+
+    Some((bounding_box.center(), bounding_box.size().mag() / 2.0 + 2.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1.0e-4, "{} != {}", a, b);
+    }
+
+    fn view(yaw: f32, pitch: f32, distance: f32) -> View {
+        View {
+            yaw,
+            pitch,
+            distance,
+            target: Vec3::new(1.0, 2.0, 3.0),
+        }
+    }
+
+    fn finish_transition(camera: &mut ArcballCamera) {
+        let transition = camera.transition.take().expect("no transition is running");
+        camera.view = transition.to;
+    }
+
+    #[test]
+    fn lerp_starts_and_ends_at_its_views() {
+        let from = view(0.5, 0.2, 10.0);
+        let to = View {
+            target: Vec3::new(-4.0, 0.0, 1.0),
+            ..view(-1.0, -0.6, 30.0)
+        };
+
+        assert_eq!(from.lerp(&to, 0.0), from);
+        let end = from.lerp(&to, 1.0);
+        assert_close(end.yaw, to.yaw);
+        assert_close(end.pitch, to.pitch);
+        assert_close(end.distance, to.distance);
+        assert!((end.target - to.target).mag() < 1.0e-4);
+    }
+
+    #[test]
+    fn lerp_turns_the_short_way_around() {
+        let from = view(PI - 0.1, 0.0, 10.0);
+        let to = view(-PI + 0.1, 0.0, 10.0);
+
+        // Halfway is behind, at a yaw of pi, rather than in front at zero.
+        assert_close(from.lerp(&to, 0.5).yaw.abs(), PI);
+        assert_close((from.lerp(&to, 1.0).yaw - to.yaw).rem_euclid(PI * 2.0), 0.0);
+    }
+
+    #[test]
+    fn fitted_view_fits_the_narrower_field_of_view() {
+        let mut camera = ArcballCamera::new(100.0, 1.0).with_bounds(Vec3::unit_x(), 10.0);
+        camera.fov = PI / 2.0;
+
+        camera.aspect = 2.0;
+        let wide = camera.fitted_view().unwrap();
+        assert_eq!(wide.target, Vec3::unit_x());
+        assert_close(wide.distance, 10.0 / (PI / 4.0).sin());
+
+        // In a tall window, the horizontal field of view is the narrow one.
+        camera.aspect = 0.5;
+        let half_width = (0.5f32).atan();
+        assert_close(
+            camera.fitted_view().unwrap().distance,
+            10.0 / half_width.sin(),
+        );
+    }
+
+    #[test]
+    fn new_bounds_change_the_fitted_view() {
+        let mut camera = ArcballCamera::new(100.0, 1.0);
+        assert_eq!(camera.fitted_view(), None);
+
+        camera.update(InputEvent::Bounds(Some((Vec3::unit_z(), 5.0))));
+        assert_eq!(camera.fitted_view().unwrap().target, Vec3::unit_z());
+
+        camera.update(InputEvent::Bounds(None));
+        assert_eq!(camera.fitted_view(), None);
+    }
+
+    #[test]
+    fn bookmarks_are_saved_and_recalled() {
+        let mut camera = ArcballCamera::new(100.0, 1.0);
+        let saved = view(1.0, 0.3, 25.0);
+        camera.view = saved;
+
+        camera.modifiers = ModifiersState::CTRL | ModifiersState::SHIFT;
+        camera.key_pressed(VirtualKeyCode::Key3);
+        assert!(camera.transition.is_none());
+
+        camera.view = view(-2.0, -0.5, 5.0);
+        camera.modifiers = ModifiersState::CTRL;
+        camera.key_pressed(VirtualKeyCode::Key3);
+        finish_transition(&mut camera);
+        assert_eq!(camera.view, saved);
+
+        // Nothing was saved in this one.
+        camera.key_pressed(VirtualKeyCode::Key4);
+        assert!(camera.transition.is_none());
+    }
+
+    #[test]
+    fn standard_views_look_along_each_axis() {
+        let mut camera = ArcballCamera::new(100.0, 1.0);
+        let keys = [
+            VirtualKeyCode::Key1,
+            VirtualKeyCode::Key2,
+            VirtualKeyCode::Key3,
+            VirtualKeyCode::Key4,
+            VirtualKeyCode::Key5,
+            VirtualKeyCode::Key6,
+        ];
+        let axes = [
+            -Vec3::unit_y(),
+            Vec3::unit_y(),
+            Vec3::unit_x(),
+            -Vec3::unit_x(),
+            Vec3::unit_z(),
+            -Vec3::unit_z(),
+        ];
+
+        for (&key, &axis) in keys.iter().zip(&axes) {
+            camera.key_pressed(key);
+            finish_transition(&mut camera);
+
+            assert_close(camera.view.distance, 100.0);
+            let from_target = (camera.view.eye() - camera.view.target).normalized();
+            assert!(
+                from_target.dot(axis) > 0.999,
+                "{:?} for {:?}",
+                from_target,
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn manual_input_interrupts_a_transition() {
+        let mut camera = ArcballCamera::new(100.0, 1.0);
+        camera.key_pressed(VirtualKeyCode::Key5);
+        assert!(camera.transition.is_some());
+
+        assert!(camera.key_pressed(VirtualKeyCode::Left));
+        assert!(camera.transition.is_none());
+    }
+}
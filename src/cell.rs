@@ -0,0 +1,177 @@
+//! Crystal unit cells.
+
+use ultraviolet::{Mat3, Vec3};
+
+/// The lengths are in angstroms and the angles are in degrees, the same as in pdb and mmCIF
+/// files. `alpha` is between `b` and `c`, `beta` is between `a` and `c`, and `gamma` is between
+/// `a` and `b`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct UnitCell {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub alpha: f32,
+    pub beta: f32,
+    pub gamma: f32,
+}
+
+impl UnitCell {
+    /// Returns `None` if the cell wouldn't have any volume.
+    pub fn new(a: f32, b: f32, c: f32, alpha: f32, beta: f32, gamma: f32) -> Option<Self> {
+        let cell = Self {
+            a,
+            b,
+            c,
+            alpha,
+            beta,
+            gamma,
+        };
+
+        let lengths_valid = [a, b, c].iter().all(|&length| length > 0.0);
+        let angles_valid = [alpha, beta, gamma]
+            .iter()
+            .all(|&angle| angle > 0.0 && angle < 180.0);
+        if lengths_valid && angles_valid && cell.matrix().determinant() > 1.0e-6 {
+            Some(cell)
+        } else {
+            None
+        }
+    }
+
+    /// Takes the cell vectors as columns.
+    pub fn from_matrix(matrix: Mat3) -> Self {
+        let [a, b, c] = matrix.cols;
+        let angle = |u: Vec3, v: Vec3| {
+            (u.dot(v) / (u.mag() * v.mag()))
+                .max(-1.0)
+                .min(1.0)
+                .acos()
+                .to_degrees()
+        };
+
+        Self {
+            a: a.mag(),
+            b: b.mag(),
+            c: c.mag(),
+            alpha: angle(b, c),
+            beta: angle(a, c),
+            gamma: angle(a, b),
+        }
+    }
+
+    /// The cell vectors as columns, in the usual orientation: `a` is along x and `b` is in the
+    /// xy plane.
+    pub fn matrix(&self) -> Mat3 {
+        let (alpha, beta, gamma) = (
+            self.alpha.to_radians(),
+            self.beta.to_radians(),
+            self.gamma.to_radians(),
+        );
+
+        let c_x = self.c * beta.cos();
+        let c_y = self.c * (alpha.cos() - beta.cos() * gamma.cos()) / gamma.sin();
+        let c_z = (self.c * self.c - c_x * c_x - c_y * c_y).max(0.0).sqrt();
+
+        Mat3::new(
+            Vec3::new(self.a, 0.0, 0.0),
+            Vec3::new(self.b * gamma.cos(), self.b * gamma.sin(), 0.0),
+            Vec3::new(c_x, c_y, c_z),
+        )
+    }
+
+    pub fn to_cartesian(&self, fractional: Vec3) -> Vec3 {
+        self.matrix() * fractional
+    }
+
+    pub fn to_fractional(&self, cartesian: Vec3) -> Vec3 {
+        self.matrix().inversed() * cartesian
+    }
+
+    /// Moves a position by whole cell vectors until it's inside the cell at the origin.
+    pub fn wrap(&self, cartesian: Vec3) -> Vec3 {
+        self.to_cartesian(wrap_fractional(self.to_fractional(cartesian)))
+    }
+}
+
+/// Moves fractional coordinates into `[0, 1)`.
+pub fn wrap_fractional(fractional: Vec3) -> Vec3 {
+    let wrap = |fraction: f32| {
+        let wrapped = fraction - fraction.floor();
+        // Tiny negative fractions round up to exactly one.
+        if wrapped >= 1.0 {
+            0.0
+        } else {
+            wrapped
+        }
+    };
+
+    Vec3::new(wrap(fractional.x), wrap(fractional.y), wrap(fractional.z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triclinic() -> UnitCell {
+        UnitCell::new(5.0, 6.0, 7.0, 80.0, 95.0, 110.0).unwrap()
+    }
+
+    #[test]
+    fn fractional_round_trip() {
+        let cell = triclinic();
+
+        for &fractional in &[
+            Vec3::new(0.1, 0.2, 0.3),
+            Vec3::new(0.9, 0.0, 0.5),
+            Vec3::new(-1.5, 2.25, 0.75),
+        ] {
+            let round_trip = cell.to_fractional(cell.to_cartesian(fractional));
+            assert!((round_trip - fractional).mag() < 1.0e-5);
+        }
+    }
+
+    #[test]
+    fn matrix_matches_the_parameters() {
+        let cell = triclinic();
+        let round_trip = UnitCell::from_matrix(cell.matrix());
+
+        for &(a, b) in &[
+            (cell.a, round_trip.a),
+            (cell.b, round_trip.b),
+            (cell.c, round_trip.c),
+            (cell.alpha, round_trip.alpha),
+            (cell.beta, round_trip.beta),
+            (cell.gamma, round_trip.gamma),
+        ] {
+            assert!((a - b).abs() < 1.0e-3, "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn flat_cells_are_rejected() {
+        assert_eq!(UnitCell::new(5.0, 5.0, 0.0, 90.0, 90.0, 90.0), None);
+        assert_eq!(UnitCell::new(5.0, 5.0, 5.0, 90.0, 90.0, 180.0), None);
+        // The angles add up to a full turn, so all three vectors are in one plane.
+        assert_eq!(UnitCell::new(5.0, 5.0, 5.0, 120.0, 120.0, 120.0), None);
+    }
+
+    #[test]
+    fn wrap_fractional_is_in_range() {
+        let wrapped = wrap_fractional(Vec3::new(-0.25, 1.5, 3.0));
+        assert!((wrapped - Vec3::new(0.75, 0.5, 0.0)).mag() < 1.0e-6);
+
+        // This would round to exactly one without the special case.
+        let tiny = wrap_fractional(Vec3::new(-1.0e-9, 0.0, 0.0));
+        assert!(tiny.x >= 0.0 && tiny.x < 1.0);
+    }
+
+    #[test]
+    fn wrap_moves_by_whole_cell_vectors() {
+        let cell = triclinic();
+        let inside = cell.to_cartesian(Vec3::new(0.2, 0.4, 0.6));
+        let outside = inside + cell.to_cartesian(Vec3::new(-1.0, 2.0, -3.0));
+
+        assert!((cell.wrap(outside) - inside).mag() < 1.0e-4);
+        assert!((cell.wrap(inside) - inside).mag() < 1.0e-4);
+    }
+}
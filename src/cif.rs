@@ -0,0 +1,914 @@
+//! mmCIF (PDBx), which is what the PDB distributes now, and the older core CIF that
+//! crystallography databases use for small molecules and materials. This is read-only.
+//!
+//! Files for large structures run to hundreds of megabytes, so this reads one token at a time
+//! and only keeps what's in the `_atom_site` loop. Only the first model is read. Parts are
+//! chains and fragments are residues, like they are for pdb files.
+//!
+//! Core CIF files usually only list the asymmetric unit, in fractional coordinates, along with
+//! the symmetry operations of the space group. Those are applied to fill the whole unit cell,
+//! and each operation's copy of a residue is its own fragment. Files with Cartesian
+//! coordinates are left alone, since they already have the molecule the authors modeled.
+//!
+//! We don't have bonds yet, so `_struct_conn` and the chemical component dictionary are ignored.
+
+use crate::{
+    cell::{wrap_fractional, UnitCell},
+    structure::{Structure, StructurePart},
+};
+use periodic_table::Element;
+use render::{AtomKind, AtomRepr};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+use ultraviolet::Vec3;
+
+const CELL_FIELDS: [&str; 6] = [
+    "length_a",
+    "length_b",
+    "length_c",
+    "angle_alpha",
+    "angle_beta",
+    "angle_gamma",
+];
+/// Symmetry copies of an atom that are closer than this, in angstroms, are the same atom. This
+/// happens for atoms on a mirror plane or a rotation axis.
+const DUPLICATE_DISTANCE: f32 = 0.01;
+
+pub fn read_cif<P: AsRef<Path>>(name: &str, path: P) -> Result<Structure, String> {
+    let path = path.as_ref();
+    let file =
+        File::open(path).map_err(|e| format!("failed to read `{}`: {}", path.display(), e))?;
+    read(name, BufReader::new(file))
+}
+
+fn read<R: BufRead>(name: &str, reader: R) -> Result<Structure, String> {
+    let mut tokens = Tokenizer::new(reader);
+
+    let mut atoms = None;
+    let mut cell = [None; 6];
+    let mut symmetry = Vec::new();
+    while let Some(token) = tokens.next_token()? {
+        match token {
+            Token::Data(_) => {
+                // Every structure is in its own data block, and we only want the first.
+                if atoms.is_some() {
+                    break;
+                }
+            }
+            Token::Tag(tag) => {
+                let tag = tag.to_ascii_lowercase();
+                let value = match tokens.next_value()? {
+                    Some(value) => value,
+                    None => return Err(tokens.error(&format!("`{}` has no value", tag))),
+                };
+
+                if let Some(index) = field(&tag, "_cell")
+                    .and_then(|name| CELL_FIELDS.iter().position(|&field| field == name))
+                {
+                    cell[index] = value.as_deref().and_then(parse_number);
+                } else if is_symmetry_tag(&tag) {
+                    if let Some(value) = value {
+                        symmetry.push(parse_symmetry(&tokens, &tag, &value)?);
+                    }
+                }
+            }
+            Token::Loop => {
+                let mut tags = Vec::new();
+                while let Some(Token::Tag(_)) = tokens.peek()? {
+                    if let Some(Token::Tag(tag)) = tokens.next_token()? {
+                        tags.push(tag.to_ascii_lowercase());
+                    }
+                }
+                if tags.is_empty() {
+                    return Err(tokens.error("`loop_` has no tags"));
+                }
+
+                let is_atom_sites = tags.iter().any(|tag| {
+                    let name = field(tag, "_atom_site");
+                    name == Some("cartn_x") || name == Some("fract_x")
+                });
+
+                if atoms.is_none() && is_atom_sites {
+                    atoms = Some(read_atom_sites(name, &mut tokens, &tags)?);
+                } else if let Some(column) = tags.iter().position(|tag| is_symmetry_tag(tag)) {
+                    symmetry.extend(read_symmetry(&mut tokens, &tags, column)?);
+                } else {
+                    skip_loop(&mut tokens, &tags)?;
+                }
+            }
+            Token::Value(_) | Token::Null => {
+                return Err(tokens.error("found a value without a tag"));
+            }
+        }
+    }
+
+    let (mut structure, fractional) =
+        atoms.ok_or_else(|| "cif: there's no `_atom_site` loop".to_string())?;
+    if let [Some(a), Some(b), Some(c), Some(alpha), Some(beta), Some(gamma)] = cell {
+        structure.cell = UnitCell::new(a, b, c, alpha, beta, gamma);
+    }
+
+    if fractional {
+        let cell = structure.cell.ok_or_else(|| {
+            "cif: the atoms have fractional coordinates, but there's no unit cell".to_string()
+        })?;
+        // Without any operations, the space group is P1 and the atoms are already the whole
+        // cell.
+        if symmetry.is_empty() {
+            symmetry.push(SymmetryOp::identity());
+        }
+        fill_cell(&mut structure, cell, &symmetry);
+    }
+
+    Ok(structure)
+}
+
+/// The part of `tag` after its category. mmCIF separates the two with a `.`, and the core
+/// dictionary with a `_`, so this accepts either.
+fn field<'t>(tag: &'t str, category: &str) -> Option<&'t str> {
+    let rest = tag.get(category.len()..)?;
+    if tag[..category.len()].eq_ignore_ascii_case(category)
+        && (rest.starts_with('.') || rest.starts_with('_'))
+    {
+        Some(&rest[1..])
+    } else {
+        None
+    }
+}
+
+/// The core dictionary has two names for the operations, and mmCIF has both again.
+fn is_symmetry_tag(tag: &str) -> bool {
+    field(tag, "_symmetry_equiv") == Some("pos_as_xyz")
+        || field(tag, "_space_group_symop") == Some("operation_xyz")
+}
+
+/// A symmetry operation of a space group, acting on fractional coordinates.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct SymmetryOp {
+    rows: [[f32; 3]; 3],
+    translation: [f32; 3],
+}
+
+impl SymmetryOp {
+    fn identity() -> Self {
+        Self {
+            rows: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            translation: [0.0; 3],
+        }
+    }
+
+    /// Parses operations in the usual notation, like `-x+1/2, y, z-1/4` or `x-y,x,z+1/6`.
+    fn parse(op: &str) -> Option<Self> {
+        let components: Vec<_> = op.split(',').collect();
+        if components.len() != 3 {
+            return None;
+        }
+
+        let mut result = Self {
+            rows: [[0.0; 3]; 3],
+            translation: [0.0; 3],
+        };
+        for (row, component) in components.iter().enumerate() {
+            let component: String = component
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect::<String>()
+                .to_ascii_lowercase();
+            if component.is_empty() {
+                return None;
+            }
+
+            // Each term is a sign, then a number, a coordinate, or a number times a coordinate.
+            let mut rest = &*component;
+            let mut first = true;
+            while !rest.is_empty() {
+                let sign = match rest.as_bytes()[0] {
+                    b'-' => -1.0,
+                    b'+' => 1.0,
+                    _ if first => 1.0,
+                    _ => return None,
+                };
+                if rest.starts_with('-') || rest.starts_with('+') {
+                    rest = &rest[1..];
+                }
+                first = false;
+
+                let end = rest
+                    .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '/'))
+                    .unwrap_or_else(|| rest.len());
+                let number = match end {
+                    0 => None,
+                    _ => Some(parse_fraction(&rest[..end])?),
+                };
+                rest = &rest[end..];
+                if number.is_some() && rest.starts_with('*') {
+                    rest = &rest[1..];
+                }
+
+                match rest.chars().next().and_then(|c| "xyz".find(c)) {
+                    Some(axis) => {
+                        result.rows[row][axis] += sign * number.unwrap_or(1.0);
+                        rest = &rest[1..];
+                    }
+                    None => result.translation[row] += sign * number?,
+                }
+            }
+        }
+
+        Some(result)
+    }
+
+    fn apply(&self, fractional: Vec3) -> Vec3 {
+        let row = |index: usize| {
+            let [x, y, z] = self.rows[index];
+            x * fractional.x + y * fractional.y + z * fractional.z + self.translation[index]
+        };
+        Vec3::new(row(0), row(1), row(2))
+    }
+}
+
+/// Fractions like `1/2`, as well as plain numbers.
+fn parse_fraction(value: &str) -> Option<f32> {
+    let mut halves = value.splitn(2, '/');
+    let numerator: f32 = halves.next()?.parse().ok()?;
+    match halves.next() {
+        Some(denominator) => {
+            let denominator: f32 = denominator.parse().ok()?;
+            if denominator == 0.0 {
+                None
+            } else {
+                Some(numerator / denominator)
+            }
+        }
+        None => Some(numerator),
+    }
+}
+
+fn parse_symmetry<R>(tokens: &Tokenizer<R>, tag: &str, op: &str) -> Result<SymmetryOp, String> {
+    SymmetryOp::parse(op).ok_or_else(|| {
+        tokens.error(&format!(
+            "`{}` has an invalid symmetry operation `{}`",
+            tag, op
+        ))
+    })
+}
+
+/// Reads the operations in `column` of a loop.
+fn read_symmetry<R: BufRead>(
+    tokens: &mut Tokenizer<R>,
+    tags: &[String],
+    column: usize,
+) -> Result<Vec<SymmetryOp>, String> {
+    let mut ops = Vec::new();
+    let mut count = 0;
+    while let Some(value) = tokens.next_value()? {
+        if count % tags.len() == column {
+            if let Some(value) = value {
+                ops.push(parse_symmetry(tokens, &tags[column], &value)?);
+            }
+        }
+        count += 1;
+    }
+
+    if count % tags.len() == 0 {
+        Ok(ops)
+    } else {
+        Err(tokens.error(&format!(
+            "the `{}` loop has {} values, which isn't a multiple of its {} columns",
+            tags[column],
+            count,
+            tags.len()
+        )))
+    }
+}
+
+/// Applies every symmetry operation to atoms in fractional coordinates, moves the copies into
+/// the cell, and converts everything to Cartesian coordinates.
+///
+/// Copies that land on an atom of the same element that's already there are dropped, so atoms
+/// on special positions aren't doubled up.
+fn fill_cell(structure: &mut Structure, cell: UnitCell, symmetry: &[SymmetryOp]) {
+    let matrix = cell.matrix();
+    let mut placed: Vec<(Element, Vec3)> = Vec::new();
+
+    for part in &mut structure.parts {
+        let asymmetric = std::mem::replace(&mut part.fragments, Vec::new());
+        for op in symmetry {
+            for atoms in &asymmetric {
+                let mut copy = Vec::new();
+                for atom in atoms {
+                    let element = atom.kind.element();
+                    let pos = wrap_fractional(op.apply(atom.pos));
+
+                    let duplicate = placed.iter().any(|&(other_element, other)| {
+                        let mut offset = pos - other;
+                        // The nearest image, since 0.999 and 0.001 are neighbors.
+                        offset -= Vec3::new(offset.x.round(), offset.y.round(), offset.z.round());
+                        other_element == element
+                            && (matrix * offset).mag_sq() < DUPLICATE_DISTANCE * DUPLICATE_DISTANCE
+                    });
+                    if duplicate {
+                        continue;
+                    }
+
+                    placed.push((element, pos));
+                    copy.push(AtomRepr {
+                        pos: matrix * pos,
+                        kind: atom.kind,
+                    });
+                }
+
+                if !copy.is_empty() {
+                    part.fragments.push(copy);
+                }
+            }
+        }
+    }
+}
+
+/// The columns of `_atom_site` that we use.
+struct Columns {
+    element: Option<usize>,
+    atom_name: Option<usize>,
+    x: usize,
+    y: usize,
+    z: usize,
+    /// Whether the coordinates are fractional, rather than Cartesian.
+    fractional: bool,
+    model: Option<usize>,
+    chain: Option<usize>,
+    // Together, these tell residues apart.
+    residue: [Option<usize>; 3],
+}
+
+impl Columns {
+    fn new<R>(tokens: &Tokenizer<R>, tags: &[String]) -> Result<Self, String> {
+        let find = |names: &[&str]| {
+            names.iter().find_map(|name| {
+                tags.iter()
+                    .position(|tag| field(tag, "_atom_site") == Some(*name))
+            })
+        };
+        // Cartesian coordinates win if a file has both.
+        let fractional = find(&["cartn_x"]).is_none();
+        let coordinate = |axis: &str| {
+            let name = if fractional {
+                format!("fract_{}", axis)
+            } else {
+                format!("Cartn_{}", axis)
+            };
+            find(&[&*name.to_ascii_lowercase()])
+                .ok_or_else(|| tokens.error(&format!("`_atom_site` has no `{}`", name)))
+        };
+
+        Ok(Self {
+            element: find(&["type_symbol"]),
+            // Core CIF only has labels, which usually start with the element, like `C12`.
+            atom_name: find(&["label_atom_id", "auth_atom_id", "label"]),
+            x: coordinate("x")?,
+            y: coordinate("y")?,
+            z: coordinate("z")?,
+            fractional,
+            model: find(&["pdbx_pdb_model_num"]),
+            chain: find(&["label_asym_id", "auth_asym_id"]),
+            // Waters and ligands don't have a `label_seq_id`, so the author's numbering is
+            // better here.
+            residue: [
+                find(&["auth_seq_id", "label_seq_id"]),
+                find(&["pdbx_pdb_ins_code"]),
+                find(&["label_comp_id", "auth_comp_id"]),
+            ],
+        })
+    }
+}
+
+/// Builds up the structure as rows are read, so the rows themselves are never kept around.
+struct Builder<'a> {
+    name: &'a str,
+    parts: Vec<StructurePart>,
+    chains: HashMap<String, usize>,
+    // The residue that was last added to each part.
+    residues: Vec<[Option<String>; 3]>,
+    model: Option<String>,
+}
+
+impl<'a> Builder<'a> {
+    /// Small molecules don't have chains, so they're all in one part named after the file.
+    fn add(&mut self, chain: Option<&str>, residue: [Option<&str>; 3], atom: AtomRepr) {
+        let key = chain.unwrap_or("");
+        let index = match self.chains.get(key) {
+            Some(&index) => index,
+            None => {
+                self.parts.push(StructurePart {
+                    name: match chain {
+                        Some(chain) => format!("{} {}", self.name, chain),
+                        None => self.name.to_string(),
+                    },
+                    fragments: Vec::new(),
+                });
+                self.residues.push([None, None, None]);
+                self.chains.insert(key.to_string(), self.parts.len() - 1);
+                self.parts.len() - 1
+            }
+        };
+
+        let last = &mut self.residues[index];
+        let fragments = &mut self.parts[index].fragments;
+        if fragments.is_empty()
+            || !last
+                .iter()
+                .map(Option::as_deref)
+                .eq(residue.iter().copied())
+        {
+            *last = [
+                residue[0].map(str::to_string),
+                residue[1].map(str::to_string),
+                residue[2].map(str::to_string),
+            ];
+            fragments.push(Vec::new());
+        }
+
+        if let Some(fragment) = fragments.last_mut() {
+            fragment.push(atom);
+        }
+    }
+}
+
+fn read_atom_sites<R: BufRead>(
+    name: &str,
+    tokens: &mut Tokenizer<R>,
+    tags: &[String],
+) -> Result<(Structure, bool), String> {
+    let columns = Columns::new(tokens, tags)?;
+    let mut builder = Builder {
+        name,
+        parts: Vec::new(),
+        chains: HashMap::new(),
+        residues: Vec::new(),
+        model: None,
+    };
+
+    let mut row = Vec::with_capacity(tags.len());
+    while let Some(value) = tokens.next_value()? {
+        row.push(value);
+        if row.len() == tags.len() {
+            add_row(tokens, &columns, &row, &mut builder)?;
+            row.clear();
+        }
+    }
+
+    if !row.is_empty() {
+        return Err(tokens.error(&format!(
+            "the `_atom_site` loop ends partway through a row ({} of {} values)",
+            row.len(),
+            tags.len()
+        )));
+    }
+
+    let structure = Structure {
+        parts: builder.parts,
+        cell: None,
+    };
+    Ok((structure, columns.fractional))
+}
+
+fn add_row<R: BufRead>(
+    tokens: &Tokenizer<R>,
+    columns: &Columns,
+    row: &[Option<String>],
+    builder: &mut Builder,
+) -> Result<(), String> {
+    let get = |column: Option<usize>| column.and_then(|column| row[column].as_deref());
+
+    if let Some(model) = get(columns.model) {
+        match &builder.model {
+            Some(first) if first != model => return Ok(()),
+            Some(_) => {}
+            None => builder.model = Some(model.to_string()),
+        }
+    }
+
+    let coordinate = |column: usize| {
+        let value = row[column]
+            .as_deref()
+            .ok_or_else(|| tokens.error("an atom is missing a coordinate"))?;
+        parse_number(value).ok_or_else(|| tokens.error(&format!("`{}` is not a number", value)))
+    };
+    let pos = Vec3::new(
+        coordinate(columns.x)?,
+        coordinate(columns.y)?,
+        coordinate(columns.z)?,
+    );
+
+    let symbol = get(columns.element)
+        .or_else(|| get(columns.atom_name))
+        .ok_or_else(|| tokens.error("an atom has no element"))?;
+    let element = parse_element(symbol)
+        .ok_or_else(|| tokens.error(&format!("unknown element `{}`", symbol)))?;
+
+    let residue = [
+        get(columns.residue[0]),
+        get(columns.residue[1]),
+        get(columns.residue[2]),
+    ];
+    builder.add(
+        get(columns.chain),
+        residue,
+        AtomRepr {
+            pos,
+            kind: AtomKind::new(element),
+        },
+    );
+
+    Ok(())
+}
+
+/// Numbers can have their uncertainty in parentheses, like `1.234(5)`.
+fn parse_number(value: &str) -> Option<f32> {
+    value.split('(').next().unwrap_or(value).parse().ok()
+}
+
+/// Type symbols can have a charge on them, like `FE2+`, and deuterium has its own symbol.
+fn parse_element(symbol: &str) -> Option<Element> {
+    let letters: String = symbol
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect();
+
+    match &*letters.to_ascii_uppercase() {
+        "D" | "T" => Some(Element::Hydrogen),
+        _ => Element::from_symbol(&letters),
+    }
+}
+
+fn skip_loop<R: BufRead>(tokens: &mut Tokenizer<R>, tags: &[String]) -> Result<(), String> {
+    let mut count = 0;
+    while tokens.next_value()?.is_some() {
+        count += 1;
+    }
+
+    if count % tags.len() == 0 {
+        Ok(())
+    } else {
+        Err(tokens.error(&format!(
+            "the `{}` loop has {} values, which isn't a multiple of its {} columns",
+            tags[0],
+            count,
+            tags.len()
+        )))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Data(String),
+    Loop,
+    Tag(String),
+    Value(String),
+    /// `.` (inapplicable) and `?` (unknown).
+    Null,
+}
+
+struct Tokenizer<R> {
+    reader: R,
+    line: String,
+    position: usize,
+    line_number: usize,
+    peeked: Option<Token>,
+}
+
+impl<R> Tokenizer<R> {
+    fn error(&self, message: &str) -> String {
+        format!("cif: {} on line {}", message, self.line_number)
+    }
+}
+
+impl<R: BufRead> Tokenizer<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: String::new(),
+            position: 0,
+            line_number: 0,
+            peeked: None,
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, String> {
+        match self.peeked.take() {
+            Some(token) => Ok(Some(token)),
+            None => self.read_token(),
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<&Token>, String> {
+        if self.peeked.is_none() {
+            self.peeked = self.read_token()?;
+        }
+        Ok(self.peeked.as_ref())
+    }
+
+    /// Takes the next token if it's a value. Nulls are `Some(None)`.
+    fn next_value(&mut self) -> Result<Option<Option<String>>, String> {
+        match self.peek()? {
+            Some(Token::Value(_)) | Some(Token::Null) => {}
+            _ => return Ok(None),
+        }
+
+        match self.next_token()? {
+            Some(Token::Value(value)) => Ok(Some(Some(value))),
+            _ => Ok(Some(None)),
+        }
+    }
+
+    /// Returns false at the end of the file.
+    fn read_line(&mut self) -> Result<bool, String> {
+        self.line.clear();
+        self.position = 0;
+
+        match self.reader.read_line(&mut self.line) {
+            Ok(0) => Ok(false),
+            Ok(_) => {
+                self.line_number += 1;
+                Ok(true)
+            }
+            Err(e) => Err(format!(
+                "cif: failed to read line {}: {}",
+                self.line_number + 1,
+                e
+            )),
+        }
+    }
+
+    fn read_token(&mut self) -> Result<Option<Token>, String> {
+        loop {
+            let rest = &self.line[self.position..];
+            let trimmed = rest.trim_start();
+            self.position += rest.len() - trimmed.len();
+
+            if !trimmed.is_empty() && !trimmed.starts_with('#') {
+                break;
+            }
+
+            if !self.read_line()? {
+                return Ok(None);
+            }
+            // Text fields are the only thing that depends on where a line starts.
+            if self.line.starts_with(';') {
+                return self.text_field().map(Some);
+            }
+        }
+
+        let rest = &self.line[self.position..];
+        let quote = rest.as_bytes()[0];
+        if quote == b'\'' || quote == b'"' {
+            // A quote only ends a value when it's followed by whitespace, so `'O5'' isn't closed
+            // until after the second quote.
+            let bytes = rest.as_bytes();
+            let end = (1..bytes.len())
+                .find(|&i| {
+                    bytes[i] == quote && bytes.get(i + 1).map_or(true, u8::is_ascii_whitespace)
+                })
+                .ok_or_else(|| self.error("unclosed quote"))?;
+
+            let value = rest[1..end].to_string();
+            self.position += end + 1;
+            return Ok(Some(Token::Value(value)));
+        }
+
+        let end = rest
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        let word = &rest[..end];
+        let lowercase = word.to_ascii_lowercase();
+
+        let token = if word.starts_with('_') {
+            Token::Tag(word.to_string())
+        } else if lowercase == "loop_" {
+            Token::Loop
+        } else if lowercase.starts_with("data_") {
+            Token::Data(word["data_".len()..].to_string())
+        } else if lowercase.starts_with("save_") || lowercase == "global_" || lowercase == "stop_" {
+            return Err(self.error(&format!("`{}` isn't supported", word)));
+        } else if word == "." || word == "?" {
+            Token::Null
+        } else {
+            Token::Value(word.to_string())
+        };
+
+        self.position += end;
+        Ok(Some(token))
+    }
+
+    /// A value between two lines that start with `;`.
+    fn text_field(&mut self) -> Result<Token, String> {
+        let start = self.line_number;
+        let mut value = self.line[1..]
+            .trim_end_matches(&['\r', '\n'][..])
+            .to_string();
+
+        loop {
+            if !self.read_line()? {
+                return Err(format!(
+                    "cif: the text field on line {} is never closed",
+                    start
+                ));
+            }
+            if self.line.starts_with(';') {
+                self.position = 1;
+                return Ok(Token::Value(value));
+            }
+
+            value.push('\n');
+            value.push_str(self.line.trim_end_matches(&['\r', '\n'][..]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_str(contents: &str) -> Result<Structure, String> {
+        read("test", contents.as_bytes())
+    }
+
+    const MMCIF: &str = "\
+data_TEST
+#
+loop_
+_atom_site.group_PDB
+_atom_site.id
+_atom_site.type_symbol
+_atom_site.label_atom_id
+_atom_site.label_comp_id
+_atom_site.label_asym_id
+_atom_site.auth_seq_id
+_atom_site.pdbx_PDB_ins_code
+_atom_site.Cartn_x
+_atom_site.Cartn_y
+_atom_site.Cartn_z
+_atom_site.pdbx_PDB_model_num
+ATOM   1 N N   GLY A 1 ? 0.000 0.000 0.000 1
+ATOM   2 C CA  GLY A 1 ? 1.458 0.000 0.000 1
+ATOM   3 N N   ALA A 2 ? 2.000 1.200 0.000 1
+HETATM 4 O O   HOH B 5 ? 5.000 5.000 5.000 1
+ATOM   5 N N   GLY A 1 ? 0.100 0.000 0.000 2
+ATOM   6 C CA  GLY A 1 ? 1.558 0.000 0.000 2
+#
+";
+
+    #[test]
+    fn chains_are_parts_and_residues_are_fragments() {
+        let structure = read_str(MMCIF).unwrap();
+
+        let names: Vec<_> = structure.parts.iter().map(|part| &*part.name).collect();
+        assert_eq!(names, ["test A", "test B"]);
+
+        let chain_a: Vec<_> = structure.parts[0].fragments.iter().map(Vec::len).collect();
+        assert_eq!(chain_a, [2, 1]);
+        assert_eq!(structure.parts[1].fragments.len(), 1);
+        assert_eq!(
+            structure.parts[1].fragments[0][0].kind.element(),
+            Element::Oxygen
+        );
+    }
+
+    #[test]
+    fn only_the_first_model_is_read() {
+        let structure = read_str(MMCIF).unwrap();
+
+        assert_eq!(structure.atoms().count(), 4);
+        assert!(structure
+            .atoms()
+            .all(|atom| atom.pos != Vec3::new(0.1, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn partial_row() {
+        let error = read_str(
+            "data_TEST
+loop_
+_atom_site.type_symbol
+_atom_site.Cartn_x
+_atom_site.Cartn_y
+_atom_site.Cartn_z
+C 0.0 0.0 0.0
+C 1.5 0.0
+",
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            error,
+            "cif: the `_atom_site` loop ends partway through a row (3 of 4 values) on line 8"
+        );
+    }
+
+    #[test]
+    fn loop_without_tags() {
+        let error = read_str("data_TEST\nloop_\n1 2 3\n").err().unwrap();
+        assert_eq!(error, "cif: `loop_` has no tags on line 3");
+    }
+
+    #[test]
+    fn ragged_loop() {
+        let error = read_str(
+            "data_TEST
+loop_
+_citation.id
+_citation.title
+1 first
+2
+",
+        )
+        .err()
+        .unwrap();
+        assert!(
+            error.contains("isn't a multiple of its 2 columns"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn parse_symmetry_operations() {
+        assert_eq!(
+            SymmetryOp::parse("-x+1/2, y, z-1/4"),
+            Some(SymmetryOp {
+                rows: [[-1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+                translation: [0.5, 0.0, -0.25],
+            })
+        );
+        assert_eq!(
+            SymmetryOp::parse("x-y,x,z+1/6"),
+            Some(SymmetryOp {
+                rows: [[1.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+                translation: [0.0, 0.0, 1.0 / 6.0],
+            })
+        );
+        assert_eq!(SymmetryOp::parse("x,y"), None);
+        assert_eq!(SymmetryOp::parse("x,y,w"), None);
+    }
+
+    fn inverted_cell(asymmetric: &str) -> String {
+        format!(
+            "data_TEST
+_cell_length_a 5.0
+_cell_length_b 5.0
+_cell_length_c 5.0
+_cell_angle_alpha 90
+_cell_angle_beta 90
+_cell_angle_gamma 90
+loop_
+_symmetry_equiv_pos_as_xyz
+'x, y, z'
+'-x, -y, -z'
+loop_
+_atom_site_label
+_atom_site_fract_x
+_atom_site_fract_y
+_atom_site_fract_z
+{}
+",
+            asymmetric
+        )
+    }
+
+    #[test]
+    fn symmetry_copies_fill_the_cell() {
+        let structure = read_str(&inverted_cell("C1 0.1 0.2 0.3")).unwrap();
+
+        let positions: Vec<_> = structure.atoms().map(|atom| atom.pos).collect();
+        assert_eq!(positions.len(), 2);
+        assert!((positions[0] - Vec3::new(0.5, 1.0, 1.5)).mag() < 1.0e-4);
+        assert!((positions[1] - Vec3::new(4.5, 4.0, 3.5)).mag() < 1.0e-4);
+    }
+
+    #[test]
+    fn special_positions_are_not_doubled() {
+        // On the inversion center, and on one that's only there after wrapping into the cell.
+        let structure = read_str(&inverted_cell("C1 0.0 0.0 0.0\nO1 0.5 0.5 0.5")).unwrap();
+        assert_eq!(structure.atoms().count(), 2);
+    }
+
+    #[test]
+    fn truncated_symmetry_loop() {
+        let error = read_str(
+            "data_TEST
+loop_
+_symmetry_equiv_pos_site_id
+_symmetry_equiv_pos_as_xyz
+1 x,y,z
+2
+",
+        )
+        .err()
+        .unwrap();
+        assert!(error.contains("`_symmetry_equiv_pos_as_xyz`"), "{}", error);
+    }
+}
@@ -0,0 +1,451 @@
+use crate::{
+    camera::NavigationSettings,
+    convert::{self, Format},
+    hydrogen_bonds, sasa, stl, superpose,
+};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use std::{path::PathBuf, process};
+use winit::dpi::PhysicalSize;
+
+const DEFAULT_EXPORT_SIZE: PhysicalSize<u32> = PhysicalSize {
+    width: 1920,
+    height: 1080,
+};
+
+pub struct Args {
+    /// The structure to open on startup.
+    pub file: Option<PathBuf>,
+    /// Opens a nanotube with these chiral indices and length, instead of a file.
+    pub nanotube: Option<(u32, u32, f32)>,
+    /// Render `file` into a png and exit without opening a window.
+    pub export_image: Option<(PathBuf, PhysicalSize<u32>)>,
+    /// Convert `file` into another format and exit.
+    pub export: Option<(Format, PathBuf)>,
+    /// Move every atom into the unit cell when converting.
+    pub wrap: bool,
+    /// Repeat the unit cell along each axis when converting.
+    pub supercell: Option<[u32; 3]>,
+    /// The grid spacing of surfaces when converting, in angstroms.
+    pub resolution: f32,
+    /// Print the solvent-accessible surface area of `file`, with this probe radius, and exit.
+    pub sasa: Option<f32>,
+    /// Print the hydrogen bonds in `file` and exit.
+    pub hydrogen_bonds: bool,
+    /// Superimpose `file` on this reference structure, print the RMSD, and exit. The moved
+    /// structure is written to the second path, if there is one.
+    pub superimpose: Option<(PathBuf, Option<PathBuf>)>,
+    /// Print the RMSD between `file` and this reference structure, and exit.
+    pub rmsd: Option<PathBuf>,
+    pub log_level: Option<String>,
+    /// Records the time of every frame and writes them here as csv on exit.
+    pub frame_stats: Option<PathBuf>,
+    /// Part of the name of the gpu to render with.
+    pub gpu: Option<String>,
+    /// Turns off ambient occlusion.
+    pub no_ssao: bool,
+    /// A json file of element color and radius overrides.
+    pub element_styles: Option<PathBuf>,
+    /// How gamepads move the camera.
+    pub navigation: NavigationSettings,
+}
+
+impl Args {
+    /// Prints usage and exits if the arguments are invalid.
+    pub fn parse() -> Self {
+        let app = App::new("atomCAD")
+            .version(env!("CARGO_PKG_VERSION"))
+            .setting(AppSettings::ColoredHelp)
+            .arg(
+                Arg::with_name("file")
+                    .help("The structure to open (.pdb, .cif, .xyz, .mol, or .smi)")
+                    .index(1),
+            )
+            .arg(
+                Arg::with_name("nanotube")
+                    .long("nanotube")
+                    .value_names(&["N", "M", "LENGTH"])
+                    .help("Opens an (N, M) carbon nanotube that's LENGTH angstroms long")
+                    .number_of_values(3)
+                    .conflicts_with("file"),
+            )
+            .arg(
+                Arg::with_name("export-image")
+                    .long("export-image")
+                    .value_name("OUTPUT")
+                    .help("Renders the file into a png without opening a window")
+                    .takes_value(true)
+                    .requires("file")
+                    .conflicts_with("export"),
+            )
+            .arg(
+                Arg::with_name("width")
+                    .long("width")
+                    .value_name("PIXELS")
+                    .help("The width of the exported image")
+                    .takes_value(true)
+                    .requires("export-image"),
+            )
+            .arg(
+                Arg::with_name("height")
+                    .long("height")
+                    .value_name("PIXELS")
+                    .help("The height of the exported image")
+                    .takes_value(true)
+                    .requires("export-image"),
+            )
+            .arg(
+                Arg::with_name("export")
+                    .long("export")
+                    .value_names(&["FORMAT", "OUTPUT"])
+                    .help(
+                        "Converts the file into another format (xyz, pdb, mol, smi, stl, gltf, or \
+                         glb), which is taken from OUTPUT's extension if FORMAT is left out",
+                    )
+                    .min_values(1)
+                    .max_values(2)
+                    .requires("file"),
+            )
+            .arg(
+                Arg::with_name("wrap")
+                    .long("wrap")
+                    .help("Moves every atom into the unit cell when converting")
+                    .requires("export"),
+            )
+            .arg(
+                Arg::with_name("supercell")
+                    .long("supercell")
+                    .value_names(&["NA", "NB", "NC"])
+                    .help("Repeats the unit cell along a, b, and c when converting")
+                    .number_of_values(3)
+                    .requires("export"),
+            )
+            .arg(
+                Arg::with_name("resolution")
+                    .long("resolution")
+                    .value_name("ANGSTROMS")
+                    .help("The grid spacing of the surface when exporting stl [default: 0.5]")
+                    .takes_value(true)
+                    .requires("export"),
+            )
+            .arg(
+                Arg::with_name("sasa")
+                    .long("sasa")
+                    .help("Prints the solvent-accessible surface area of each part")
+                    .requires("file")
+                    .conflicts_with_all(&["export-image", "export"]),
+            )
+            .arg(
+                Arg::with_name("probe-radius")
+                    .long("probe-radius")
+                    .value_name("ANGSTROMS")
+                    .help("The radius of the solvent for --sasa [default: 1.4, for water]")
+                    .takes_value(true)
+                    .requires("sasa"),
+            )
+            .arg(
+                Arg::with_name("hydrogen-bonds")
+                    .long("hydrogen-bonds")
+                    .help("Prints the hydrogen bonds between atoms")
+                    .requires("file")
+                    .conflicts_with_all(&["export-image", "export", "sasa"]),
+            )
+            .arg(
+                Arg::with_name("superimpose")
+                    .long("superimpose")
+                    .value_names(&["REFERENCE", "OUTPUT"])
+                    .help(
+                        "Turns and moves the file onto REFERENCE, pairing atoms by their order, \
+                         prints the RMSD, and writes the result to OUTPUT if it's given",
+                    )
+                    .min_values(1)
+                    .max_values(2)
+                    .requires("file")
+                    .conflicts_with_all(&["export-image", "export", "sasa", "hydrogen-bonds"]),
+            )
+            .arg(
+                Arg::with_name("rmsd")
+                    .long("rmsd")
+                    .value_name("REFERENCE")
+                    .help("Prints the RMSD between the file and REFERENCE, pairing atoms by order")
+                    .takes_value(true)
+                    .requires("file")
+                    .conflicts_with_all(&[
+                        "export-image",
+                        "export",
+                        "sasa",
+                        "hydrogen-bonds",
+                        "superimpose",
+                    ]),
+            )
+            .arg(
+                Arg::with_name("log-level")
+                    .long("log-level")
+                    .value_name("LEVEL")
+                    .help("Overrides the RUST_LOG environment variable")
+                    .takes_value(true)
+                    .possible_values(&["error", "warn", "info", "debug", "trace"]),
+            )
+            .arg(
+                Arg::with_name("frame-stats")
+                    .long("frame-stats")
+                    .value_name("OUTPUT")
+                    .help("Writes per-frame timings to a csv file on exit")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("gpu")
+                    .long("gpu")
+                    .value_name("NAME")
+                    .help("Prefers the gpu whose name contains NAME")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("no-ssao")
+                    .long("no-ssao")
+                    .help("Turns off ambient occlusion, which is slow on some gpus"),
+            )
+            .arg(
+                Arg::with_name("element-styles")
+                    .long("element-styles")
+                    .value_name("FILE")
+                    .help("Overrides element colors and radii, and reloads them when FILE changes")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("gamepad-sensitivity")
+                    .long("gamepad-sensitivity")
+                    .value_name("SCALE")
+                    .help("Scales how fast a gamepad moves the camera [default: 1]")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("invert-gamepad-y")
+                    .long("invert-gamepad-y")
+                    .help("Makes pushing a gamepad's right stick up orbit down"),
+            )
+            .subcommand(
+                SubCommand::with_name("convert")
+                    .about("Converts INPUT into the format of OUTPUT, based on their extensions")
+                    .arg(Arg::with_name("input").required(true).index(1))
+                    .arg(Arg::with_name("output").required(true).index(2)),
+            )
+            .subcommand(
+                SubCommand::with_name("headless")
+                    .about("Renders INPUT into a png without opening a window")
+                    .arg(Arg::with_name("input").required(true).index(1))
+                    .arg(Arg::with_name("output").required(true).index(2))
+                    .arg(Arg::with_name("width").index(3).requires("height"))
+                    .arg(Arg::with_name("height").index(4)),
+            );
+
+        let matches = app.get_matches();
+
+        match Self::from_matches(&matches) {
+            Ok(args) => args,
+            Err(e) => {
+                eprintln!("error: {}\n\n{}", e, matches.usage());
+                process::exit(1);
+            }
+        }
+    }
+
+    fn from_matches(matches: &ArgMatches) -> Result<Self, String> {
+        let size = |matches: &ArgMatches, name: &str, default: u32| match matches.value_of(name) {
+            Some(value) => value
+                .parse::<u32>()
+                .ok()
+                .filter(|&value| value > 0)
+                .ok_or_else(|| format!("invalid {} `{}`", name, value)),
+            None => Ok(default),
+        };
+        let path = |matches: &ArgMatches, name: &str| matches.value_of_os(name).map(PathBuf::from);
+
+        // The subcommands are shorthands for opening a file and exporting it.
+        let (file, export_image, export) = match matches.subcommand() {
+            ("convert", Some(convert)) => {
+                let output = path(convert, "output").unwrap_or_default();
+                let format = Format::from_path(&output)?;
+                (path(convert, "input"), None, Some((format, output)))
+            }
+            ("headless", Some(headless)) => {
+                let size = PhysicalSize::new(
+                    size(headless, "width", DEFAULT_EXPORT_SIZE.width)?,
+                    size(headless, "height", DEFAULT_EXPORT_SIZE.height)?,
+                );
+                let output = path(headless, "output").unwrap_or_default();
+                (path(headless, "input"), Some((output, size)), None)
+            }
+            _ => {
+                let export_image = match matches.value_of("export-image") {
+                    Some(output) => Some((
+                        PathBuf::from(output),
+                        PhysicalSize::new(
+                            size(matches, "width", DEFAULT_EXPORT_SIZE.width)?,
+                            size(matches, "height", DEFAULT_EXPORT_SIZE.height)?,
+                        ),
+                    )),
+                    None => None,
+                };
+
+                let export = match matches.values_of_os("export") {
+                    Some(values) => {
+                        let values: Vec<_> = values.collect();
+                        let output = PathBuf::from(values[values.len() - 1]);
+                        let format = match values.len() {
+                            1 => Format::from_path(&output)?,
+                            _ => Format::from_name(&values[0].to_string_lossy())?,
+                        };
+                        Some((format, output))
+                    }
+                    None => None,
+                };
+
+                (path(matches, "file"), export_image, export)
+            }
+        };
+
+        let supercell = match matches.values_of("supercell") {
+            Some(values) => {
+                let mut counts = [1; 3];
+                for (count, value) in counts.iter_mut().zip(values) {
+                    *count = value
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|&count| count > 0)
+                        .ok_or_else(|| format!("invalid --supercell `{}`", value))?;
+                }
+                Some(counts)
+            }
+            None => None,
+        };
+
+        let nanotube = match matches.values_of("nanotube") {
+            Some(values) => {
+                let values: Vec<_> = values.collect();
+                let index = |value: &str| {
+                    value
+                        .parse::<u32>()
+                        .map_err(|_| format!("invalid --nanotube index `{}`", value))
+                };
+                let length = values[2]
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|&length| length > 0.0)
+                    .ok_or_else(|| format!("invalid --nanotube length `{}`", values[2]))?;
+                Some((index(values[0])?, index(values[1])?, length))
+            }
+            None => None,
+        };
+
+        let resolution = match matches.value_of("resolution") {
+            Some(value) => value
+                .parse::<f32>()
+                .ok()
+                .filter(|&resolution| resolution > 0.0)
+                .ok_or_else(|| format!("invalid --resolution `{}`", value))?,
+            None => stl::DEFAULT_RESOLUTION,
+        };
+
+        let sasa = if matches.is_present("sasa") {
+            match matches.value_of("probe-radius") {
+                Some(value) => Some(
+                    value
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|&radius| radius >= 0.0)
+                        .ok_or_else(|| format!("invalid --probe-radius `{}`", value))?,
+                ),
+                None => Some(sasa::WATER_PROBE_RADIUS),
+            }
+        } else {
+            None
+        };
+
+        let superimpose = matches.values_of_os("superimpose").map(|values| {
+            let values: Vec<_> = values.map(PathBuf::from).collect();
+            (values[0].clone(), values.get(1).cloned())
+        });
+
+        let sensitivity = match matches.value_of("gamepad-sensitivity") {
+            Some(value) => value
+                .parse::<f32>()
+                .ok()
+                .filter(|&sensitivity| sensitivity > 0.0)
+                .ok_or_else(|| format!("invalid --gamepad-sensitivity `{}`", value))?,
+            None => NavigationSettings::default().sensitivity,
+        };
+
+        Ok(Self {
+            file,
+            nanotube,
+            export_image,
+            export,
+            wrap: matches.is_present("wrap"),
+            supercell,
+            resolution,
+            sasa,
+            hydrogen_bonds: matches.is_present("hydrogen-bonds"),
+            superimpose,
+            rmsd: matches.value_of_os("rmsd").map(PathBuf::from),
+            log_level: matches.value_of("log-level").map(str::to_string),
+            frame_stats: matches.value_of_os("frame-stats").map(PathBuf::from),
+            gpu: matches.value_of("gpu").map(str::to_string),
+            no_ssao: matches.is_present("no-ssao"),
+            element_styles: matches.value_of_os("element-styles").map(PathBuf::from),
+            navigation: NavigationSettings {
+                sensitivity,
+                invert_y: matches.is_present("invert-gamepad-y"),
+            },
+        })
+    }
+
+    /// Runs the export that was requested, if any.
+    ///
+    /// Returns `None` if the app should open a window instead.
+    pub fn run_export(&self) -> Option<Result<(), String>> {
+        let file = self.file.as_ref()?;
+        if self.export_image.is_none()
+            && self.export.is_none()
+            && self.sasa.is_none()
+            && !self.hydrogen_bonds
+            && self.superimpose.is_none()
+            && self.rmsd.is_none()
+        {
+            return None;
+        }
+
+        // None of these open a window, and there might not even be a display.
+        crate::crash::set_headless();
+
+        if let Some((output, size)) = &self.export_image {
+            Some(futures::executor::block_on(crate::headless::render_to_png(
+                file,
+                output,
+                *size,
+                self.gpu.clone(),
+                !self.no_ssao,
+            )))
+        } else if let Some((format, output)) = &self.export {
+            Some(convert::convert(
+                file,
+                *format,
+                output,
+                self.wrap,
+                self.supercell,
+                self.resolution,
+            ))
+        } else if let Some(probe_radius) = self.sasa {
+            Some(sasa::print_sasa(file, probe_radius))
+        } else if let Some((reference, output)) = &self.superimpose {
+            Some(superpose::print_superposition(
+                file,
+                reference,
+                output.as_deref(),
+            ))
+        } else if let Some(reference) = &self.rmsd {
+            Some(superpose::print_rmsd(file, reference))
+        } else {
+            Some(hydrogen_bonds::print_hydrogen_bonds(file))
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Copying parts to the system clipboard, and pasting them back in, as xyz text.
+
+use crate::{
+    structure::{Structure, StructurePart},
+    xyz,
+};
+use clipboard::{ClipboardContext, ClipboardProvider};
+use render::{Fragment, Part, PartId, World};
+
+/// How far pasted atoms are moved from where they were copied, in angstroms, so they don't
+/// land on top of the originals.
+const PASTE_OFFSET: f32 = 10.0;
+
+pub fn copy(world: &World, part_id: PartId) -> Result<(), String> {
+    let part = world
+        .part(part_id)
+        .ok_or_else(|| "that part doesn't exist anymore".to_string())?;
+
+    let structure = Structure {
+        parts: vec![StructurePart {
+            name: part.name().to_string(),
+            fragments: vec![world.part_atoms(part).collect()],
+        }],
+        cell: None,
+    };
+    let mut text = Vec::new();
+    xyz::write_xyz(&structure, part.name(), &mut text).map_err(|e| e.to_string())?;
+    let text = String::from_utf8(text).map_err(|e| e.to_string())?;
+
+    let mut clipboard = open()?;
+    clipboard
+        .set_contents(text)
+        .map_err(|e| format!("failed to copy to the clipboard: {}", e))
+}
+
+/// Adds whatever is on the clipboard as a new part.
+pub fn paste(world: &mut World) -> Result<PartId, String> {
+    let mut clipboard = open()?;
+    let text = clipboard
+        .get_contents()
+        .map_err(|e| format!("failed to read the clipboard: {}", e))?;
+
+    // Copied parts keep their name in the comment line.
+    let name = match text.lines().nth(1).map(str::trim) {
+        Some(comment) if !comment.is_empty() => comment,
+        _ => "Pasted atoms",
+    };
+    let structure = xyz::read_xyz(name, &text)
+        .map_err(|e| format!("the clipboard doesn't hold an xyz structure ({})", e))?;
+
+    let fragments: Vec<_> = structure
+        .parts
+        .into_iter()
+        .flat_map(|part| part.fragments)
+        .filter(|atoms| !atoms.is_empty())
+        .map(Fragment::new)
+        .collect();
+    if fragments.is_empty() {
+        return Err("there are no atoms on the clipboard".to_string());
+    }
+
+    let mut part = Part::from_fragments(world, name, fragments);
+    part.offset_by(PASTE_OFFSET, 0.0, 0.0);
+    Ok(world.spawn_part(part))
+}
+
+fn open() -> Result<ClipboardContext, String> {
+    ClipboardProvider::new().map_err(|e| format!("failed to open the clipboard: {}", e))
+}
@@ -0,0 +1,194 @@
+//! Coloring atoms by properties of the structure, rather than by element.
+//!
+//! Each property is worked out for every atom and handed to the renderer as scalars, which
+//! `ColorMode::Scalar` maps through a colormap. Bonds are inferred from distances, the same
+//! way the minimizer does it.
+
+use crate::{
+    minimize::infer_bonds,
+    sasa::{sasa, WATER_PROBE_RADIUS},
+};
+use render::{ColorMode, Colormap, PartId, Renderer, World};
+use ultraviolet::Vec3;
+
+/// How many colors `Colormap::categorical` has. Labels past that wrap around.
+const CATEGORIES: usize = 8;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Coloring {
+    Element,
+    /// Each part gets its own color.
+    Part,
+    /// How many atoms each atom is bonded to.
+    Coordination,
+    /// Each group of atoms that are bonded to each other gets its own color.
+    Component,
+    /// How far each atom is from the center of the selected part, or of everything if nothing
+    /// is selected.
+    Distance,
+    /// How much of each atom's surface a water molecule can reach.
+    Exposure,
+}
+
+impl Coloring {
+    pub fn next(self) -> Self {
+        match self {
+            Coloring::Element => Coloring::Part,
+            Coloring::Part => Coloring::Coordination,
+            Coloring::Coordination => Coloring::Component,
+            Coloring::Component => Coloring::Distance,
+            Coloring::Distance => Coloring::Exposure,
+            Coloring::Exposure => Coloring::Element,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Coloring::Element => "element",
+            Coloring::Part => "part",
+            Coloring::Coordination => "coordination number",
+            Coloring::Component => "connected component",
+            Coloring::Distance => "distance",
+            Coloring::Exposure => "solvent-accessible area",
+        }
+    }
+
+    /// Switches the renderer over to this coloring, and says what the colors mean.
+    pub fn apply(self, renderer: &mut Renderer, world: &mut World, selected: Option<PartId>) {
+        match self.refresh(renderer, world, selected) {
+            Some((min, max)) => {
+                log::info!("coloring by {}, from {:.2} to {:.2}", self.name(), min, max)
+            }
+            None => log::info!("coloring by {}", self.name()),
+        }
+    }
+
+    /// Recomputes the scalars for every atom and switches the renderer over to them. Returns the
+    /// range that the colormap covers, unless this colors by element.
+    pub fn refresh(
+        self,
+        renderer: &mut Renderer,
+        world: &mut World,
+        selected: Option<PartId>,
+    ) -> Option<(f32, f32)> {
+        let (scalars, colormap, range) = match self {
+            Coloring::Element => {
+                world.clear_scalars();
+                renderer.set_color_mode(ColorMode::Element);
+                return None;
+            }
+            Coloring::Part => {
+                let scalars: Vec<_> = world
+                    .parts()
+                    .enumerate()
+                    .flat_map(|(index, part)| {
+                        let label = (index % CATEGORIES) as f32;
+                        world.part_atoms(part).map(move |_| label)
+                    })
+                    .collect();
+                (scalars, Colormap::categorical(), categorical_range())
+            }
+            Coloring::Coordination => {
+                let atoms: Vec<_> = world.world_atoms().collect();
+                let mut counts = vec![0.0; atoms.len()];
+                for (a, b) in infer_bonds(&atoms) {
+                    counts[a] += 1.0;
+                    counts[b] += 1.0;
+                }
+                (counts, Colormap::viridis(), Some((0.0, 4.0)))
+            }
+            Coloring::Component => (
+                components(world),
+                Colormap::categorical(),
+                categorical_range(),
+            ),
+            Coloring::Distance => {
+                let center = selected
+                    .and_then(|id| world.part(id))
+                    .and_then(|part| centroid(world.part_atoms(part).map(|atom| atom.pos)))
+                    .or_else(|| centroid(world.world_atoms().map(|atom| atom.pos)))
+                    .unwrap_or_else(Vec3::zero);
+                let scalars: Vec<_> = world
+                    .world_atoms()
+                    .map(|atom| (atom.pos - center).mag())
+                    .collect();
+                (scalars, Colormap::viridis(), None)
+            }
+            Coloring::Exposure => {
+                let atoms: Vec<_> = world.world_atoms().collect();
+                let areas = sasa(&atoms, renderer.periodic_table(), WATER_PROBE_RADIUS);
+                (areas, Colormap::viridis(), None)
+            }
+        };
+
+        let fitted = range.unwrap_or_else(|| fit(&scalars));
+        world.set_world_scalars(&scalars);
+        renderer.set_color_mode(ColorMode::Scalar { colormap, range });
+        Some(fitted)
+    }
+}
+
+fn categorical_range() -> Option<(f32, f32)> {
+    Some((0.0, (CATEGORIES - 1) as f32))
+}
+
+/// Labels every atom with the connected component it's in, in the order they're first seen.
+fn components(world: &World) -> Vec<f32> {
+    let atoms: Vec<_> = world.world_atoms().collect();
+
+    // Union-find, merging the two ends of every bond.
+    let mut parents: Vec<_> = (0..atoms.len()).collect();
+    for (a, b) in infer_bonds(&atoms) {
+        let (a, b) = (root(&mut parents, a), root(&mut parents, b));
+        parents[a.max(b)] = a.min(b);
+    }
+
+    let mut labels = vec![usize::MAX; atoms.len()];
+    let mut count = 0;
+    (0..atoms.len())
+        .map(|index| {
+            let root = root(&mut parents, index);
+            if labels[root] == usize::MAX {
+                labels[root] = count;
+                count += 1;
+            }
+            (labels[root] % CATEGORIES) as f32
+        })
+        .collect()
+}
+
+/// The representative of the set that `index` is in, halving the path to it along the way.
+fn root(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+    index
+}
+
+fn centroid<I: Iterator<Item = Vec3>>(positions: I) -> Option<Vec3> {
+    let (sum, count) = positions.fold((Vec3::zero(), 0), |(sum, count), pos| {
+        (sum + pos, count + 1)
+    });
+
+    if count > 0 {
+        Some(sum / count as f32)
+    } else {
+        None
+    }
+}
+
+/// The range that an automatic colormap covers, for the legend in the log.
+fn fit(scalars: &[f32]) -> (f32, f32) {
+    let (min, max) = scalars
+        .iter()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &scalar| {
+            (min.min(scalar), max.max(scalar))
+        });
+
+    if min <= max {
+        (min, max)
+    } else {
+        (0.0, 0.0)
+    }
+}
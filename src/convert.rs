@@ -0,0 +1,180 @@
+//! Reads and writes every supported structure format without touching the gpu.
+
+use crate::{cif, gltf, mol, pdb, smiles, stl, structure::Structure, xyz};
+use periodic_table::PeriodicTable;
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::Path,
+};
+
+/// Every extension `Format::from_name` accepts, other than aliases, for error messages.
+const EXTENSIONS: &str = "xyz, pdb, cif, mol, smi, stl, gltf, or glb";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Xyz,
+    Pdb,
+    Mol,
+    /// mmCIF, which can only be read for now.
+    Cif,
+    /// A SMILES string, which is embedded into 3D when it's read. Writing infers the bonds from
+    /// distances and produces the canonical string.
+    Smiles,
+    /// The van der Waals surface, which can't be read back.
+    Stl,
+    /// Spheres for every atom, with the geometry in a separate `.bin` file. This can't be read
+    /// back either.
+    Gltf,
+    /// The same as `Gltf`, but in a single binary file.
+    Glb,
+}
+
+impl Format {
+    /// Picks a format based on the file extension.
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+
+        Self::from_name(&extension).map_err(|_| {
+            format!(
+                "unsupported format for `{}` (expected {})",
+                path.display(),
+                EXTENSIONS
+            )
+        })
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match &*name.to_ascii_lowercase() {
+            "xyz" => Ok(Format::Xyz),
+            "pdb" | "ent" => Ok(Format::Pdb),
+            "cif" | "mmcif" => Ok(Format::Cif),
+            "mol" | "sdf" => Ok(Format::Mol),
+            "smi" | "smiles" => Ok(Format::Smiles),
+            "stl" => Ok(Format::Stl),
+            "gltf" => Ok(Format::Gltf),
+            "glb" => Ok(Format::Glb),
+            _ => Err(format!(
+                "unsupported format `{}` (expected {})",
+                name, EXTENSIONS
+            )),
+        }
+    }
+}
+
+/// Reads a structure in any supported format, based on its file extension.
+pub fn load(path: &Path) -> Result<Structure, String> {
+    read(Format::from_path(path)?, path)
+}
+
+/// `wrap` moves every atom into the input's unit cell, and then `supercell` repeats the cell
+/// that many times along each axis. `resolution` is the grid spacing of surfaces, in
+/// angstroms.
+pub fn convert(
+    input: &Path,
+    output_format: Format,
+    output: &Path,
+    wrap: bool,
+    supercell: Option<[u32; 3]>,
+    resolution: f32,
+) -> Result<(), String> {
+    let mut structure = load(input)?;
+    if wrap {
+        structure
+            .wrap_into_cell()
+            .map_err(|e| format!("can't wrap `{}` into its cell: {}", input.display(), e))?;
+    }
+    if let Some(counts) = supercell {
+        structure = structure
+            .supercell(counts)
+            .map_err(|e| format!("can't make a supercell of `{}`: {}", input.display(), e))?;
+    }
+    write(output_format, &structure, output, resolution)?;
+
+    log::info!(
+        "converted {} atoms from `{}` to `{}`",
+        structure.atoms().count(),
+        input.display(),
+        output.display()
+    );
+
+    Ok(())
+}
+
+pub fn read(format: Format, path: &Path) -> Result<Structure, String> {
+    let name = file_name(path);
+    let contents = || {
+        fs::read_to_string(path).map_err(|e| format!("failed to read `{}`: {}", path.display(), e))
+    };
+
+    match format {
+        Format::Xyz => xyz::read_xyz(&name, &contents()?),
+        Format::Pdb => pdb::read_pdb(&name, path),
+        Format::Cif => cif::read_cif(&name, path),
+        Format::Mol => mol::read_mol(&name, &contents()?),
+        Format::Smiles => smiles::read_smiles(&name, &contents()?),
+        Format::Stl => Err("stl files only hold a surface, so they can't be opened".to_string()),
+        Format::Gltf | Format::Glb => Err("gltf files can't be opened".to_string()),
+    }
+}
+
+pub fn write(
+    format: Format,
+    structure: &Structure,
+    path: &Path,
+    resolution: f32,
+) -> Result<(), String> {
+    let name = file_name(path);
+
+    if format == Format::Cif {
+        return Err("cif files can't be written yet".to_string());
+    }
+
+    let file =
+        File::create(path).map_err(|e| format!("failed to create `{}`: {}", path.display(), e))?;
+    let writer = BufWriter::new(file);
+
+    match format {
+        Format::Xyz => xyz::write_xyz(structure, &name, writer),
+        Format::Pdb => pdb::write_pdb(structure, writer),
+        Format::Mol => mol::write_mol(structure, &name, writer),
+        Format::Smiles => smiles::write_smiles(structure, &name, writer),
+        Format::Stl => {
+            let mesh = render::vdw_surface(
+                structure.atoms().copied(),
+                &PeriodicTable::new(),
+                resolution,
+            );
+            stl::write_stl(&mesh, writer)
+        }
+        Format::Gltf => {
+            let bin_path = path.with_extension("bin");
+            let bin_uri = bin_path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+
+            File::create(&bin_path).and_then(|bin| {
+                gltf::write_gltf(
+                    structure,
+                    gltf::SphereResolution::default(),
+                    &bin_uri,
+                    writer,
+                    BufWriter::new(bin),
+                )
+            })
+        }
+        Format::Glb => gltf::write_glb(structure, gltf::SphereResolution::default(), writer),
+        Format::Cif => unreachable!(),
+    }
+    .map_err(|e| format!("failed to write `{}`: {}", path.display(), e))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
@@ -0,0 +1,137 @@
+//! Reports panics somewhere the user can actually see them.
+//!
+//! The hook is installed before anything else, so it can't rely on logging or windowing
+//! being initialized, and it must never panic itself.
+
+use parking_lot::{const_mutex, Mutex};
+use std::{
+    fmt::Write as _,
+    panic::PanicInfo,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+// Filled in once the renderer has picked an adapter.
+static GPU: Mutex<Option<String>> = const_mutex(None);
+// Set when running from the command line without a window, where there may be no display to
+// show a dialog on.
+static HEADLESS: AtomicBool = AtomicBool::new(false);
+
+pub fn install() {
+    std::panic::set_hook(Box::new(|info| {
+        let report = report(info);
+        handle(info, &report);
+    }));
+}
+
+pub fn set_gpu(description: String) {
+    *GPU.lock() = Some(description);
+}
+
+/// Reports are only printed and saved from now on, without showing a dialog.
+pub fn set_headless() {
+    HEADLESS.store(true, Ordering::Relaxed);
+}
+
+fn report(info: &PanicInfo) -> String {
+    let mut report = String::with_capacity(4096);
+
+    let message = if let Some(message) = info.payload().downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = info.payload().downcast_ref::<String>() {
+        message.as_str()
+    } else {
+        "<unknown>"
+    };
+
+    // Writing into a string can't fail.
+    let _ = writeln!(report, "atomCAD {} crashed", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(report);
+    let _ = writeln!(report, "message: {}", message);
+    if let Some(location) = info.location() {
+        let _ = writeln!(report, "location: {}", location);
+    }
+    let _ = writeln!(
+        report,
+        "os: {} ({})",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    // The panic may have happened while the lock was held.
+    match GPU.try_lock().as_deref() {
+        Some(Some(gpu)) => {
+            let _ = writeln!(report, "gpu: {}", gpu);
+        }
+        Some(None) => {
+            let _ = writeln!(report, "gpu: not initialized");
+        }
+        None => {}
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = writeln!(report);
+        let _ = writeln!(report, "{:?}", backtrace::Backtrace::new());
+    }
+
+    report
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn handle(_info: &PanicInfo, report: &str) {
+    use std::{fs, time::SystemTime};
+
+    eprintln!("{}", report);
+
+    let dir = dirs::data_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("atomCAD")
+        .join("crashes");
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let path = dir.join(format!("crash-{}.txt", timestamp));
+
+    let message = match fs::create_dir_all(&dir).and_then(|_| fs::write(&path, report)) {
+        Ok(()) => {
+            eprintln!("a crash report was saved to `{}`", path.display());
+            format!("atomCAD crashed. A report was saved to {}", path.display())
+        }
+        Err(e) => {
+            eprintln!("failed to save a crash report: {}", e);
+            "atomCAD crashed. The report couldn't be saved, but it was printed to stderr."
+                .to_string()
+        }
+    };
+
+    if HEADLESS.load(Ordering::Relaxed) {
+        return;
+    }
+
+    // Quotes break some of the dialog backends.
+    let message = message.replace(|c| c == '"' || c == '\'', "");
+    tinyfiledialogs::message_box_ok("atomCAD", &message, tinyfiledialogs::MessageBoxIcon::Error);
+}
+
+#[cfg(target_arch = "wasm32")]
+fn handle(info: &PanicInfo, report: &str) {
+    console_error_panic_hook::hook(info);
+
+    // Show the report over the canvas, since the console is usually closed.
+    let banner = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| {
+            let banner = document.create_element("pre").ok()?;
+            document.body()?.append_child(&banner).ok()?;
+            Some(banner)
+        });
+
+    if let Some(banner) = banner {
+        let _ = banner.set_attribute(
+            "style",
+            "position: fixed; top: 0; left: 0; right: 0; margin: 0; padding: 1em; \
+             background: #b00020; color: white; white-space: pre-wrap;",
+        );
+        banner.set_text_content(Some(report));
+    }
+}
@@ -0,0 +1,174 @@
+//! Duplicating the selected part with ctrl-D, and then placing the copy with the mouse.
+//!
+//! The copy starts a little way off from the original and follows the cursor across a plane
+//! that faces the camera and goes through the copy, so it stays at the same depth. Clicking
+//! drops it where it is, and Escape removes it again.
+
+use crate::placement::cursor_ray;
+use render::{CameraRepr, GlobalRenderResources, PartId, World};
+use ultraviolet::Vec3;
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent},
+};
+
+/// How far the copy starts from the original, in angstroms, so the two don't overlap before
+/// the mouse moves.
+const START_OFFSET: f32 = 2.0;
+
+pub struct Duplication {
+    cursor: PhysicalPosition<f64>,
+    placing: Option<Placing>,
+}
+
+struct Placing {
+    id: PartId,
+    /// A point on the plane the copy moves on.
+    center: Vec3,
+    /// Where the cursor last hit that plane.
+    grab: Option<Vec3>,
+}
+
+impl Duplication {
+    pub fn new() -> Self {
+        Self {
+            cursor: PhysicalPosition::new(0.0, 0.0),
+            placing: None,
+        }
+    }
+
+    /// Copies `source` and starts placing the copy. Returns the copy.
+    pub fn start(
+        &mut self,
+        world: &mut World,
+        gpu_resources: &GlobalRenderResources,
+        source: PartId,
+        size: PhysicalSize<u32>,
+        camera: Option<CameraRepr>,
+    ) -> PartId {
+        self.finish(world);
+
+        let id = world.copy_part(gpu_resources, source);
+        let offset = match camera {
+            Some(camera) => (camera.right() + camera.up()) * START_OFFSET,
+            None => Vec3::new(START_OFFSET, 0.0, 0.0),
+        };
+        let part = world.part_mut(id);
+        part.offset_by(offset.x, offset.y, offset.z);
+        log::info!("placing `{}`, click to drop it", part.name());
+
+        let center = world
+            .part(id)
+            .map(|part| {
+                let (sum, count) = world
+                    .part_atoms(part)
+                    .fold((Vec3::zero(), 0), |(sum, count), atom| {
+                        (sum + atom.pos, count + 1)
+                    });
+                sum / count.max(1) as f32
+            })
+            .unwrap_or_else(Vec3::zero);
+
+        let mut placing = Placing {
+            id,
+            center,
+            grab: None,
+        };
+        placing.grab = camera.and_then(|camera| self.hit(&placing, size, &camera));
+        self.placing = Some(placing);
+
+        id
+    }
+
+    /// Returns true if the event placed or cancelled the copy, in which case nothing else should
+    /// handle it.
+    pub fn update(
+        &mut self,
+        event: &WindowEvent,
+        size: PhysicalSize<u32>,
+        camera: Option<CameraRepr>,
+        world: &mut World,
+    ) -> bool {
+        // The copy might have been removed some other way since.
+        if let Some(placing) = &self.placing {
+            if world.part(placing.id).is_none() {
+                self.placing = None;
+            }
+        }
+
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = *position;
+
+                let hit = match (&self.placing, camera) {
+                    (Some(placing), Some(camera)) => self.hit(placing, size, &camera),
+                    _ => None,
+                };
+                if let (Some(placing), Some(hit)) = (&mut self.placing, hit) {
+                    if let Some(grab) = placing.grab {
+                        let delta = hit - grab;
+                        world
+                            .part_mut(placing.id)
+                            .offset_by(delta.x, delta.y, delta.z);
+                    }
+                    placing.grab = Some(hit);
+                }
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } if self.placing.is_some() => {
+                self.finish(world);
+                return true;
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Escape),
+                        ..
+                    },
+                ..
+            } => {
+                if let Some(placing) = self.placing.take() {
+                    if let Some(part) = world.remove_part(placing.id) {
+                        log::info!("cancelled duplicating `{}`", part.name());
+                    }
+                    return true;
+                }
+            }
+            _ => {}
+        }
+
+        false
+    }
+
+    /// Drops the copy that's being placed, if there is one.
+    fn finish(&mut self, world: &World) {
+        if let Some(part) = self
+            .placing
+            .take()
+            .and_then(|placing| world.part(placing.id))
+        {
+            log::info!("placed `{}`", part.name());
+        }
+    }
+
+    /// Where the cursor is on the plane that the copy moves on.
+    fn hit(&self, placing: &Placing, size: PhysicalSize<u32>, camera: &CameraRepr) -> Option<Vec3> {
+        let (origin, direction) = cursor_ray(self.cursor, size, camera)?;
+
+        let normal = camera.forward();
+        let facing = direction.dot(normal);
+        if facing.abs() < 1.0e-6 {
+            return None;
+        }
+        let distance = (placing.center - origin).dot(normal) / facing;
+        if distance > 0.0 {
+            Some(origin + direction * distance)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Eigenvectors of the small symmetric matrices that come up in geometry, like inertia tensors.
+
+/// Jacobi rotations stop once the off-diagonal part is this small compared to the diagonal.
+const TOLERANCE: f32 = 1.0e-6;
+const MAX_SWEEPS: usize = 50;
+
+/// Diagonalizes a symmetric matrix with cyclic Jacobi rotations. Returns the eigenvalues, and
+/// the eigenvectors as columns, in no particular order.
+pub fn symmetric_eigen(mut a: [[f32; 3]; 3]) -> ([f32; 3], [[f32; 3]; 3]) {
+    let mut v = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+    for _ in 0..MAX_SWEEPS {
+        let off_diagonal = a[0][1] * a[0][1] + a[0][2] * a[0][2] + a[1][2] * a[1][2];
+        let diagonal = a[0][0] * a[0][0] + a[1][1] * a[1][1] + a[2][2] * a[2][2];
+        if off_diagonal <= TOLERANCE * TOLERANCE * diagonal.max(f32::MIN_POSITIVE) {
+            break;
+        }
+
+        for &(p, q) in &[(0, 1), (0, 2), (1, 2)] {
+            if a[p][q].abs() < f32::MIN_POSITIVE {
+                continue;
+            }
+
+            // The rotation that zeroes a[p][q], from Numerical Recipes.
+            let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+            let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+            let c = 1.0 / (t * t + 1.0).sqrt();
+            let s = t * c;
+
+            for k in 0..3 {
+                let (akp, akq) = (a[k][p], a[k][q]);
+                a[k][p] = c * akp - s * akq;
+                a[k][q] = s * akp + c * akq;
+            }
+            for k in 0..3 {
+                let (apk, aqk) = (a[p][k], a[q][k]);
+                a[p][k] = c * apk - s * aqk;
+                a[q][k] = s * apk + c * aqk;
+            }
+            for row in &mut v {
+                let (vkp, vkq) = (row[p], row[q]);
+                row[p] = c * vkp - s * vkq;
+                row[q] = s * vkp + c * vkq;
+            }
+        }
+    }
+
+    ([a[0][0], a[1][1], a[2][2]], v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eigenvectors_are_scaled_by_their_eigenvalues() {
+        let a = [[4.0, 1.0, -2.0], [1.0, 2.0, 0.5], [-2.0, 0.5, 3.0]];
+        let (values, vectors) = symmetric_eigen(a);
+
+        for k in 0..3 {
+            let v = [vectors[0][k], vectors[1][k], vectors[2][k]];
+            for i in 0..3 {
+                let av: f32 = (0..3).map(|j| a[i][j] * v[j]).sum();
+                assert!((av - values[k] * v[i]).abs() < 1.0e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn eigenvectors_are_orthonormal() {
+        let (_, vectors) = symmetric_eigen([[1.0, 2.0, 3.0], [2.0, -1.0, 0.0], [3.0, 0.0, 5.0]]);
+
+        for a in 0..3 {
+            for b in 0..3 {
+                let dot: f32 = (0..3).map(|i| vectors[i][a] * vectors[i][b]).sum();
+                let expected = if a == b { 1.0 } else { 0.0 };
+                assert!((dot - expected).abs() < 1.0e-4);
+            }
+        }
+    }
+}
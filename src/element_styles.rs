@@ -0,0 +1,137 @@
+//! Per-element color and radius overrides, read from a json file.
+//!
+//! The file maps element symbols to the parts of their appearance that should change, and
+//! every element or field that's left out keeps its default:
+//!
+//! ```json
+//! {
+//!     "C": { "color": [0.2, 0.2, 0.2] },
+//!     "Au": { "color": [1.0, 0.84, 0.0], "radius": 1.66 }
+//! }
+//! ```
+//!
+//! Colors are linear rgb, and radii are in angstroms. The file is checked for changes every so
+//! often and reloaded, so it can be edited while the app is running.
+
+use periodic_table::{Element, ElementRepr, PeriodicTable};
+use serde_json::Value;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use ultraviolet::Vec3;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Watches a file of element styles for changes.
+pub struct ElementStyles {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    last_poll: instant::Instant,
+}
+
+impl ElementStyles {
+    /// Reads the styles in `path`. If that fails, the default styles are used until the file
+    /// is fixed.
+    pub fn load(path: PathBuf) -> (Self, PeriodicTable) {
+        let styles = Self {
+            modified: modified_time(&path),
+            path,
+            last_poll: instant::Instant::now(),
+        };
+
+        let periodic_table = read_element_styles(&styles.path).unwrap_or_else(|e| {
+            log::error!("{}", e);
+            PeriodicTable::new()
+        });
+
+        (styles, periodic_table)
+    }
+
+    /// Returns the new periodic table if the file changed since it was last read.
+    ///
+    /// If it can't be read, this logs why, and the current styles should be kept.
+    pub fn poll(&mut self) -> Option<PeriodicTable> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return None;
+        }
+        self.last_poll = instant::Instant::now();
+
+        let modified = modified_time(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        match read_element_styles(&self.path) {
+            Ok(periodic_table) => {
+                log::info!("loaded element styles from `{}`", self.path.display());
+                Some(periodic_table)
+            }
+            Err(e) => {
+                log::error!("{}", e);
+                None
+            }
+        }
+    }
+}
+
+/// The default periodic table, with the overrides in `path` applied.
+pub fn read_element_styles(path: &Path) -> Result<PeriodicTable, String> {
+    let error = |e: &dyn std::fmt::Display| {
+        format!(
+            "failed to read element styles from `{}`: {}",
+            path.display(),
+            e
+        )
+    };
+
+    let contents = fs::read_to_string(path).map_err(|e| error(&e))?;
+    let styles: Value = serde_json::from_str(&contents).map_err(|e| error(&e))?;
+    let styles = styles
+        .as_object()
+        .ok_or_else(|| error(&"expected an object of element symbols"))?;
+
+    let mut periodic_table = PeriodicTable::new();
+    for (symbol, style) in styles {
+        let element = Element::from_symbol(symbol)
+            .ok_or_else(|| error(&format!("unknown element `{}`", symbol)))?;
+        let default = periodic_table.element_repr(element);
+
+        let color = match style.get("color") {
+            Some(color) => {
+                parse_color(color).ok_or_else(|| error(&format!("invalid color for {}", symbol)))?
+            }
+            None => default.color(),
+        };
+        let radius = match style.get("radius") {
+            Some(radius) => radius
+                .as_f64()
+                .map(|radius| radius as f32)
+                .filter(|&radius| radius > 0.0)
+                .ok_or_else(|| error(&format!("invalid radius for {}", symbol)))?,
+            None => default.radius(),
+        };
+
+        periodic_table.set_element_repr(element, ElementRepr::new(color, radius));
+    }
+
+    Ok(periodic_table)
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn parse_color(value: &Value) -> Option<Vec3> {
+    let components = value.as_array()?;
+    if components.len() != 3 {
+        return None;
+    }
+
+    let component = |index: usize| components[index].as_f64().map(|c| c as f32);
+    Some(Vec3::new(component(0)?, component(1)?, component(2)?))
+}
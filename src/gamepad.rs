@@ -0,0 +1,84 @@
+//! Navigating with a gamepad.
+//!
+//! The left stick pans, the right stick orbits, and the triggers dolly in and out. Only the
+//! first connected gamepad is used. Without one, or on platforms gilrs doesn't support, this
+//! does nothing.
+
+use common::Navigation;
+use gilrs::{Axis, Button, EventType, Gilrs};
+use instant::Instant;
+
+/// Sticks don't quite rest at zero, so anything smaller than this is ignored.
+const DEADZONE: f32 = 0.15;
+/// Caps the movement after a long frame, like when the window was being dragged.
+const MAX_FRAME_TIME: f32 = 0.1;
+
+pub struct Gamepads {
+    gilrs: Option<Gilrs>,
+    last_poll: Instant,
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(e) => {
+                log::info!("gamepads aren't available: {}", e);
+                None
+            }
+        };
+
+        Self {
+            gilrs,
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// How the gamepad moved since the last poll, if it did. This should be called every frame.
+    pub fn poll(&mut self) -> Option<Navigation> {
+        let elapsed = self.last_poll.elapsed().as_secs_f32().min(MAX_FRAME_TIME);
+        self.last_poll = Instant::now();
+
+        let gilrs = self.gilrs.as_mut()?;
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                EventType::Connected => {
+                    log::info!("connected `{}`", gilrs.gamepad(event.id).name());
+                }
+                EventType::Disconnected => {
+                    log::info!("disconnected `{}`", gilrs.gamepad(event.id).name());
+                }
+                _ => {}
+            }
+        }
+
+        let (_, gamepad) = gilrs.gamepads().next()?;
+        let axis = |axis: Axis| {
+            let value = gamepad.value(axis);
+            if value.abs() < DEADZONE {
+                0.0
+            } else {
+                value * elapsed
+            }
+        };
+        let trigger = |button: Button| {
+            gamepad
+                .button_data(button)
+                .map_or(0.0, |data| data.value() * elapsed)
+        };
+
+        let navigation = Navigation {
+            yaw: axis(Axis::RightStickX),
+            pitch: axis(Axis::RightStickY),
+            pan_right: axis(Axis::LeftStickX),
+            pan_up: axis(Axis::LeftStickY),
+            dolly: trigger(Button::RightTrigger2) - trigger(Button::LeftTrigger2),
+        };
+
+        if navigation.is_zero() {
+            None
+        } else {
+            Some(navigation)
+        }
+    }
+}
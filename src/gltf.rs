@@ -0,0 +1,376 @@
+//! glTF 2.0, for Blender and web viewers. This is write-only.
+//!
+//! Every atom is a node that scales and moves a shared unit sphere, so the geometry is only
+//! stored once. Each element gets its own mesh, which only differs by material. Units are
+//! angstroms, and structures are rotated from atomCAD's z-up to glTF's y-up.
+
+use crate::structure::Structure;
+use periodic_table::{Element, PeriodicTable};
+use serde_json::json;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    f32::consts::PI,
+    io,
+};
+use ultraviolet::Vec3;
+
+// From the glTF spec.
+const FLOAT: u32 = 5126;
+const UNSIGNED_SHORT: u32 = 5123;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const GLB_MAGIC: u32 = 0x4654_6c67; // "glTF"
+const GLB_JSON_CHUNK: u32 = 0x4e4f_534a; // "JSON"
+const GLB_BIN_CHUNK: u32 = 0x004e_4942; // "BIN\0"
+
+/// How finely the atom spheres are tessellated.
+#[derive(Debug, Copy, Clone)]
+pub struct SphereResolution {
+    /// From pole to pole.
+    pub rings: u16,
+    /// Around the equator.
+    pub segments: u16,
+}
+
+impl Default for SphereResolution {
+    fn default() -> Self {
+        Self {
+            rings: 16,
+            segments: 32,
+        }
+    }
+}
+
+/// Writes the json to `writer` and the geometry to `bin_writer`, which the json refers to as
+/// `bin_uri`.
+pub fn write_gltf<W, B>(
+    structure: &Structure,
+    resolution: SphereResolution,
+    bin_uri: &str,
+    writer: W,
+    mut bin_writer: B,
+) -> io::Result<()>
+where
+    W: io::Write,
+    B: io::Write,
+{
+    let (gltf, bin) = build(structure, resolution, Some(bin_uri))?;
+
+    serde_json::to_writer(writer, &gltf)?;
+    bin_writer.write_all(&bin)
+}
+
+/// Writes a single binary `.glb` file, with the geometry embedded.
+pub fn write_glb<W: io::Write>(
+    structure: &Structure,
+    resolution: SphereResolution,
+    mut writer: W,
+) -> io::Result<()> {
+    let (gltf, bin) = build(structure, resolution, None)?;
+
+    // Chunks have to be a multiple of four bytes long, and json is padded with spaces.
+    let mut json = serde_json::to_vec(&gltf)?;
+    json.resize((json.len() + 3) / 4 * 4, b' ');
+
+    let length = 12 + 8 + json.len() + 8 + bin.len();
+    if length > u32::MAX as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the structure is too large for a glb file",
+        ));
+    }
+
+    writer.write_all(&GLB_MAGIC.to_le_bytes())?;
+    writer.write_all(&2u32.to_le_bytes())?;
+    writer.write_all(&(length as u32).to_le_bytes())?;
+
+    writer.write_all(&(json.len() as u32).to_le_bytes())?;
+    writer.write_all(&GLB_JSON_CHUNK.to_le_bytes())?;
+    writer.write_all(&json)?;
+
+    writer.write_all(&(bin.len() as u32).to_le_bytes())?;
+    writer.write_all(&GLB_BIN_CHUNK.to_le_bytes())?;
+    writer.write_all(&bin)
+}
+
+/// Returns the json and the binary buffer, which is already padded to four bytes.
+///
+/// Without a `bin_uri`, the buffer is the one embedded in a glb.
+fn build(
+    structure: &Structure,
+    resolution: SphereResolution,
+    bin_uri: Option<&str>,
+) -> io::Result<(serde_json::Value, Vec<u8>)> {
+    let periodic_table = PeriodicTable::new();
+    let (positions, indices) = unit_sphere(resolution)?;
+
+    // A unit sphere's normals are its positions.
+    let positions_len = positions.len() * 12;
+    let indices_len = indices.len() * 2;
+    let mut bin = Vec::with_capacity(2 * positions_len + indices_len + 2);
+    for _ in 0..2 {
+        for position in &positions {
+            for component in &[position.x, position.y, position.z] {
+                bin.extend_from_slice(&component.to_le_bytes());
+            }
+        }
+    }
+    for index in &indices {
+        bin.extend_from_slice(&index.to_le_bytes());
+    }
+    // Buffers have to be a multiple of four bytes long.
+    bin.resize((bin.len() + 3) / 4 * 4, 0);
+
+    // One mesh and material per element, in a stable order.
+    let elements: BTreeSet<Element> = structure.atoms().map(|atom| atom.kind.element()).collect();
+    let mesh_indices: BTreeMap<Element, usize> = elements
+        .iter()
+        .enumerate()
+        .map(|(index, &element)| (element, index))
+        .collect();
+
+    let materials: Vec<_> = elements
+        .iter()
+        .map(|&element| {
+            let color = periodic_table.element_repr(element).color();
+            json!({
+                "name": element.symbol(),
+                "pbrMetallicRoughness": {
+                    "baseColorFactor": [color.x, color.y, color.z, 1.0],
+                    "metallicFactor": 0.0,
+                    "roughnessFactor": 0.5,
+                },
+            })
+        })
+        .collect();
+
+    let meshes: Vec<_> = elements
+        .iter()
+        .enumerate()
+        .map(|(material, element)| {
+            json!({
+                "name": element.symbol(),
+                "primitives": [{
+                    "attributes": { "POSITION": 0, "NORMAL": 1 },
+                    "indices": 2,
+                    "material": material,
+                }],
+            })
+        })
+        .collect();
+
+    // Each part is a node, with a child node for every atom.
+    let mut nodes = Vec::new();
+    let mut roots = Vec::new();
+    for part in &structure.parts {
+        let atoms = part.fragments.iter().flatten();
+        let first_child = nodes.len() + 1;
+        let children: Vec<_> = (first_child..first_child + atoms.clone().count()).collect();
+
+        roots.push(nodes.len());
+        nodes.push(json!({ "name": part.name, "children": children }));
+
+        for atom in atoms {
+            let element = atom.kind.element();
+            let radius = periodic_table.element_repr(element).radius();
+            let pos = z_up_to_y_up(atom.pos);
+            nodes.push(json!({
+                "mesh": mesh_indices[&element],
+                "translation": [pos.x, pos.y, pos.z],
+                "scale": [radius, radius, radius],
+            }));
+        }
+    }
+
+    let mut buffer = json!({ "byteLength": bin.len() });
+    if let Some(bin_uri) = bin_uri {
+        buffer["uri"] = json!(bin_uri);
+    }
+
+    let gltf = json!({
+        "asset": { "version": "2.0", "generator": "atomCAD" },
+        "scene": 0,
+        "scenes": [{ "nodes": roots }],
+        "nodes": nodes,
+        "meshes": meshes,
+        "materials": materials,
+        "buffers": [buffer],
+        "bufferViews": [
+            {
+                "buffer": 0,
+                "byteOffset": 0,
+                "byteLength": positions_len,
+                "target": ARRAY_BUFFER,
+            },
+            {
+                "buffer": 0,
+                "byteOffset": positions_len,
+                "byteLength": positions_len,
+                "target": ARRAY_BUFFER,
+            },
+            {
+                "buffer": 0,
+                "byteOffset": 2 * positions_len,
+                "byteLength": indices_len,
+                "target": ELEMENT_ARRAY_BUFFER,
+            },
+        ],
+        "accessors": [
+            {
+                "bufferView": 0,
+                "componentType": FLOAT,
+                "count": positions.len(),
+                "type": "VEC3",
+                "min": [-1.0, -1.0, -1.0],
+                "max": [1.0, 1.0, 1.0],
+            },
+            {
+                "bufferView": 1,
+                "componentType": FLOAT,
+                "count": positions.len(),
+                "type": "VEC3",
+            },
+            {
+                "bufferView": 2,
+                "componentType": UNSIGNED_SHORT,
+                "count": indices.len(),
+                "type": "SCALAR",
+            },
+        ],
+    });
+
+    Ok((gltf, bin))
+}
+
+/// Both are right-handed, so this is a rotation rather than a flip.
+fn z_up_to_y_up(v: Vec3) -> Vec3 {
+    Vec3::new(v.x, v.z, -v.y)
+}
+
+/// A uv sphere with counter-clockwise triangles.
+fn unit_sphere(resolution: SphereResolution) -> io::Result<(Vec<Vec3>, Vec<u16>)> {
+    let SphereResolution { rings, segments } = resolution;
+
+    // Indices are 16-bit.
+    let vertices = (rings as usize + 1) * (segments as usize + 1);
+    if rings < 2 || segments < 3 || vertices > u16::MAX as usize + 1 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid sphere resolution {:?}", resolution),
+        ));
+    }
+
+    let mut positions = Vec::with_capacity(vertices);
+    for ring in 0..=rings {
+        let theta = PI * ring as f32 / rings as f32;
+        for segment in 0..=segments {
+            let phi = 2.0 * PI * segment as f32 / segments as f32;
+            positions.push(Vec3::new(
+                theta.sin() * phi.cos(),
+                theta.cos(),
+                -theta.sin() * phi.sin(),
+            ));
+        }
+    }
+
+    let mut indices = Vec::new();
+    let stride = segments + 1;
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let a = ring * stride + segment;
+            let b = a + stride;
+            indices.extend_from_slice(&[a, b, a + 1, a + 1, b, b + 1]);
+        }
+    }
+
+    Ok((positions, indices))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure::StructurePart;
+    use render::{AtomKind, AtomRepr};
+    use serde_json::Value;
+
+    const RESOLUTION: SphereResolution = SphereResolution {
+        rings: 4,
+        segments: 6,
+    };
+
+    fn atom(element: Element, x: f32) -> AtomRepr {
+        AtomRepr {
+            pos: Vec3::new(x, 0.0, 0.0),
+            kind: AtomKind::new(element),
+        }
+    }
+
+    fn structure() -> Structure {
+        Structure {
+            parts: vec![
+                StructurePart {
+                    name: "water".to_string(),
+                    fragments: vec![vec![
+                        atom(Element::Oxygen, 0.0),
+                        atom(Element::Hydrogen, 1.0),
+                        atom(Element::Hydrogen, -1.0),
+                    ]],
+                },
+                StructurePart {
+                    name: "carbon".to_string(),
+                    fragments: vec![vec![atom(Element::Carbon, 5.0)]],
+                },
+            ],
+            cell: None,
+        }
+    }
+
+    #[test]
+    fn gltf_refers_to_the_geometry() {
+        let (mut json, mut bin) = (Vec::new(), Vec::new());
+        write_gltf(&structure(), RESOLUTION, "water.bin", &mut json, &mut bin).unwrap();
+        let gltf: Value = serde_json::from_slice(&json).unwrap();
+
+        // A node for each part, and one for each atom.
+        assert_eq!(gltf["nodes"].as_array().unwrap().len(), 2 + 4);
+        assert_eq!(gltf["scenes"][0]["nodes"], serde_json::json!([0, 4]));
+        assert_eq!(gltf["nodes"][0]["children"], serde_json::json!([1, 2, 3]));
+        // One mesh for each element.
+        assert_eq!(gltf["meshes"].as_array().unwrap().len(), 3);
+
+        assert_eq!(gltf["buffers"][0]["uri"], "water.bin");
+        assert_eq!(gltf["buffers"][0]["byteLength"], bin.len());
+        assert_eq!(bin.len() % 4, 0);
+
+        let vertices = (4 + 1) * (6 + 1);
+        let indices = 4 * 6 * 6;
+        assert_eq!(gltf["accessors"][0]["count"], vertices);
+        assert_eq!(gltf["accessors"][1]["count"], vertices);
+        assert_eq!(gltf["accessors"][2]["count"], indices);
+        assert_eq!(bin.len(), 2 * vertices * 12 + indices * 2);
+    }
+
+    #[test]
+    fn glb_embeds_the_geometry() {
+        let mut glb = Vec::new();
+        write_glb(&structure(), RESOLUTION, &mut glb).unwrap();
+
+        let word = |offset: usize| {
+            let mut word = [0; 4];
+            word.copy_from_slice(&glb[offset..offset + 4]);
+            u32::from_le_bytes(word) as usize
+        };
+        assert_eq!(word(0), GLB_MAGIC as usize);
+        assert_eq!(word(8), glb.len());
+
+        let json_len = word(12);
+        assert_eq!(word(16), GLB_JSON_CHUNK as usize);
+        let gltf: Value = serde_json::from_slice(&glb[20..20 + json_len]).unwrap();
+        assert!(gltf["buffers"][0].get("uri").is_none());
+
+        let bin_len = word(20 + json_len);
+        assert_eq!(word(24 + json_len), GLB_BIN_CHUNK as usize);
+        assert_eq!(gltf["buffers"][0]["byteLength"], bin_len);
+        assert_eq!(glb.len(), 28 + json_len + bin_len);
+    }
+}
@@ -0,0 +1,77 @@
+//! Renders a structure into a PNG without opening a window.
+
+use crate::{
+    camera::{bounding_sphere, ArcballCamera},
+    convert,
+};
+use render::{Interactions, RenderOptions, Renderer, SsaoOptions, MAX_TEXTURE_DIMENSION};
+use std::{fs::File, io::BufWriter, path::Path};
+use winit::dpi::PhysicalSize;
+
+pub async fn render_to_png(
+    input: &Path,
+    output: &Path,
+    size: PhysicalSize<u32>,
+    gpu: Option<String>,
+    ssao: bool,
+) -> Result<(), String> {
+    if size.width > MAX_TEXTURE_DIMENSION || size.height > MAX_TEXTURE_DIMENSION {
+        return Err(format!(
+            "cannot render a {}x{} image, because neither side can be larger than {} pixels",
+            size.width, size.height, MAX_TEXTURE_DIMENSION
+        ));
+    }
+
+    let mut world = convert::load(input)
+        .map_err(|e| format!("failed to load `{}`: {}", input.display(), e))?
+        .into_world();
+
+    let (mut renderer, _gpu_resources) = Renderer::new_headless(
+        size,
+        RenderOptions {
+            fxaa: Some(()), // placeholder
+            attempt_gpu_driven: false,
+            gpu,
+            ssao: if ssao {
+                Some(SsaoOptions::default())
+            } else {
+                None
+            },
+        },
+    )
+    .await
+    .map_err(|e| format!("failed to initialize the renderer: {}", e))?;
+
+    crate::crash::set_gpu(format!("{:?}", renderer.adapter_info()));
+
+    for part in world.parts_mut() {
+        part.move_to(0.0, 0.0, 0.0);
+    }
+
+    // There's no way to move the camera, so it has to start out looking at everything.
+    let camera = ArcballCamera::new(100.0, 1.0);
+    renderer.set_camera(match bounding_sphere(&world) {
+        Some((center, radius)) => camera.with_bounds(center, radius).zoomed_to_fit(),
+        None => camera,
+    });
+
+    renderer.render(&mut world, &Interactions::default());
+
+    let pixels = renderer
+        .read_frame()
+        .await
+        .ok_or_else(|| "failed to read back the rendered frame".to_string())?;
+
+    write_png(output, size, &pixels)
+        .map_err(|e| format!("failed to write `{}`: {}", output.display(), e))
+}
+
+fn write_png(path: &Path, size: PhysicalSize<u32>, rgba: &[u8]) -> Result<(), png::EncodingError> {
+    let file = File::create(path)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), size.width, size.height);
+    encoder.set_color(png::ColorType::RGBA);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(rgba)
+}
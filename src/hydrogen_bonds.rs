@@ -0,0 +1,157 @@
+//! Hydrogen bonds, found from the geometry alone.
+//!
+//! A hydrogen bond needs a hydrogen that's covalently bonded to a nitrogen, oxygen, or
+//! fluorine (the donor), and another of those within reach of the donor (the acceptor), with
+//! the hydrogen roughly between them. These aren't bonds in the structure; they're only found
+//! and reported. Structures from the PDB usually leave hydrogens out, and then there's nothing
+//! to find.
+
+use crate::{
+    convert,
+    minimize::{infer_bonds, pairs_within},
+};
+use periodic_table::Element;
+use render::AtomRepr;
+use std::path::Path;
+
+/// The longest donor-acceptor distance, in angstroms.
+const MAX_DISTANCE: f32 = 3.5;
+/// The smallest donor-hydrogen-acceptor angle, in degrees.
+const MIN_ANGLE: f32 = 120.0;
+
+/// Indices into the atoms that a hydrogen bond was found in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HydrogenBond {
+    pub donor: usize,
+    pub hydrogen: usize,
+    pub acceptor: usize,
+}
+
+fn is_electronegative(element: Element) -> bool {
+    [Element::Nitrogen, Element::Oxygen, Element::Fluorine].contains(&element)
+}
+
+pub fn find_hydrogen_bonds(atoms: &[AtomRepr]) -> Vec<HydrogenBond> {
+    let element = |index: usize| atoms[index].kind.element();
+
+    // The hydrogens that can be donated, along with their donors.
+    let mut donated = Vec::new();
+    for (a, b) in infer_bonds(atoms) {
+        for &(hydrogen, donor) in &[(a, b), (b, a)] {
+            if element(hydrogen) == Element::Hydrogen && is_electronegative(element(donor)) {
+                donated.push((hydrogen, donor));
+            }
+        }
+    }
+
+    let positions: Vec<_> = atoms.iter().map(|atom| atom.pos).collect();
+    let mut acceptors = vec![Vec::new(); atoms.len()];
+    for (a, b) in pairs_within(&positions, MAX_DISTANCE) {
+        if is_electronegative(element(a))
+            && is_electronegative(element(b))
+            && (positions[b] - positions[a]).mag() <= MAX_DISTANCE
+        {
+            acceptors[a].push(b);
+            acceptors[b].push(a);
+        }
+    }
+
+    let max_cos = MIN_ANGLE.to_radians().cos();
+    let mut bonds = Vec::new();
+    for (hydrogen, donor) in donated {
+        let to_donor = (positions[donor] - positions[hydrogen]).normalized();
+        for &acceptor in &acceptors[donor] {
+            let to_acceptor = (positions[acceptor] - positions[hydrogen]).normalized();
+            if to_donor.dot(to_acceptor) <= max_cos {
+                bonds.push(HydrogenBond {
+                    donor,
+                    hydrogen,
+                    acceptor,
+                });
+            }
+        }
+    }
+
+    bonds
+}
+
+/// Prints every hydrogen bond in a structure file. Atoms are numbered from one, in the order
+/// they're in the file.
+pub fn print_hydrogen_bonds(path: &Path) -> Result<(), String> {
+    let structure = convert::load(path)?;
+    let atoms: Vec<_> = structure.atoms().copied().collect();
+    let bonds = find_hydrogen_bonds(&atoms);
+
+    let label = |index: usize| format!("{}{}", atoms[index].kind.element().symbol(), index + 1);
+    for bond in &bonds {
+        let (donor, hydrogen, acceptor) = (
+            atoms[bond.donor].pos,
+            atoms[bond.hydrogen].pos,
+            atoms[bond.acceptor].pos,
+        );
+        let angle = (donor - hydrogen)
+            .normalized()
+            .dot((acceptor - hydrogen).normalized())
+            .max(-1.0)
+            .min(1.0)
+            .acos()
+            .to_degrees();
+
+        println!(
+            "{}-{}···{}: {:.2} Å, {:.0}°",
+            label(bond.donor),
+            label(bond.hydrogen),
+            label(bond.acceptor),
+            (acceptor - donor).mag(),
+            angle
+        );
+    }
+    println!("{} hydrogen bonds", bonds.len());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use render::AtomKind;
+    use ultraviolet::Vec3;
+
+    fn atom(element: Element, x: f32, y: f32, z: f32) -> AtomRepr {
+        AtomRepr {
+            pos: Vec3::new(x, y, z),
+            kind: AtomKind::new(element),
+        }
+    }
+
+    /// The first water donates its hydrogen along the x axis to the second, whose hydrogens
+    /// point away.
+    fn water_dimer(separation: f32) -> Vec<AtomRepr> {
+        vec![
+            atom(Element::Oxygen, 0.0, 0.0, 0.0),
+            atom(Element::Hydrogen, 0.96, 0.0, 0.0),
+            atom(Element::Hydrogen, -0.24, 0.93, 0.0),
+            atom(Element::Oxygen, separation, 0.0, 0.0),
+            atom(Element::Hydrogen, separation + 0.3, -0.45, 0.78),
+            atom(Element::Hydrogen, separation + 0.3, -0.45, -0.78),
+        ]
+    }
+
+    #[test]
+    fn water_dimer_has_one_hydrogen_bond() {
+        let bonds = find_hydrogen_bonds(&water_dimer(2.9));
+        assert_eq!(
+            bonds,
+            [HydrogenBond {
+                donor: 0,
+                hydrogen: 1,
+                acceptor: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn distant_waters_have_none() {
+        assert!(find_hydrogen_bonds(&water_dimer(4.0)).is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+//! Principal axes of inertia, for orienting structures.
+
+use crate::eigen::symmetric_eigen;
+use render::AtomRepr;
+use ultraviolet::{Rotor3, Vec3};
+
+/// The center of mass, and the principal axes sorted from the smallest moment of inertia to
+/// the largest. The first axis is the one a structure is longest along.
+///
+/// The axes are a right-handed orthonormal basis. Returns `None` if there are no atoms.
+pub fn principal_axes<I>(atoms: I) -> Option<(Vec3, [Vec3; 3])>
+where
+    I: IntoIterator<Item = AtomRepr>,
+{
+    let atoms: Vec<_> = atoms
+        .into_iter()
+        .map(|atom| (atom.pos, atom.kind.element().atomic_mass()))
+        .collect();
+
+    let total_mass: f32 = atoms.iter().map(|&(_, mass)| mass).sum();
+    if atoms.is_empty() || total_mass <= 0.0 {
+        return None;
+    }
+    let center = atoms
+        .iter()
+        .fold(Vec3::zero(), |sum, &(pos, mass)| sum + pos * mass)
+        / total_mass;
+
+    let mut inertia = [[0.0f32; 3]; 3];
+    for &(pos, mass) in &atoms {
+        let r = pos - center;
+        let r = [r.x, r.y, r.z];
+        let r_sq = r[0] * r[0] + r[1] * r[1] + r[2] * r[2];
+        for i in 0..3 {
+            for j in 0..3 {
+                let diagonal = if i == j { r_sq } else { 0.0 };
+                inertia[i][j] += mass * (diagonal - r[i] * r[j]);
+            }
+        }
+    }
+
+    let (moments, vectors) = symmetric_eigen(inertia);
+    let mut order = [0, 1, 2];
+    order.sort_by(|&a, &b| {
+        moments[a]
+            .partial_cmp(&moments[b])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let axis = |index: usize| Vec3::new(vectors[0][index], vectors[1][index], vectors[2][index]);
+    let first = axis(order[0]).normalized();
+    let second = axis(order[1]).normalized();
+    Some((center, [first, second, first.cross(second)]))
+}
+
+/// The rotation that turns a structure with these principal axes so its longest axis points
+/// along `right` and its second longest along `up`.
+///
+/// Principal axes only have a direction up to their sign, so this picks whichever sign needs
+/// the smaller turn. `right` and `up` must be perpendicular unit vectors.
+pub fn align_axes(axes: [Vec3; 3], right: Vec3, up: Vec3) -> Rotor3 {
+    let [mut first, mut second, _] = axes;
+
+    if first.dot(right) < 0.0 {
+        first = -first;
+        second = -second;
+    }
+    let to_right = Rotor3::from_rotation_between(first, right);
+
+    // This is perpendicular to `right`, so the second turn is around it.
+    let mut second = to_right * second;
+    if second.dot(up) < 0.0 {
+        second = -second;
+    }
+    let to_up = Rotor3::from_rotation_between(second, up);
+
+    to_up * to_right
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use periodic_table::Element;
+    use render::AtomKind;
+
+    fn atom(element: Element, pos: Vec3) -> AtomRepr {
+        AtomRepr {
+            pos,
+            kind: AtomKind::new(element),
+        }
+    }
+
+    fn assert_orthonormal_right_handed(axes: [Vec3; 3]) {
+        for (i, axis) in axes.iter().enumerate() {
+            assert!((axis.mag() - 1.0).abs() < 1.0e-4);
+            for other in &axes[i + 1..] {
+                assert!(axis.dot(*other).abs() < 1.0e-4);
+            }
+        }
+        assert!(axes[0].cross(axes[1]).dot(axes[2]) > 0.999);
+    }
+
+    #[test]
+    fn linear_molecule_is_longest_along_its_chain() {
+        let direction = Vec3::new(1.0, 2.0, -0.5).normalized();
+        let offset = Vec3::new(3.0, -1.0, 2.0);
+        let atoms = vec![
+            atom(Element::Oxygen, offset - direction * 1.16),
+            atom(Element::Carbon, offset),
+            atom(Element::Oxygen, offset + direction * 1.16),
+        ];
+
+        let (center, axes) = principal_axes(atoms).unwrap();
+        assert!((center - offset).mag() < 1.0e-4);
+        assert!(axes[0].dot(direction).abs() > 0.9999);
+        assert_orthonormal_right_handed(axes);
+    }
+
+    #[test]
+    fn axes_are_a_right_handed_basis() {
+        let atoms = vec![
+            atom(Element::Oxygen, Vec3::new(0.0, 0.0, 0.1)),
+            atom(Element::Hydrogen, Vec3::new(0.76, 0.0, -0.5)),
+            atom(Element::Hydrogen, Vec3::new(-0.76, 0.2, -0.5)),
+            atom(Element::Carbon, Vec3::new(0.3, 1.4, 0.8)),
+        ];
+
+        let (_, axes) = principal_axes(atoms).unwrap();
+        assert_orthonormal_right_handed(axes);
+    }
+
+    #[test]
+    fn no_atoms_have_no_axes() {
+        assert!(principal_axes(Vec::new()).is_none());
+    }
+}
@@ -1,65 +1,170 @@
-use crate::camera::ArcballCamera;
+use crate::{
+    camera::{bounding_sphere, ArcballCamera, NavigationSettings},
+    coloring::Coloring,
+    minimize::{idealize_geometry, Minimizer, Status},
+    resize::PendingResize,
+};
 // use crate::rotating_camera::RotatingArcballCamera;
 use common::InputEvent;
-use render::{Interactions, RenderOptions, Renderer, World};
+use render::{
+    CameraRepr, ClipPlane, FrameStats, GlobalRenderResources, Interactions, Part, PartId,
+    RenderOptions, Renderer, SsaoOptions, World,
+};
+use std::path::{Path, PathBuf};
+use ultraviolet::Vec3;
 
 use winit::{
-    event::{Event, WindowEvent},
+    event::{ElementState, Event, KeyboardInput, ModifiersState, VirtualKeyCode, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     window::Window,
 };
 
 mod camera;
+mod cell;
+mod cif;
+#[cfg(not(target_arch = "wasm32"))]
+mod cli;
+#[cfg(not(target_arch = "wasm32"))]
+mod clipboard;
+mod coloring;
+mod convert;
+mod crash;
+mod duplicate;
+mod eigen;
+#[cfg(not(target_arch = "wasm32"))]
+mod element_styles;
+#[cfg(not(target_arch = "wasm32"))]
+mod gamepad;
+mod gltf;
+#[cfg(not(target_arch = "wasm32"))]
+mod headless;
+#[cfg(not(target_arch = "wasm32"))]
+mod hydrogen_bonds;
+mod inertia;
+mod minimize;
+mod mol;
+mod nanotube;
 // mod rotating_camera;
 mod pdb;
+mod placement;
+mod resize;
+mod sasa;
+mod smiles;
+mod stl;
+mod structure;
+#[cfg(not(target_arch = "wasm32"))]
+mod superpose;
+mod xyz;
 // mod ti;
 
-async fn run(event_loop: EventLoop<()>, window: Window) {
+async fn run(
+    event_loop: EventLoop<()>,
+    window: Window,
+    file: Option<PathBuf>,
+    nanotube: Option<(u32, u32, f32)>,
+    frame_stats: Option<PathBuf>,
+    gpu: Option<String>,
+    ssao: bool,
+    element_styles: Option<PathBuf>,
+    navigation: NavigationSettings,
+) {
     let (mut renderer, gpu_resources) = Renderer::new(
         &window,
         RenderOptions {
             fxaa: Some(()), // placeholder
             attempt_gpu_driven: false,
+            gpu,
+            ssao: if ssao {
+                Some(SsaoOptions::default())
+            } else {
+                None
+            },
         },
     )
-    .await;
+    .await
+    .expect("failed to initialize the renderer");
 
-    renderer.set_camera(ArcballCamera::new(100.0, 1.0));
+    crash::set_gpu(format!("{:?}", renderer.adapter_info()));
+
+    if frame_stats.is_some() {
+        renderer.stats_mut().start_recording();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut element_styles = element_styles.map(|path| {
+        let (styles, periodic_table) = element_styles::ElementStyles::load(path);
+        renderer.set_periodic_table(periodic_table);
+        styles
+    });
+    #[cfg(target_arch = "wasm32")]
+    let _ = element_styles;
 
     let mut world = World::new();
 
-    let mut neon_pump = pdb::load_from_pdb(&gpu_resources, "Neon Pump", "data/neon_pump_imm.pdb")
-        .expect("failed to load pdb");
+    let mut loaded = match (&file, nanotube) {
+        (Some(path), _) => convert::load(path).map(structure::Structure::into_world),
+        (None, Some((n, m, length))) => {
+            log::info!(
+                "building a ({}, {}) nanotube with a radius of {:.2} Å",
+                n,
+                m,
+                nanotube::radius(n, m)
+            );
+            nanotube::nanotube(n, m, length).map(structure::Structure::into_world)
+        }
+        (None, None) => load_default_structure(),
+    }
+    .expect("failed to load structure");
+    window.set_title(&window_title(file.as_deref(), false));
 
     println!(
         "Loaded {} parts and {} fragments",
-        neon_pump.parts().len(),
-        neon_pump.fragments().len()
+        loaded.parts().len(),
+        loaded.fragments().len()
     );
 
-    for part in neon_pump.parts_mut() {
+    for part in loaded.parts_mut() {
         // This doesn't let the world now that this part is going to be updated,
         // but we're adding them for the first time, so it'll work anyhow.
         part.move_to(0.0, 0.0, 0.0);
     }
 
-    world.merge(neon_pump);
+    world.merge(loaded);
+    // Loading isn't an unsaved change.
+    world.mark_saved();
+
+    let camera = ArcballCamera::new(100.0, 1.0).with_navigation(navigation);
+    renderer.set_camera(match bounding_sphere(&world) {
+        Some((center, radius)) => camera.with_bounds(center, radius),
+        None => camera,
+    });
 
     // let loaded_pdb = pdb::load_from_pdb_str(
-    //     &gpu_resources,
     //     "Neon Pump",
     //     include_str!("../data/neon_pump_imm.pdb"),
     // )
     // .unwrap();
 
     // let loaded_pdb = pdb::load_from_pdb(
-    //     &gpu_resources,
     //     "Carbon Nanotube and DNA",
     //     "data/nanotube_and_dna.pdb",
     // )
     // .expect("failed to load pdb");
 
     let interations = Interactions::default();
+    let mut placement = placement::AtomPlacement::new();
+    let mut duplication = duplicate::Duplication::new();
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut gamepads = gamepad::Gamepads::new();
+    let mut minimizer = None;
+    let mut selected_part = None;
+    let mut modifiers = ModifiersState::empty();
+    let mut coloring = Coloring::Element;
+    let mut colored_changes = world.change_count();
+    let mut bounded_changes = world.change_count();
+    let mut shown_dirty = false;
+
+    let mut resize = PendingResize::new(window.inner_size());
 
     event_loop.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Poll;
@@ -68,17 +173,131 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                 event: WindowEvent::Resized(new_size),
                 ..
             } => {
-                renderer.resize(new_size);
+                resize.push(new_size);
             }
             Event::MainEventsCleared => {
-                renderer.render(&mut world, &interations);
+                if let Some(size) = resize.take() {
+                    renderer.resize(size);
+                }
+
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Some(periodic_table) = element_styles.as_mut().and_then(|s| s.poll()) {
+                    renderer.set_periodic_table(periodic_table);
+                }
+
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Some(navigation) = gamepads.poll() {
+                    renderer.camera().update(InputEvent::Navigate(navigation));
+                }
+
+                run_minimizer(&mut minimizer, &mut world);
+
+                // Every coloring but by element depends on where the atoms are, and new atoms
+                // don't have scalars yet.
+                if coloring != Coloring::Element && world.change_count() != colored_changes {
+                    coloring.refresh(&mut renderer, &mut world, selected_part);
+                    colored_changes = world.change_count();
+                }
+
+                // Home and the standard views fit whatever is in the world now.
+                if world.change_count() != bounded_changes {
+                    renderer
+                        .camera()
+                        .update(InputEvent::Bounds(bounding_sphere(&world)));
+                    bounded_changes = world.change_count();
+                }
+
+                let dirty = world.is_dirty();
+                if dirty != shown_dirty {
+                    window.set_title(&window_title(file.as_deref(), dirty));
+                    shown_dirty = dirty;
+                }
+
+                if resize.has_area() {
+                    renderer.render(&mut world, &interations);
+                }
             }
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 ..
-            } => *control_flow = ControlFlow::Exit,
+            } => {
+                if !world.is_dirty() || confirm_close() {
+                    *control_flow = ControlFlow::Exit;
+                }
+            }
+            Event::LoopDestroyed => {
+                log::info!("{}", renderer.stats().summary());
+                if let Some(path) = &frame_stats {
+                    write_frame_stats(renderer.stats(), path);
+                }
+            }
             Event::WindowEvent { event, .. } => {
-                renderer.camera().update(InputEvent::Window(event));
+                if let WindowEvent::ModifiersChanged(new_modifiers) = event {
+                    modifiers = new_modifiers;
+                }
+                if let WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(key),
+                            ..
+                        },
+                    ..
+                } = event
+                {
+                    if modifiers.ctrl() {
+                        #[cfg(not(target_arch = "wasm32"))]
+                        update_clipboard(&mut selected_part, &mut world, key);
+                        #[cfg(not(target_arch = "wasm32"))]
+                        if key == VirtualKeyCode::S {
+                            save(&mut world, file.as_deref());
+                        }
+                        if key == VirtualKeyCode::D {
+                            match selected_part.filter(|&id| world.part(id).is_some()) {
+                                Some(id) => {
+                                    selected_part = Some(duplication.start(
+                                        &mut world,
+                                        &gpu_resources,
+                                        id,
+                                        resize.size(),
+                                        renderer.camera().repr(),
+                                    ));
+                                }
+                                None => log::info!("select a part with tab to duplicate it"),
+                            }
+                        }
+                    } else {
+                        update_clip_plane(&mut renderer, key);
+                        update_minimizer(&mut minimizer, &mut world, key);
+                        update_parts(
+                            &mut selected_part,
+                            &mut world,
+                            &gpu_resources,
+                            key,
+                            modifiers,
+                        );
+                        if key == VirtualKeyCode::O {
+                            orient_part(selected_part, &mut world, renderer.camera().repr());
+                        }
+                        if key == VirtualKeyCode::K {
+                            coloring = coloring.next();
+                            coloring.apply(&mut renderer, &mut world, selected_part);
+                            colored_changes = world.change_count();
+                        }
+                    }
+                }
+                let camera = renderer.camera().repr();
+                if !duplication.update(&event, resize.size(), camera, &mut world)
+                    && !placement.update(
+                        &event,
+                        resize.size(),
+                        camera,
+                        renderer.periodic_table(),
+                        &mut world,
+                    )
+                {
+                    renderer.camera().update(InputEvent::Window(event));
+                }
             }
             Event::DeviceEvent { event, .. } => {
                 renderer.camera().update(InputEvent::Device(event));
@@ -88,18 +307,365 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
     })
 }
 
+/// How far page up and page down move the clip plane, in angstroms.
+const CLIP_PLANE_STEP: f32 = 1.0;
+const SLAB_THICKNESS: f32 = 10.0;
+
+/// `C` cycles between no clipping, clipping everything in front of a plane through the
+/// center of the view, and keeping just a slab around that plane. Page up and page down move
+/// the plane away from and toward the camera.
+fn update_clip_plane(renderer: &mut Renderer, key: VirtualKeyCode) {
+    let clip_plane = renderer.clip_plane().copied();
+
+    let clip_plane = match (key, clip_plane) {
+        // Structures are centered on the origin when they're loaded.
+        (VirtualKeyCode::C, None) => renderer
+            .camera()
+            .repr()
+            .map(|camera| ClipPlane::facing(camera.forward(), Vec3::zero())),
+        (VirtualKeyCode::C, Some(clip_plane)) if clip_plane.slab.is_none() => Some(ClipPlane {
+            slab: Some(SLAB_THICKNESS),
+            ..clip_plane
+        }),
+        (VirtualKeyCode::C, Some(_)) => None,
+        (VirtualKeyCode::PageUp, Some(mut clip_plane)) => {
+            clip_plane.offset_by(CLIP_PLANE_STEP);
+            Some(clip_plane)
+        }
+        (VirtualKeyCode::PageDown, Some(mut clip_plane)) => {
+            clip_plane.offset_by(-CLIP_PLANE_STEP);
+            Some(clip_plane)
+        }
+        _ => return,
+    };
+
+    renderer.set_clip_plane(clip_plane);
+}
+
+/// How many iterations of minimization run before each frame.
+const MINIMIZER_ITERATIONS_PER_FRAME: usize = 20;
+const IDEALIZE_ITERATIONS: usize = 100;
+
+/// `M` starts minimizing the energy of every atom, and stops early, keeping the atoms where
+/// they are, if it's pressed again. Escape cancels and puts every atom back.
+///
+/// `I` idealizes bond lengths and angles all at once, which is much quicker.
+fn update_minimizer(minimizer: &mut Option<Minimizer>, world: &mut World, key: VirtualKeyCode) {
+    match (key, minimizer.take()) {
+        (VirtualKeyCode::M, None) => {
+            log::info!("minimizing");
+            *minimizer = Some(Minimizer::new(world.world_atoms()));
+        }
+        (VirtualKeyCode::I, None) => {
+            let mut atoms: Vec<_> = world.world_atoms().collect();
+            let error = idealize_geometry(&mut atoms, IDEALIZE_ITERATIONS);
+            let positions: Vec<_> = atoms.iter().map(|atom| atom.pos).collect();
+            world.set_world_positions(&positions);
+            log::info!(
+                "idealized the geometry, leaving an error of {:.3} Å²",
+                error
+            );
+        }
+        (VirtualKeyCode::M, Some(stopped)) => log::info!(
+            "stopped minimizing after {} iterations, at an energy of {:.3}",
+            stopped.iterations(),
+            stopped.energy()
+        ),
+        (VirtualKeyCode::Escape, Some(cancelled)) => {
+            world.set_world_positions(cancelled.initial_positions());
+            log::info!("cancelled minimizing");
+        }
+        (_, running) => *minimizer = running,
+    }
+}
+
+/// Runs some more of the minimization and shows where it's gotten to.
+fn run_minimizer(minimizer: &mut Option<Minimizer>, world: &mut World) {
+    let running = match minimizer {
+        Some(running) => running,
+        None => return,
+    };
+
+    // Atoms can be added or removed while it's running, and then the positions don't match up
+    // anymore.
+    if world.world_atoms().count() != running.positions().len() {
+        log::warn!("stopped minimizing, since atoms were added or removed");
+        *minimizer = None;
+        return;
+    }
+
+    let status = running.step(MINIMIZER_ITERATIONS_PER_FRAME);
+    world.set_world_positions(running.positions());
+
+    match status {
+        Status::Running => return,
+        Status::Converged => log::info!(
+            "minimized in {} iterations, to an energy of {:.3}",
+            running.iterations(),
+            running.energy()
+        ),
+        Status::OutOfIterations => log::warn!(
+            "gave up minimizing after {} iterations, at an energy of {:.3}",
+            running.iterations(),
+            running.energy()
+        ),
+    }
+    *minimizer = None;
+}
+
+/// How far a duplicated part is moved from the original, in angstroms.
+const DUPLICATE_OFFSET: f32 = 10.0;
+
+/// Tab selects the next part. `V` shows or hides the selected part, Insert duplicates it and
+/// Delete removes it.
+fn update_parts(
+    selected: &mut Option<PartId>,
+    world: &mut World,
+    gpu_resources: &GlobalRenderResources,
+    key: VirtualKeyCode,
+    modifiers: ModifiersState,
+) {
+    // The selected part might have been removed since.
+    let current = selected.filter(|&id| world.part(id).is_some());
+
+    match (key, current) {
+        (VirtualKeyCode::Tab, _) => {
+            let ids: Vec<_> = world.parts().map(|part| part.id()).collect();
+            let next = match current.and_then(|id| ids.iter().position(|&other| other == id)) {
+                Some(index) => ids.get(index + 1).or_else(|| ids.first()),
+                None => ids.first(),
+            };
+
+            *selected = next.copied();
+            if let Some(part) = selected.and_then(|id| world.part(id)) {
+                log::info!("selected `{}`", part.name());
+            }
+        }
+        (VirtualKeyCode::V, Some(id)) => {
+            let visible = !world.part(id).map_or(true, Part::is_visible);
+            world.set_part_visible(id, visible);
+            if let Some(part) = world.part(id) {
+                log::info!(
+                    "{} `{}`",
+                    if visible { "showing" } else { "hiding" },
+                    part.name()
+                );
+            }
+        }
+        // Hiding is only a view of the world, so hidden parts are still exported, and it
+        // doesn't count as an unsaved change.
+        (VirtualKeyCode::H, _) if modifiers.alt() => {
+            let ids: Vec<_> = world.parts().map(Part::id).collect();
+            for id in ids {
+                world.set_part_visible(id, true);
+            }
+            log::info!("showing every part");
+        }
+        (VirtualKeyCode::H, Some(id)) if modifiers.shift() => {
+            let ids: Vec<_> = world.parts().map(Part::id).collect();
+            for other in ids {
+                world.set_part_visible(other, other == id);
+            }
+            if let Some(part) = world.part(id) {
+                log::info!("isolating `{}`", part.name());
+            }
+        }
+        (VirtualKeyCode::H, Some(id)) => {
+            world.set_part_visible(id, false);
+            if let Some(part) = world.part(id) {
+                log::info!("hiding `{}`", part.name());
+            }
+        }
+        (VirtualKeyCode::H, None) => log::info!("select a part with tab to hide it"),
+        (VirtualKeyCode::Insert, Some(id)) => {
+            let copy = world.copy_part(gpu_resources, id);
+            let part = world.part_mut(copy);
+            part.offset_by(DUPLICATE_OFFSET, 0.0, 0.0);
+            log::info!("added `{}`", part.name());
+            *selected = Some(copy);
+        }
+        (VirtualKeyCode::Delete, Some(id)) => {
+            if let Some(part) = world.remove_part(id) {
+                log::info!("removed `{}`", part.name());
+            }
+            *selected = None;
+        }
+        _ => {}
+    }
+}
+
+/// Turns the selected part around its center of mass, so that its longest principal axis is
+/// horizontal on screen and its second longest is vertical.
+fn orient_part(selected: Option<PartId>, world: &mut World, camera: Option<CameraRepr>) {
+    let (id, camera) = match (selected, camera) {
+        (Some(id), Some(camera)) => (id, camera),
+        (None, _) => {
+            log::info!("select a part with tab to orient it");
+            return;
+        }
+        (_, None) => return,
+    };
+
+    let principal_axes = world
+        .part(id)
+        .and_then(|part| inertia::principal_axes(world.part_atoms(part)));
+    if let Some((center, axes)) = principal_axes {
+        let rotation = inertia::align_axes(axes, camera.right(), camera.up());
+        let part = world.part_mut(id);
+        part.rotate_about(rotation, center);
+        log::info!("oriented `{}` along its principal axes", part.name());
+    }
+}
+
+/// Ctrl-C copies the selected part to the clipboard as xyz text, and Ctrl-V pastes it back in
+/// as a new part, which is then selected.
+#[cfg(not(target_arch = "wasm32"))]
+fn update_clipboard(selected: &mut Option<PartId>, world: &mut World, key: VirtualKeyCode) {
+    match (key, *selected) {
+        (VirtualKeyCode::C, Some(id)) => match clipboard::copy(world, id) {
+            Ok(()) => log::info!("copied the selected part"),
+            Err(e) => log::warn!("{}", e),
+        },
+        (VirtualKeyCode::C, None) => log::info!("select a part with tab to copy it"),
+        (VirtualKeyCode::V, _) => match clipboard::paste(world) {
+            Ok(id) => {
+                if let Some(part) = world.part(id) {
+                    log::info!("pasted `{}`", part.name());
+                }
+                *selected = Some(id);
+            }
+            Err(e) => log::warn!("{}", e),
+        },
+        _ => {}
+    }
+}
+
+/// Ctrl-S writes every part back to the file that was opened, in the same format.
+#[cfg(not(target_arch = "wasm32"))]
+fn save(world: &mut World, file: Option<&Path>) {
+    let path = match file {
+        Some(path) => path,
+        None => {
+            log::info!("open a file to save the structure to it");
+            return;
+        }
+    };
+
+    let structure = structure::Structure::from_world(world);
+    let result = convert::Format::from_path(path)
+        .and_then(|format| convert::write(format, &structure, path, stl::DEFAULT_RESOLUTION));
+    match result {
+        Ok(()) => {
+            world.mark_saved();
+            log::info!("saved `{}`", path.display());
+        }
+        Err(e) => log::error!("failed to save `{}`: {}", path.display(), e),
+    }
+}
+
+/// The structure that's shown when no file is opened.
+///
+/// There's no file system on the web, so it's embedded in the binary there, and with the
+/// `embedded-assets` feature. Otherwise it's read from `data/` in the working directory.
+#[cfg(any(target_arch = "wasm32", feature = "embedded-assets"))]
+fn load_default_structure() -> Result<World, String> {
+    pdb::load_from_pdb_str("Neon Pump", include_str!("../data/neon_pump_imm.pdb"))
+}
+
+#[cfg(not(any(target_arch = "wasm32", feature = "embedded-assets")))]
+fn load_default_structure() -> Result<World, String> {
+    pdb::load_from_pdb("Neon Pump", "data/neon_pump_imm.pdb")
+}
+
+/// The structure that's loaded by default isn't a document, so it's untitled. A `*` in front
+/// means it has changed since it was loaded or saved.
+fn window_title(file: Option<&Path>, dirty: bool) -> String {
+    let name = file
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "Untitled".to_string());
+
+    format!("{}{} - atomCAD", if dirty { "*" } else { "" }, name)
+}
+
+/// Asks whether to close the window even though the changes will be lost.
+#[cfg(not(target_arch = "wasm32"))]
+fn confirm_close() -> bool {
+    // This can't have quotes in it, since they break some of the dialog backends.
+    let answer = tinyfiledialogs::message_box_yes_no(
+        "atomCAD",
+        "The structure has unsaved changes, which will be lost. Close anyway?",
+        tinyfiledialogs::MessageBoxIcon::Warning,
+        tinyfiledialogs::YesNo::No,
+    );
+    matches!(answer, tinyfiledialogs::YesNo::Yes)
+}
+
+/// Closing the tab doesn't send `CloseRequested` on the web, so there's nothing to ask.
+#[cfg(target_arch = "wasm32")]
+fn confirm_close() -> bool {
+    true
+}
+
+fn write_frame_stats(stats: &FrameStats, path: &Path) {
+    let result =
+        std::fs::File::create(path).and_then(|file| stats.write_csv(std::io::BufWriter::new(file)));
+
+    match result {
+        Ok(()) => log::info!("wrote frame stats to `{}`", path.display()),
+        Err(e) => log::error!("failed to write frame stats to `{}`: {}", path.display(), e),
+    }
+}
+
 fn main() {
+    crash::install();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let (file, nanotube, frame_stats, gpu, ssao, element_styles, navigation) = {
+        let args = cli::Args::parse();
+
+        if let Some(level) = &args.log_level {
+            std::env::set_var("RUST_LOG", level);
+        }
+        subscriber::initialize_default_subscriber(None);
+
+        match args.run_export() {
+            Some(Ok(())) => return,
+            Some(Err(e)) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            None => (
+                args.file,
+                args.nanotube,
+                args.frame_stats,
+                args.gpu,
+                !args.no_ssao,
+                args.element_styles,
+                args.navigation,
+            ),
+        }
+    };
+
     let event_loop = EventLoop::new();
     let window = Window::new(&event_loop).unwrap();
 
     #[cfg(not(target_arch = "wasm32"))]
     {
-        subscriber::initialize_default_subscriber(None);
-        futures::executor::block_on(run(event_loop, window));
+        futures::executor::block_on(run(
+            event_loop,
+            window,
+            file,
+            nanotube,
+            frame_stats,
+            gpu,
+            ssao,
+            element_styles,
+            navigation,
+        ));
     }
     #[cfg(target_arch = "wasm32")]
     {
-        std::panic::set_hook(Box::new(console_error_panic_hook::hook));
         console_log::init().expect("could not initialize logger");
         use winit::platform::web::WindowExtWebSys;
         // On wasm, append the canvas to the document body
@@ -111,6 +677,16 @@ fn main() {
                     .ok()
             })
             .expect("couldn't append canvas to document body");
-        wasm_bindgen_futures::spawn_local(run(event_loop, window));
+        wasm_bindgen_futures::spawn_local(run(
+            event_loop,
+            window,
+            None,
+            None,
+            None,
+            None,
+            true,
+            None,
+            NavigationSettings::default(),
+        ));
     }
 }
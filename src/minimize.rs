@@ -0,0 +1,601 @@
+//! Energy minimization with a very simple force field, for cleaning up hand-built geometry.
+//!
+//! Structures don't have bonds yet, so atoms closer than their covalent radii allow (with some
+//! slack) are treated as bonded. There are three terms:
+//!
+//! - Harmonic bond stretching towards the sum of the covalent radii.
+//! - A harmonic term on the cosine of every bond angle, towards an idealized angle for the
+//!   center atom's element and neighbor count.
+//! - The repulsive part of a Lennard-Jones potential between atoms that aren't bonded and don't
+//!   share a neighbor.
+//!
+//! The energy is roughly in kcal/mol, but none of the constants are fit to anything. Everything
+//! is a single bond, and missing hydrogens aren't inferred, so structures without hydrogens
+//! come out flatter than they should.
+//!
+//! The minimizer is FIRE (Bitzek et al. 2006), which runs a few iterations at a time so it can
+//! be interleaved with rendering.
+
+use periodic_table::{Element, PeriodicTable};
+use render::AtomRepr;
+use std::collections::{HashMap, HashSet};
+use ultraviolet::Vec3;
+
+/// How much longer than the sum of their covalent radii two atoms can be and still be bonded.
+const BOND_TOLERANCE: f32 = 1.2;
+const BOND_STIFFNESS: f32 = 300.0;
+const ANGLE_STIFFNESS: f32 = 60.0;
+const REPULSION_STRENGTH: f32 = 0.1;
+/// Atoms only repel each other once they're this much closer than their van der Waals radii.
+const REPULSION_SCALE: f32 = 0.75;
+
+/// Minimization stops once the largest force on any atom is below this, in kcal/mol/Å.
+const FORCE_TOLERANCE: f32 = 0.1;
+const MAX_ITERATIONS: usize = 5000;
+/// No atom moves further than this in one iteration, in angstroms.
+const MAX_STEP: f32 = 0.1;
+
+// FIRE's parameters. Every atom has the same mass, so time is in arbitrary units.
+const DT_START: f32 = 0.005;
+const DT_MAX: f32 = 0.03;
+const MIN_STEPS_BEFORE_SPEEDUP: usize = 5;
+const DT_GROWTH: f32 = 1.1;
+const DT_SHRINK: f32 = 0.5;
+const ALPHA_START: f32 = 0.1;
+const ALPHA_DECAY: f32 = 0.99;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Status {
+    Running,
+    Converged,
+    /// Gave up after `MAX_ITERATIONS`.
+    OutOfIterations,
+}
+
+pub struct Minimizer {
+    force_field: ForceField,
+    initial_positions: Vec<Vec3>,
+    positions: Vec<Vec3>,
+    velocities: Vec<Vec3>,
+    forces: Vec<Vec3>,
+    energy: f32,
+    iterations: usize,
+
+    dt: f32,
+    alpha: f32,
+    steps_since_uphill: usize,
+}
+
+impl Minimizer {
+    pub fn new<I>(atoms: I) -> Self
+    where
+        I: IntoIterator<Item = AtomRepr>,
+    {
+        let atoms: Vec<_> = atoms.into_iter().collect();
+        let positions: Vec<_> = atoms.iter().map(|atom| atom.pos).collect();
+        let force_field = ForceField::new(&atoms);
+
+        let mut forces = vec![Vec3::zero(); atoms.len()];
+        let energy = force_field.evaluate(&positions, &mut forces);
+
+        Self {
+            force_field,
+            initial_positions: positions.clone(),
+            velocities: vec![Vec3::zero(); atoms.len()],
+            positions,
+            forces,
+            energy,
+            iterations: 0,
+
+            dt: DT_START,
+            alpha: ALPHA_START,
+            steps_since_uphill: 0,
+        }
+    }
+
+    /// In the same order as the atoms that were passed to `new`.
+    pub fn positions(&self) -> &[Vec3] {
+        &self.positions
+    }
+
+    pub fn initial_positions(&self) -> &[Vec3] {
+        &self.initial_positions
+    }
+
+    pub fn energy(&self) -> f32 {
+        self.energy
+    }
+
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// Runs up to `iterations` more iterations.
+    pub fn step(&mut self, iterations: usize) -> Status {
+        for _ in 0..iterations {
+            let max_force_sq = self.forces.iter().map(Vec3::mag_sq).fold(0.0, f32::max);
+            if max_force_sq < FORCE_TOLERANCE * FORCE_TOLERANCE {
+                return Status::Converged;
+            }
+            if self.iterations >= MAX_ITERATIONS {
+                return Status::OutOfIterations;
+            }
+
+            self.iterate();
+        }
+
+        Status::Running
+    }
+
+    fn iterate(&mut self) {
+        let power: f32 = self
+            .forces
+            .iter()
+            .zip(&self.velocities)
+            .map(|(force, velocity)| force.dot(*velocity))
+            .sum();
+
+        if power > 0.0 {
+            // Steer the velocity towards the force.
+            let speed = self.velocities.iter().map(Vec3::mag_sq).sum::<f32>().sqrt();
+            let force = self.forces.iter().map(Vec3::mag_sq).sum::<f32>().sqrt();
+            for (velocity, &f) in self.velocities.iter_mut().zip(&self.forces) {
+                *velocity = *velocity * (1.0 - self.alpha) + f * (self.alpha * speed / force);
+            }
+
+            if self.steps_since_uphill > MIN_STEPS_BEFORE_SPEEDUP {
+                self.dt = (self.dt * DT_GROWTH).min(DT_MAX);
+                self.alpha *= ALPHA_DECAY;
+            }
+            self.steps_since_uphill += 1;
+        } else {
+            for velocity in &mut self.velocities {
+                *velocity = Vec3::zero();
+            }
+            self.dt *= DT_SHRINK;
+            self.alpha = ALPHA_START;
+            self.steps_since_uphill = 0;
+        }
+
+        for ((position, velocity), &force) in self
+            .positions
+            .iter_mut()
+            .zip(&mut self.velocities)
+            .zip(&self.forces)
+        {
+            *velocity += force * self.dt;
+
+            let step = *velocity * self.dt;
+            let length = step.mag();
+            *position += if length > MAX_STEP {
+                step * (MAX_STEP / length)
+            } else {
+                step
+            };
+        }
+
+        self.energy = self.force_field.evaluate(&self.positions, &mut self.forces);
+        self.iterations += 1;
+    }
+}
+
+/// Nudges atoms towards ideal bond lengths and angles, without the rest of the force field.
+///
+/// Every bond, and every angle as the distance between its two outer atoms, is a distance
+/// constraint, and each iteration moves atoms part of the way towards satisfying all of them.
+/// An iteration that makes the total error worse is undone and the next one takes smaller
+/// steps, so the error never goes up. Returns the final error, in square angstroms.
+pub fn idealize_geometry(atoms: &mut [AtomRepr], iterations: usize) -> f32 {
+    let force_field = ForceField::new(atoms);
+    let constraints: Vec<_> = force_field
+        .bonds
+        .iter()
+        .map(|bond| (bond.a, bond.b, bond.length, 1.0))
+        .chain(
+            force_field
+                .angles
+                .iter()
+                .map(|angle| (angle.a, angle.b, angle.span, 0.5)),
+        )
+        .collect();
+
+    let error = |atoms: &[AtomRepr]| -> f32 {
+        constraints
+            .iter()
+            .map(|&(a, b, length, _)| ((atoms[b].pos - atoms[a].pos).mag() - length).powi(2))
+            .sum()
+    };
+
+    let mut current_error = error(atoms);
+    let mut step = 1.0;
+    for _ in 0..iterations {
+        let previous: Vec<_> = atoms.iter().map(|atom| atom.pos).collect();
+
+        for &(a, b, length, weight) in &constraints {
+            let delta = atoms[b].pos - atoms[a].pos;
+            let distance = delta.mag().max(1.0e-6);
+            let correction = delta * ((distance - length) / distance * 0.5 * weight * step);
+            atoms[a].pos += correction;
+            atoms[b].pos -= correction;
+        }
+
+        let new_error = error(atoms);
+        if new_error <= current_error {
+            current_error = new_error;
+        } else {
+            for (atom, &pos) in atoms.iter_mut().zip(&previous) {
+                atom.pos = pos;
+            }
+            step *= 0.5;
+            if step < 1.0e-3 {
+                break;
+            }
+        }
+    }
+
+    current_error
+}
+
+struct Bond {
+    a: usize,
+    b: usize,
+    length: f32,
+}
+
+struct Angle {
+    a: usize,
+    center: usize,
+    b: usize,
+    cos: f32,
+    /// The distance between `a` and `b` when both bonds and the angle are ideal.
+    span: f32,
+}
+
+struct ForceField {
+    bonds: Vec<Bond>,
+    angles: Vec<Angle>,
+    /// Pairs that don't repel each other, with the smaller index first.
+    excluded: HashSet<(usize, usize)>,
+    /// Half of the distance inside which each atom starts repelling others.
+    repulsion_radii: Vec<f32>,
+    max_repulsion_radius: f32,
+}
+
+impl ForceField {
+    fn new(atoms: &[AtomRepr]) -> Self {
+        let elements: Vec<_> = atoms.iter().map(|atom| atom.kind.element()).collect();
+
+        let mut bonds = Vec::new();
+        let mut neighbors = vec![Vec::new(); atoms.len()];
+        for (a, b) in infer_bonds(atoms) {
+            let length = elements[a].covalent_radius() + elements[b].covalent_radius();
+            bonds.push(Bond { a, b, length });
+            neighbors[a].push(b);
+            neighbors[b].push(a);
+        }
+
+        let mut angles = Vec::new();
+        let mut excluded: HashSet<_> = bonds.iter().map(|bond| (bond.a, bond.b)).collect();
+        for (center, bonded) in neighbors.iter().enumerate() {
+            let cos = ideal_angle(elements[center], bonded.len())
+                .to_radians()
+                .cos();
+
+            for (i, &a) in bonded.iter().enumerate() {
+                for &b in &bonded[i + 1..] {
+                    let length_a =
+                        elements[a].covalent_radius() + elements[center].covalent_radius();
+                    let length_b =
+                        elements[b].covalent_radius() + elements[center].covalent_radius();
+                    let span = (length_a * length_a + length_b * length_b
+                        - 2.0 * length_a * length_b * cos)
+                        .sqrt();
+
+                    angles.push(Angle {
+                        a,
+                        center,
+                        b,
+                        cos,
+                        span,
+                    });
+                    excluded.insert((a.min(b), a.max(b)));
+                }
+            }
+        }
+
+        let periodic_table = PeriodicTable::new();
+        let repulsion_radii: Vec<_> = elements
+            .iter()
+            .map(|&element| periodic_table.element_repr(element).radius() * REPULSION_SCALE)
+            .collect();
+        let max_repulsion_radius = repulsion_radii.iter().copied().fold(0.0, f32::max);
+
+        Self {
+            bonds,
+            angles,
+            excluded,
+            repulsion_radii,
+            max_repulsion_radius,
+        }
+    }
+
+    /// Returns the energy and writes the force on each atom, which is the negative gradient.
+    fn evaluate(&self, positions: &[Vec3], forces: &mut [Vec3]) -> f32 {
+        for force in forces.iter_mut() {
+            *force = Vec3::zero();
+        }
+        let mut energy = 0.0;
+
+        for bond in &self.bonds {
+            let delta = positions[bond.b] - positions[bond.a];
+            let distance = delta.mag().max(1.0e-6);
+            let stretch = distance - bond.length;
+
+            energy += BOND_STIFFNESS * stretch * stretch;
+            let force = delta * (2.0 * BOND_STIFFNESS * stretch / distance);
+            forces[bond.a] += force;
+            forces[bond.b] -= force;
+        }
+
+        for angle in &self.angles {
+            let u = positions[angle.a] - positions[angle.center];
+            let v = positions[angle.b] - positions[angle.center];
+            let (u_length, v_length) = (u.mag().max(1.0e-6), v.mag().max(1.0e-6));
+            let cos = u.dot(v) / (u_length * v_length);
+            let difference = cos - angle.cos;
+
+            energy += ANGLE_STIFFNESS * difference * difference;
+            let slope = 2.0 * ANGLE_STIFFNESS * difference;
+            let gradient_a = v / (u_length * v_length) - u * (cos / (u_length * u_length));
+            let gradient_b = u / (u_length * v_length) - v * (cos / (v_length * v_length));
+
+            forces[angle.a] -= gradient_a * slope;
+            forces[angle.b] -= gradient_b * slope;
+            forces[angle.center] += (gradient_a + gradient_b) * slope;
+        }
+
+        for (a, b) in pairs_within(positions, 2.0 * self.max_repulsion_radius) {
+            if self.excluded.contains(&(a, b)) {
+                continue;
+            }
+
+            let contact = self.repulsion_radii[a] + self.repulsion_radii[b];
+            let delta = positions[b] - positions[a];
+            let distance = delta.mag().max(1.0e-3);
+            if distance >= contact {
+                continue;
+            }
+
+            // Shifted so the energy is zero at contact, and cut off there.
+            let s6 = (contact / distance).powi(6);
+            energy += REPULSION_STRENGTH * (s6 * s6 - 2.0 * s6 + 1.0);
+            let slope = 12.0 * REPULSION_STRENGTH * (s6 - s6 * s6) / distance;
+            let force = delta * (slope / distance);
+            forces[a] += force;
+            forces[b] -= force;
+        }
+
+        energy
+    }
+}
+
+/// Idealized bond angles in degrees. Carbon and boron with three neighbors are trigonal, and
+/// carbon with two is linear.
+fn ideal_angle(element: Element, neighbors: usize) -> f32 {
+    match (element, neighbors) {
+        (Element::Carbon, 2) => 180.0,
+        (Element::Carbon, 3) | (Element::Boron, 3) | (Element::Nitrogen, 2) => 120.0,
+        (Element::Nitrogen, 3) => 107.0,
+        (Element::Oxygen, 2) => 104.5,
+        _ => 109.47,
+    }
+}
+
+/// Every pair of atoms that are close enough to be bonded, with the smaller index first.
+pub fn infer_bonds(atoms: &[AtomRepr]) -> Vec<(usize, usize)> {
+    let positions: Vec<_> = atoms.iter().map(|atom| atom.pos).collect();
+    let max_covalent_radius = atoms
+        .iter()
+        .map(|atom| atom.kind.element().covalent_radius())
+        .fold(0.0, f32::max);
+
+    pairs_within(&positions, 2.0 * max_covalent_radius * BOND_TOLERANCE)
+        .into_iter()
+        .filter(|&(a, b)| {
+            let length = atoms[a].kind.element().covalent_radius()
+                + atoms[b].kind.element().covalent_radius();
+            (positions[b] - positions[a]).mag() <= length * BOND_TOLERANCE
+        })
+        .collect()
+}
+
+/// Every pair of atoms closer than `cutoff`, with the smaller index first. Atoms are bucketed
+/// into cubes the size of the cutoff, so only neighboring cubes have to be compared.
+pub fn pairs_within(positions: &[Vec3], cutoff: f32) -> Vec<(usize, usize)> {
+    let cutoff = cutoff.max(1.0e-3);
+    let cell = |pos: Vec3| {
+        (
+            (pos.x / cutoff).floor() as i32,
+            (pos.y / cutoff).floor() as i32,
+            (pos.z / cutoff).floor() as i32,
+        )
+    };
+
+    let mut cells: HashMap<_, Vec<usize>> = HashMap::new();
+    for (index, &pos) in positions.iter().enumerate() {
+        cells.entry(cell(pos)).or_default().push(index);
+    }
+
+    let mut pairs = Vec::new();
+    for (a, &pos) in positions.iter().enumerate() {
+        let (x, y, z) = cell(pos);
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let others = match cells.get(&(x + dx, y + dy, z + dz)) {
+                        Some(others) => others,
+                        None => continue,
+                    };
+
+                    for &b in others {
+                        if b > a && (positions[b] - pos).mag_sq() < cutoff * cutoff {
+                            pairs.push((a, b));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use render::AtomKind;
+
+    fn atom(element: Element, x: f32, y: f32, z: f32) -> AtomRepr {
+        AtomRepr {
+            pos: Vec3::new(x, y, z),
+            kind: AtomKind::new(element),
+        }
+    }
+
+    /// Ethane with a stretched C-C bond and uneven C-H bonds and angles, all still short
+    /// enough to be inferred as bonds.
+    fn distorted_ethane() -> Vec<AtomRepr> {
+        let mut atoms = vec![
+            atom(Element::Carbon, 0.0, 0.0, 0.0),
+            atom(Element::Carbon, 1.7, 0.0, 0.0),
+        ];
+        let directions = [
+            (Vec3::new(-0.3, 0.95, 0.0), 1.15),
+            (Vec3::new(-0.5, -0.4, 0.8), 1.0),
+            (Vec3::new(-0.2, -0.5, -0.85), 1.2),
+        ];
+        for &(carbon, sign) in &[(0, 1.0), (1, -1.0)] {
+            for &(direction, length) in &directions {
+                let direction = Vec3::new(direction.x * sign, direction.y, direction.z * sign);
+                let pos = atoms[carbon].pos + direction.normalized() * length;
+                atoms.push(atom(Element::Hydrogen, pos.x, pos.y, pos.z));
+            }
+        }
+        atoms
+    }
+
+    #[test]
+    fn ethane_bonds_are_inferred() {
+        let atoms = distorted_ethane();
+        assert_eq!(infer_bonds(&atoms).len(), 7);
+    }
+
+    #[test]
+    fn minimizing_lowers_the_energy() {
+        let mut minimizer = Minimizer::new(distorted_ethane());
+        let initial = minimizer.energy();
+
+        assert_eq!(minimizer.step(MAX_ITERATIONS), Status::Converged);
+        assert!(minimizer.iterations() < MAX_ITERATIONS);
+        assert!(
+            minimizer.energy() < initial * 0.01,
+            "{}",
+            minimizer.energy()
+        );
+
+        let positions = minimizer.positions();
+        assert!(((positions[1] - positions[0]).mag() - 1.52).abs() < 0.02);
+    }
+
+    #[test]
+    fn forces_match_the_energy() {
+        let atoms = distorted_ethane();
+        let force_field = ForceField::new(&atoms);
+        let mut positions: Vec<_> = atoms.iter().map(|atom| atom.pos).collect();
+
+        let mut forces = vec![Vec3::zero(); positions.len()];
+        force_field.evaluate(&positions, &mut forces);
+
+        let mut scratch = forces.clone();
+        let h = 1.0e-3;
+        for index in 0..positions.len() {
+            for axis in 0..3 {
+                let original = positions[index];
+                let mut offset = Vec3::zero();
+                match axis {
+                    0 => offset.x = h,
+                    1 => offset.y = h,
+                    _ => offset.z = h,
+                }
+
+                positions[index] = original + offset;
+                let plus = force_field.evaluate(&positions, &mut scratch);
+                positions[index] = original - offset;
+                let minus = force_field.evaluate(&positions, &mut scratch);
+                positions[index] = original;
+
+                let numeric = -(plus - minus) / (2.0 * h);
+                let analytic = [forces[index].x, forces[index].y, forces[index].z][axis];
+                assert!(
+                    (numeric - analytic).abs() < 0.02 * analytic.abs().max(10.0),
+                    "atom {} axis {}: {} != {}",
+                    index,
+                    axis,
+                    numeric,
+                    analytic
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn energy_keeps_going_down() {
+        let mut minimizer = Minimizer::new(distorted_ethane());
+        let mut previous = minimizer.energy();
+
+        // FIRE can go uphill for an iteration before it notices, but not over a whole batch.
+        while minimizer.step(20) == Status::Running {
+            assert!(
+                minimizer.energy() <= previous,
+                "{} > {} after {} iterations",
+                minimizer.energy(),
+                previous,
+                minimizer.iterations()
+            );
+            previous = minimizer.energy();
+        }
+    }
+
+    #[test]
+    fn idealizing_relaxes_a_stretched_bond() {
+        let stretched = vec![
+            atom(Element::Carbon, 0.0, 0.0, 0.0),
+            atom(Element::Carbon, 1.7, 0.0, 0.0),
+        ];
+
+        let mut previous = f32::INFINITY;
+        for &iterations in &[0, 1, 2, 5, 20] {
+            let mut atoms = stretched.clone();
+            let error = idealize_geometry(&mut atoms, iterations);
+            assert!(error <= previous, "{} > {}", error, previous);
+            previous = error;
+        }
+        assert!(previous < 1.0e-6);
+
+        let mut atoms = stretched;
+        idealize_geometry(&mut atoms, 20);
+        let length = (atoms[1].pos - atoms[0].pos).mag();
+        assert!((length - 1.52).abs() < 1.0e-3, "{}", length);
+    }
+
+    #[test]
+    fn idealizing_ethane_never_makes_it_worse() {
+        let mut previous = f32::INFINITY;
+        for iterations in 0..30 {
+            let mut atoms = distorted_ethane();
+            let error = idealize_geometry(&mut atoms, iterations);
+            assert!(error <= previous, "{} > {}", error, previous);
+            previous = error;
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! MDL molfiles (V2000).
+//!
+//! We don't have bonds yet, so the bond block is skipped when reading and left empty when writing.
+
+use crate::structure::{Structure, StructurePart};
+use periodic_table::Element;
+use render::{AtomKind, AtomRepr};
+use std::{io, ops::Range};
+use ultraviolet::Vec3;
+
+/// The V2000 counts line only has room for three digits.
+const MAX_ATOMS: usize = 999;
+
+pub fn read_mol(name: &str, contents: &str) -> Result<Structure, String> {
+    // The first three lines are the header block.
+    let mut lines = contents.lines().skip(3);
+
+    let counts = lines
+        .next()
+        .ok_or_else(|| "mol: missing counts line".to_string())?;
+    if counts.contains("V3000") {
+        return Err("mol: V3000 molfiles are not supported".to_string());
+    }
+    let count: usize = counts
+        .get(0..3)
+        .and_then(|field| field.trim().parse().ok())
+        .ok_or_else(|| "mol: invalid atom count".to_string())?;
+
+    let atoms = lines
+        .take(count)
+        .enumerate()
+        .map(|(index, line)| {
+            parse_atom(line).ok_or_else(|| format!("mol: invalid atom on line {}", index + 5))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if atoms.len() != count {
+        return Err(format!(
+            "mol: expected {} atoms, but found {}",
+            count,
+            atoms.len()
+        ));
+    }
+
+    Ok(Structure {
+        parts: vec![StructurePart {
+            name: name.to_string(),
+            fragments: vec![atoms],
+        }],
+        cell: None,
+    })
+}
+
+pub fn write_mol<W: io::Write>(structure: &Structure, name: &str, mut writer: W) -> io::Result<()> {
+    let count = structure.atoms().count();
+    if count > MAX_ATOMS {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "mol: V2000 molfiles can hold at most {} atoms, but there are {}",
+                MAX_ATOMS, count
+            ),
+        ));
+    }
+
+    writeln!(writer, "{}", name)?;
+    writeln!(writer, "  atomCAD")?;
+    writeln!(writer)?;
+    writeln!(
+        writer,
+        "{:>3}{:>3}  0  0  0  0  0  0  0  0999 V2000",
+        count, 0
+    )?;
+
+    for atom in structure.atoms() {
+        writeln!(
+            writer,
+            "{:>10.4}{:>10.4}{:>10.4} {:<3} 0  0  0  0  0  0  0  0  0  0  0  0",
+            atom.pos.x,
+            atom.pos.y,
+            atom.pos.z,
+            atom.kind.element().symbol()
+        )?;
+    }
+
+    writeln!(writer, "M  END")
+}
+
+fn parse_atom(line: &str) -> Option<AtomRepr> {
+    let coordinate = |range: Range<usize>| line.get(range)?.trim().parse::<f32>().ok();
+    let pos = Vec3::new(coordinate(0..10)?, coordinate(10..20)?, coordinate(20..30)?);
+    let element = Element::from_symbol(line.get(31..34)?.trim())?;
+
+    Some(AtomRepr {
+        pos,
+        kind: AtomKind::new(element),
+    })
+}
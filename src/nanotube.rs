@@ -0,0 +1,162 @@
+//! Carbon nanotubes, made by rolling up a graphene sheet.
+//!
+//! A tube is named by its chiral indices `(n, m)`: the circumference is the graphene lattice
+//! vector `n * a1 + m * a2`, so rolling the sheet up joins the atoms at either end of it.
+//! `(n, n)` tubes are armchair, `(n, 0)` tubes are zigzag, and the rest are chiral.
+//!
+//! The tube is cut straight across at both ends, and the carbons there are left with dangling
+//! bonds, for whatever is going to passivate or cap them.
+
+use crate::structure::{Structure, StructurePart};
+use periodic_table::Element;
+use render::{AtomKind, AtomRepr};
+use std::f32::consts::PI;
+use ultraviolet::{Vec2, Vec3};
+
+/// The C-C bond length in graphene, in angstroms.
+const BOND_LENGTH: f32 = 1.42;
+/// Keeps atoms that are exactly on the edge of the sheet from being cut off by rounding.
+const EPSILON: f32 = 1.0e-3;
+
+/// The circumference of the tube is the length of the chiral vector.
+pub fn radius(n: u32, m: u32) -> f32 {
+    let (n, m) = (n as f32, m as f32);
+    lattice_constant() * (n * n + n * m + m * m).sqrt() / (2.0 * PI)
+}
+
+/// The distance between neighboring lattice points of graphene.
+fn lattice_constant() -> f32 {
+    BOND_LENGTH * 3f32.sqrt()
+}
+
+/// A tube along the z axis, centered on it, that starts at z = 0 and is `length` angstroms
+/// long.
+pub fn nanotube(n: u32, m: u32, length: f32) -> Result<Structure, String> {
+    if n == 0 && m == 0 {
+        return Err("a nanotube needs at least one nonzero chiral index".to_string());
+    }
+    if !(length > 0.0) {
+        return Err(format!("invalid nanotube length `{}`", length));
+    }
+
+    let a = lattice_constant();
+    let a1 = Vec2::new(3f32.sqrt() / 2.0, 0.5) * a;
+    let a2 = Vec2::new(3f32.sqrt() / 2.0, -0.5) * a;
+    // The second atom of graphene's two-atom basis.
+    let basis = [Vec2::zero(), (a1 + a2) / 3.0];
+
+    let chiral = a1 * n as f32 + a2 * m as f32;
+    let circumference = chiral.mag();
+    let around = chiral / circumference;
+    let along = Vec2::new(-around.y, around.x);
+    let radius = circumference / (2.0 * PI);
+
+    // The lattice points that can land on the sheet are inside the parallelogram that its
+    // corners span, in lattice coordinates.
+    let det = a1.x * a2.y - a2.x * a1.y;
+    let to_lattice = |p: Vec2| {
+        (
+            (p.x * a2.y - a2.x * p.y) / det,
+            (a1.x * p.y - p.x * a1.y) / det,
+        )
+    };
+    let corners = [
+        Vec2::zero(),
+        chiral,
+        along * length,
+        chiral + along * length,
+    ];
+    let (mut min, mut max) = ((f32::MAX, f32::MAX), (f32::MIN, f32::MIN));
+    for &corner in &corners {
+        let (i, j) = to_lattice(corner);
+        min = (min.0.min(i), min.1.min(j));
+        max = (max.0.max(i), max.1.max(j));
+    }
+
+    let mut atoms = Vec::new();
+    for i in (min.0.floor() as i32 - 1)..=(max.0.ceil() as i32 + 1) {
+        for j in (min.1.floor() as i32 - 1)..=(max.1.ceil() as i32 + 1) {
+            for &offset in &basis {
+                let p = a1 * i as f32 + a2 * j as f32 + offset;
+                // Atoms a whole circumference apart are the same atom once the sheet is rolled
+                // up, so only one of them is kept.
+                let (u, v) = (p.dot(around) + EPSILON, p.dot(along) + EPSILON);
+                if u < 0.0 || u >= circumference || v < 0.0 || v >= length {
+                    continue;
+                }
+
+                let angle = 2.0 * PI * (u - EPSILON) / circumference;
+                atoms.push(AtomRepr {
+                    pos: Vec3::new(radius * angle.cos(), radius * angle.sin(), v - EPSILON),
+                    kind: AtomKind::new(Element::Carbon),
+                });
+            }
+        }
+    }
+
+    Ok(Structure {
+        parts: vec![StructurePart {
+            name: format!("({}, {}) nanotube", n, m),
+            fragments: vec![atoms],
+        }],
+        cell: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::minimize::infer_bonds;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn armchair_rings() {
+        let tube = nanotube(5, 5, 20.0).unwrap();
+        let atoms: Vec<_> = tube.atoms().copied().collect();
+
+        let expected_radius = radius(5, 5);
+        assert!(atoms
+            .iter()
+            .all(
+                |atom| (Vec2::new(atom.pos.x, atom.pos.y).mag() - expected_radius).abs() < 1.0e-3
+            ));
+
+        // Every ring around the tube is n armchair pairs, so 2n atoms.
+        let mut rings = BTreeMap::new();
+        for atom in &atoms {
+            *rings
+                .entry((atom.pos.z * 100.0).round() as i32)
+                .or_insert(0) += 1;
+        }
+        assert!(rings.len() > 2);
+        assert!(rings.values().all(|&count| count == 10), "{:?}", rings);
+    }
+
+    #[test]
+    fn interior_carbons_have_three_neighbors() {
+        let length = 20.0;
+        let atoms: Vec<_> = nanotube(5, 5, length).unwrap().atoms().copied().collect();
+
+        let mut neighbors = vec![0; atoms.len()];
+        for (a, b) in infer_bonds(&atoms) {
+            neighbors[a] += 1;
+            neighbors[b] += 1;
+        }
+
+        let mut interior = 0;
+        for (atom, &count) in atoms.iter().zip(&neighbors) {
+            if atom.pos.z > BOND_LENGTH && atom.pos.z < length - 2.0 * BOND_LENGTH {
+                assert_eq!(count, 3, "the atom at {:?}", atom.pos);
+                interior += 1;
+            }
+        }
+        assert!(interior > 0);
+    }
+
+    #[test]
+    fn invalid_tubes() {
+        assert!(nanotube(0, 0, 10.0).is_err());
+        assert!(nanotube(5, 5, 0.0).is_err());
+        assert!(nanotube(5, 5, f32::NAN).is_err());
+    }
+}
@@ -1,103 +1,176 @@
+use crate::{
+    cell::UnitCell,
+    structure::{Structure, StructurePart},
+};
 use lib3dmol::{
-    parser::{read_pdb, read_pdb_txt},
-    structures::{atom::AtomType, GetAtom as _},
+    parser::{read_pdb as parse_pdb, read_pdb_txt},
+    structures::{atom::AtomType, structure::Structure as PdbStructure, GetAtom as _},
 };
 use periodic_table::Element;
-use render::{AtomKind, AtomRepr, Fragment, GlobalRenderResources, Part, World};
-use std::path::Path;
+use render::{AtomKind, AtomRepr, World};
+use std::{fs, io, path::Path};
+use ultraviolet::Vec3;
 
 // TODO: Better result error type.
-pub fn load_from_pdb<P: AsRef<Path>>(
-    gpu_resources: &GlobalRenderResources,
-    name: &str,
-    path: P,
-) -> Result<World, String> {
-    let path = path.as_ref();
-    if !path.exists() {
-        return Err("path does not exist".to_string());
-    }
+pub fn load_from_pdb<P: AsRef<Path>>(name: &str, path: P) -> Result<World, String> {
+    let world = read_pdb(name, path)?.into_world();
 
-    let structure = read_pdb(&*path.to_string_lossy(), name);
+    log::info!("loaded {} parts", world.parts().count());
 
-    let mut world = World::new();
+    Ok(world)
+}
 
-    let mut counter = 0;
+pub fn load_from_pdb_str(name: &str, contents: &str) -> Result<World, String> {
+    let world = read_pdb_str(name, contents)?.into_world();
 
-    structure
-        .chains
-        .into_iter()
-        .map(|chain| {
-            let fragments: Vec<_> = chain
-                .lst_res
-                .iter()
-                .map(|residue| {
-                    let atoms = residue.get_atom();
-                    let atoms = atoms.iter().map(|atom| {
-                        let element = atom_type_to_element(&atom.a_type);
+    log::info!("loaded {} parts", world.parts().count());
 
-                        AtomRepr {
-                            pos: atom.coord.into(),
-                            kind: AtomKind::new(element),
-                        }
-                    });
+    Ok(world)
+}
 
-                    Fragment::from_atoms(gpu_resources, atoms)
-                })
-                .collect();
+pub fn read_pdb<P: AsRef<Path>>(name: &str, path: P) -> Result<Structure, String> {
+    let path = path.as_ref();
+    if !path.exists() {
+        return Err("path does not exist".to_string());
+    }
 
-            let part = Part::from_fragments(&mut world, format!("{}{}", name, counter), fragments);
-            counter += 1;
-            world.spawn_part(part);
-        })
-        .for_each(|_| {});
+    let mut structure = convert_structure(name, parse_pdb(&*path.to_string_lossy(), name));
+    // lib3dmol doesn't read the unit cell.
+    if let Ok(contents) = fs::read_to_string(path) {
+        structure.cell = read_cryst1(&contents);
+    }
 
-    log::info!("loaded {} parts", world.parts().count());
+    Ok(structure)
+}
 
-    Ok(world)
+pub fn read_pdb_str(name: &str, contents: &str) -> Result<Structure, String> {
+    let mut structure = convert_structure(name, read_pdb_txt(contents, name));
+    structure.cell = read_cryst1(contents);
+    Ok(structure)
 }
 
-pub fn load_from_pdb_str(
-    gpu_resources: &GlobalRenderResources,
-    name: &str,
-    contents: &str,
-) -> Result<World, String> {
-    let structure = read_pdb_txt(contents, name);
+/// Files without a crystal put a 1 angstrom cube here, so that's the same as no cell.
+fn read_cryst1(contents: &str) -> Option<UnitCell> {
+    let line = contents.lines().find(|line| line.starts_with("CRYST1"))?;
+    let field = |start: usize, end: usize| {
+        line.get(start..end.min(line.len()))
+            .and_then(|field| field.trim().parse::<f32>().ok())
+    };
+
+    let cell = UnitCell::new(
+        field(6, 15)?,
+        field(15, 24)?,
+        field(24, 33)?,
+        field(33, 40)?,
+        field(40, 47)?,
+        field(47, 54)?,
+    )?;
+
+    if cell.a == 1.0 && cell.b == 1.0 && cell.c == 1.0 {
+        None
+    } else {
+        Some(cell)
+    }
+}
 
-    let mut world = World::new();
+/// Writes every atom as a `HETATM` record. Parts become chains and fragments become residues.
+pub fn write_pdb<W: io::Write>(structure: &Structure, mut writer: W) -> io::Result<()> {
+    if let Some(cell) = &structure.cell {
+        writeln!(
+            writer,
+            "CRYST1{:>9.3}{:>9.3}{:>9.3}{:>7.2}{:>7.2}{:>7.2} P 1           1",
+            cell.a, cell.b, cell.c, cell.alpha, cell.beta, cell.gamma,
+        )?;
+
+        // The rows of the matrix that turns cartesian coordinates into fractional ones.
+        let column = |axis: Vec3| {
+            let column = cell.to_fractional(axis);
+            [column.x, column.y, column.z]
+        };
+        let columns = [
+            column(Vec3::unit_x()),
+            column(Vec3::unit_y()),
+            column(Vec3::unit_z()),
+        ];
+        for row in 0..3 {
+            writeln!(
+                writer,
+                "SCALE{}    {:>10.6}{:>10.6}{:>10.6}     {:>10.5}",
+                row + 1,
+                columns[0][row],
+                columns[1][row],
+                columns[2][row],
+                0.0,
+            )?;
+        }
+    }
 
-    let mut counter = 0;
+    let mut serial = 1;
+
+    for (part_index, part) in structure.parts.iter().enumerate() {
+        let chain = (b'A' + (part_index % 26) as u8) as char;
+
+        for (fragment_index, atoms) in part.fragments.iter().enumerate() {
+            let residue = (fragment_index + 1) % 10_000;
+
+            for atom in atoms {
+                let symbol = atom.kind.element().symbol();
+                // Single-letter element names start in the second column of the name field.
+                let atom_name = if symbol.len() == 1 {
+                    format!(" {:<3}", symbol)
+                } else {
+                    format!("{:<4}", symbol)
+                };
+
+                writeln!(
+                    writer,
+                    "HETATM{:>5} {} UNK {}{:>4}    {:>8.3}{:>8.3}{:>8.3}{:>6.2}{:>6.2}          {:>2}",
+                    serial % 100_000,
+                    atom_name,
+                    chain,
+                    residue,
+                    atom.pos.x,
+                    atom.pos.y,
+                    atom.pos.z,
+                    1.0,
+                    0.0,
+                    symbol.to_ascii_uppercase(),
+                )?;
+                serial += 1;
+            }
+        }
+
+        writeln!(writer, "TER")?;
+    }
+
+    writeln!(writer, "END")
+}
 
-    structure
+fn convert_structure(name: &str, structure: PdbStructure) -> Structure {
+    let parts = structure
         .chains
         .into_iter()
-        .map(|chain| {
-            let fragments: Vec<_> = chain
+        .enumerate()
+        .map(|(counter, chain)| StructurePart {
+            name: format!("{}{}", name, counter),
+            fragments: chain
                 .lst_res
                 .iter()
                 .map(|residue| {
-                    let atoms = residue.get_atom();
-                    let atoms = atoms.iter().map(|atom| {
-                        let element = atom_type_to_element(&atom.a_type);
-
-                        AtomRepr {
+                    residue
+                        .get_atom()
+                        .iter()
+                        .map(|atom| AtomRepr {
                             pos: atom.coord.into(),
-                            kind: AtomKind::new(element),
-                        }
-                    });
-
-                    Fragment::from_atoms(gpu_resources, atoms)
+                            kind: AtomKind::new(atom_type_to_element(&atom.a_type)),
+                        })
+                        .collect()
                 })
-                .collect();
-
-            let part = Part::from_fragments(&mut world, format!("{}{}", name, counter), fragments);
-            counter += 1;
-            world.spawn_part(part);
+                .collect(),
         })
-        .for_each(|_| {});
-
-    log::info!("loaded {} parts", world.parts().count());
+        .collect();
 
-    Ok(world)
+    Structure { parts, cell: None }
 }
 
 fn atom_type_to_element(atom_type: &AtomType) -> Element {
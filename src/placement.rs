@@ -0,0 +1,385 @@
+//! Adding atoms by Shift-clicking. Plain clicks still orbit the camera.
+//!
+//! New atoms go on the construction plane, which faces the camera and goes through the origin,
+//! since that's where structures are centered when they're loaded. Clicking on an existing atom
+//! puts the new one a bond length away from it instead, so they don't overlap. There are no
+//! bonds yet, so nothing connects the two.
+//!
+//! With snapping (toggled with `G`), the bond length is the sum of the two covalent radii, and
+//! the direction fits the geometry of whatever is already bonded to the clicked atom. Atoms
+//! within bonding distance count as bonded. Without snapping, the new atom is just put in
+//! front of the clicked one.
+//!
+//! Alt-clicking an atom changes its element instead, cycling through `ELEMENT_CYCLE`.
+
+use periodic_table::{Element, PeriodicTable};
+use render::{AtomKind, AtomRepr, CameraRepr, Fragment, Part, World};
+use ultraviolet::{Vec3, Vec4};
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{
+        ElementState, KeyboardInput, ModifiersState, MouseButton, VirtualKeyCode, WindowEvent,
+    },
+};
+
+/// There's no way to pick an element yet.
+const NEW_ELEMENT: Element = Element::Carbon;
+/// A carbon-carbon single bond, in angstroms.
+const BOND_LENGTH: f32 = 1.54;
+/// How much longer than the sum of their covalent radii two atoms can be and still be bonded.
+const BOND_TOLERANCE: f32 = 1.2;
+const TETRAHEDRAL_ANGLE: f32 = 109.47;
+/// The elements that alt-clicking goes through. Anything else goes back to the start.
+const ELEMENT_CYCLE: [Element; 4] = [
+    Element::Carbon,
+    Element::Nitrogen,
+    Element::Oxygen,
+    Element::Hydrogen,
+];
+
+pub struct AtomPlacement {
+    cursor: PhysicalPosition<f64>,
+    modifiers: ModifiersState,
+    snap: bool,
+}
+
+impl AtomPlacement {
+    pub fn new() -> Self {
+        Self {
+            cursor: PhysicalPosition::new(0.0, 0.0),
+            modifiers: ModifiersState::empty(),
+            snap: true,
+        }
+    }
+
+    /// Returns true if the event added or changed an atom, in which case nothing else should
+    /// handle it.
+    pub fn update(
+        &mut self,
+        event: &WindowEvent,
+        size: PhysicalSize<u32>,
+        camera: Option<CameraRepr>,
+        periodic_table: &PeriodicTable,
+        world: &mut World,
+    ) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => self.cursor = *position,
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = *modifiers,
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::G),
+                        ..
+                    },
+                ..
+            } => {
+                self.snap = !self.snap;
+                log::info!(
+                    "{} new atoms to bond geometry",
+                    if self.snap {
+                        "snapping"
+                    } else {
+                        "not snapping"
+                    }
+                );
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } if self.modifiers.shift() => {
+                let pos = match camera
+                    .and_then(|camera| self.placement(&camera, size, periodic_table, world))
+                {
+                    Some(pos) => pos,
+                    None => return false,
+                };
+
+                let fragment = Fragment::new(Some(AtomRepr {
+                    pos,
+                    kind: AtomKind::new(NEW_ELEMENT),
+                }));
+                let part = Part::from_fragments(world, "Added atom", Some(fragment));
+                world.spawn_part(part);
+
+                log::info!(
+                    "added {} at ({:.2}, {:.2}, {:.2})",
+                    NEW_ELEMENT.symbol(),
+                    pos.x,
+                    pos.y,
+                    pos.z
+                );
+                return true;
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } if self.modifiers.alt() => {
+                let (origin, direction) =
+                    match camera.and_then(|camera| cursor_ray(self.cursor, size, &camera)) {
+                        Some(ray) => ray,
+                        None => return false,
+                    };
+                let (index, atom) = match self.pick(world, periodic_table, origin, direction) {
+                    Some(hit) => hit,
+                    None => return false,
+                };
+
+                let old = atom.kind.element();
+                let new = ELEMENT_CYCLE
+                    .iter()
+                    .position(|&element| element == old)
+                    .map_or(ELEMENT_CYCLE[0], |position| {
+                        ELEMENT_CYCLE[(position + 1) % ELEMENT_CYCLE.len()]
+                    });
+                world.set_world_element(index, new);
+
+                log::info!("changed {} to {}", old.symbol(), new.symbol());
+                return true;
+            }
+            _ => {}
+        }
+
+        false
+    }
+
+    /// Where a click at the cursor puts the new atom.
+    fn placement(
+        &self,
+        camera: &CameraRepr,
+        size: PhysicalSize<u32>,
+        periodic_table: &PeriodicTable,
+        world: &World,
+    ) -> Option<Vec3> {
+        let (origin, direction) = cursor_ray(self.cursor, size, camera)?;
+
+        if let Some((_, hit)) = self.pick(world, periodic_table, origin, direction) {
+            return if self.snap {
+                let neighbors: Vec<_> = world.world_atoms().collect();
+                snapped_position(&hit, &neighbors, -direction)
+            } else {
+                Some(hit.pos - direction * BOND_LENGTH)
+            };
+        }
+
+        let normal = camera.forward();
+        let facing = direction.dot(normal);
+        if facing.abs() < 1.0e-6 {
+            return None;
+        }
+        let distance = -origin.dot(normal) / facing;
+        if distance > 0.0 {
+            Some(origin + direction * distance)
+        } else {
+            None
+        }
+    }
+
+    /// The closest visible atom under the cursor, and its index in `World::world_atoms`.
+    fn pick(
+        &self,
+        world: &World,
+        periodic_table: &PeriodicTable,
+        origin: Vec3,
+        direction: Vec3,
+    ) -> Option<(usize, AtomRepr)> {
+        world
+            .world_atoms()
+            .enumerate()
+            .filter(|(_, atom)| atom.kind.is_visible())
+            .filter_map(|(index, atom)| {
+                let radius = periodic_table.element_repr(atom.kind.element()).radius();
+
+                let to_center = atom.pos - origin;
+                let along = to_center.dot(direction);
+                let miss_sq = to_center.mag_sq() - along * along;
+                if along < 0.0 || miss_sq > radius * radius {
+                    return None;
+                }
+
+                Some((along - (radius * radius - miss_sq).sqrt(), (index, atom)))
+            })
+            .min_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(_, hit)| hit)
+    }
+}
+
+/// The origin and direction of the ray from the camera through the cursor.
+pub fn cursor_ray(
+    cursor: PhysicalPosition<f64>,
+    size: PhysicalSize<u32>,
+    camera: &CameraRepr,
+) -> Option<(Vec3, Vec3)> {
+    if size.width == 0 || size.height == 0 {
+        return None;
+    }
+
+    let x = (cursor.x as f32 / size.width as f32) * 2.0 - 1.0;
+    let y = 1.0 - (cursor.y as f32 / size.height as f32) * 2.0;
+    let inverse = camera.projection_view.inversed();
+    let unproject = |depth: f32| {
+        let pos = inverse * Vec4::new(x, y, depth, 1.0);
+        pos.xyz() / pos.w
+    };
+
+    // Reversed z puts the near plane at one, and the far plane is infinitely far away, so the
+    // second point is just somewhere in between.
+    let origin = unproject(1.0);
+    let direction = (unproject(0.5) - origin).normalized();
+    Some((origin, direction))
+}
+
+/// Where a new atom bonded to `atom` goes. When there's a choice, it's the one closest to
+/// `toward`, which points at the camera.
+///
+/// `neighbors` can be every atom in the world, since only the ones within bonding distance
+/// of `atom` count. `atom` itself is skipped if it's in there. Returns `None` if `atom` is
+/// linear or already has four neighbors.
+fn snapped_position(atom: &AtomRepr, neighbors: &[AtomRepr], toward: Vec3) -> Option<Vec3> {
+    let element = atom.kind.element();
+    let bonds: Vec<Vec3> = neighbors
+        .iter()
+        .filter_map(|other| {
+            let delta = other.pos - atom.pos;
+            let length = element.covalent_radius() + other.kind.element().covalent_radius();
+            let distance = delta.mag();
+            if distance > 1.0e-3 && distance <= length * BOND_TOLERANCE {
+                Some(delta / distance)
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    let direction = match bonds.len() {
+        0 => toward,
+        // Tetrahedral, rotated around the existing bond to face the camera.
+        1 => {
+            let bond = bonds[0];
+            let side = perpendicular(bond, toward);
+            let angle = TETRAHEDRAL_ANGLE.to_radians();
+            bond * angle.cos() + side * angle.sin()
+        }
+        2 => {
+            let sum = bonds[0] + bonds[1];
+            if sum.mag() < 0.1 {
+                log::info!("that atom is linear, so there's no room for another neighbor");
+                return None;
+            }
+
+            let bisector = sum.normalized();
+            if bonds[0].dot(bonds[1]) < (115f32).to_radians().cos() {
+                // Wide enough to be trigonal, so the new atom goes in the same plane.
+                -bisector
+            } else {
+                // The two remaining corners of a tetrahedron, out of the plane.
+                let normal = bonds[0].cross(bonds[1]).normalized();
+                let normal = if normal.dot(toward) < 0.0 {
+                    -normal
+                } else {
+                    normal
+                };
+                let half = (TETRAHEDRAL_ANGLE / 2.0).to_radians();
+                -bisector * half.cos() + normal * half.sin()
+            }
+        }
+        3 => {
+            let sum = bonds[0] + bonds[1] + bonds[2];
+            if sum.mag() < 0.1 {
+                // Flat, so the only room left is straight out of the plane.
+                let normal = (bonds[1] - bonds[0])
+                    .cross(bonds[2] - bonds[0])
+                    .normalized();
+                if normal.dot(toward) < 0.0 {
+                    -normal
+                } else {
+                    normal
+                }
+            } else {
+                -sum.normalized()
+            }
+        }
+        _ => {
+            log::info!("that atom already has {} neighbors", bonds.len());
+            return None;
+        }
+    };
+
+    let length = element.covalent_radius() + NEW_ELEMENT.covalent_radius();
+    Some(atom.pos + direction.normalized() * length)
+}
+
+/// A unit vector perpendicular to `axis`, as close to `toward` as possible.
+fn perpendicular(axis: Vec3, toward: Vec3) -> Vec3 {
+    let side = toward - axis * toward.dot(axis);
+    if side.mag_sq() > 1.0e-6 {
+        return side.normalized();
+    }
+
+    // `toward` is along the axis, so any perpendicular will do.
+    let helper = if axis.x.abs() < 0.9 {
+        Vec3::unit_x()
+    } else {
+        Vec3::unit_y()
+    };
+    axis.cross(helper).normalized()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atom(element: Element, pos: Vec3) -> AtomRepr {
+        AtomRepr {
+            pos,
+            kind: AtomKind::new(element),
+        }
+    }
+
+    #[test]
+    fn lone_atom_bonds_toward_the_camera() {
+        let carbon = atom(Element::Carbon, Vec3::new(1.0, 2.0, 3.0));
+        let pos = snapped_position(&carbon, &[carbon], Vec3::unit_z()).unwrap();
+
+        let bond = pos - carbon.pos;
+        let length = Element::Carbon.covalent_radius() + NEW_ELEMENT.covalent_radius();
+        assert!((bond.mag() - length).abs() < 1.0e-5);
+        assert!((bond.normalized() - Vec3::unit_z()).mag() < 1.0e-5);
+    }
+
+    #[test]
+    fn second_bond_is_tetrahedral() {
+        let carbon = atom(Element::Carbon, Vec3::zero());
+        let neighbor = atom(Element::Carbon, Vec3::new(1.52, 0.0, 0.0));
+        let pos = snapped_position(&carbon, &[carbon, neighbor], Vec3::unit_z()).unwrap();
+
+        let angle = pos.normalized().dot(Vec3::unit_x()).acos().to_degrees();
+        assert!((angle - TETRAHEDRAL_ANGLE).abs() < 0.01, "{}", angle);
+        // Rotated around the existing bond to face the camera.
+        assert!(pos.y.abs() < 1.0e-5 && pos.z > 0.0);
+    }
+
+    #[test]
+    fn linear_and_full_atoms_have_no_room() {
+        let carbon = atom(Element::Carbon, Vec3::zero());
+        let directions = [
+            Vec3::new(1.0, 1.0, 1.0),
+            Vec3::new(-1.0, -1.0, 1.0),
+            Vec3::new(-1.0, 1.0, -1.0),
+            Vec3::new(1.0, -1.0, -1.0),
+        ];
+        let full: Vec<_> = directions
+            .iter()
+            .map(|direction| atom(Element::Hydrogen, direction.normalized() * 1.07))
+            .collect();
+        assert_eq!(snapped_position(&carbon, &full, Vec3::unit_z()), None);
+
+        let linear = [
+            atom(Element::Carbon, Vec3::new(1.2, 0.0, 0.0)),
+            atom(Element::Carbon, Vec3::new(-1.2, 0.0, 0.0)),
+        ];
+        assert_eq!(snapped_position(&carbon, &linear, Vec3::unit_z()), None);
+    }
+}
@@ -0,0 +1,85 @@
+//! Applying window resizes once per frame.
+
+use std::mem;
+use winit::dpi::PhysicalSize;
+
+/// Resizing recreates every texture that depends on the size, so a drag-resize (which sends
+/// many resize events per frame) is only applied once per frame.
+pub struct PendingResize {
+    size: PhysicalSize<u32>,
+    pending: bool,
+}
+
+impl PendingResize {
+    pub fn new(size: PhysicalSize<u32>) -> Self {
+        Self {
+            size,
+            pending: false,
+        }
+    }
+
+    pub fn push(&mut self, size: PhysicalSize<u32>) {
+        self.size = size;
+        self.pending = true;
+    }
+
+    /// The size to resize to, if it changed since this was last called.
+    ///
+    /// A minimized window has no size, and there's nothing to render into, so that's skipped.
+    pub fn take(&mut self) -> Option<PhysicalSize<u32>> {
+        if mem::replace(&mut self.pending, false) && self.has_area() {
+            Some(self.size)
+        } else {
+            None
+        }
+    }
+
+    /// The latest size, whether or not it has been taken yet.
+    pub fn size(&self) -> PhysicalSize<u32> {
+        self.size
+    }
+
+    pub fn has_area(&self) -> bool {
+        self.size.width != 0 && self.size.height != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn many_resizes_in_a_frame_are_applied_once() {
+        let mut resize = PendingResize::new(PhysicalSize::new(800, 600));
+        assert_eq!(resize.take(), None);
+
+        for width in 801..=850 {
+            resize.push(PhysicalSize::new(width, 600));
+        }
+        assert_eq!(resize.take(), Some(PhysicalSize::new(850, 600)));
+        assert_eq!(resize.take(), None);
+    }
+
+    #[test]
+    fn zero_sizes_are_skipped() {
+        let mut resize = PendingResize::new(PhysicalSize::new(800, 600));
+
+        resize.push(PhysicalSize::new(0, 0));
+        assert_eq!(resize.take(), None);
+        assert!(!resize.has_area());
+
+        resize.push(PhysicalSize::new(1024, 0));
+        assert_eq!(resize.take(), None);
+    }
+
+    #[test]
+    fn later_sizes_are_applied_after_a_zero_size() {
+        let mut resize = PendingResize::new(PhysicalSize::new(800, 600));
+        resize.push(PhysicalSize::new(0, 0));
+        assert_eq!(resize.take(), None);
+
+        resize.push(PhysicalSize::new(640, 480));
+        assert_eq!(resize.take(), Some(PhysicalSize::new(640, 480)));
+        assert!(resize.has_area());
+    }
+}
@@ -99,6 +99,7 @@ impl Camera for RotatingArcballCamera {
                 }
                 _ => false,
             },
+            InputEvent::Navigate(_) | InputEvent::Bounds(_) => false,
             InputEvent::BeginningFrame => true,
         }
     }
@@ -0,0 +1,148 @@
+//! Solvent-accessible surface area, with the Shrake-Rupley algorithm.
+//!
+//! Every atom's van der Waals sphere is grown by the radius of the probe, and points spread
+//! evenly over it are tested against the grown spheres of its neighbors. The fraction of points
+//! that aren't inside any of them is the fraction of the sphere that the probe can reach.
+
+use crate::{convert, minimize::pairs_within};
+use periodic_table::PeriodicTable;
+use render::AtomRepr;
+use std::{f32::consts::PI, path::Path};
+use ultraviolet::Vec3;
+
+/// The radius of a water molecule, in angstroms, which is the usual probe.
+pub const WATER_PROBE_RADIUS: f32 = 1.4;
+/// How many points are tested on each sphere.
+const SPHERE_POINTS: usize = 100;
+
+/// Returns the accessible area of each atom, in square angstroms. The total is their sum.
+pub fn sasa(atoms: &[AtomRepr], periodic_table: &PeriodicTable, probe_radius: f32) -> Vec<f32> {
+    let positions: Vec<_> = atoms.iter().map(|atom| atom.pos).collect();
+    let radii: Vec<_> = atoms
+        .iter()
+        .map(|atom| periodic_table.element_repr(atom.kind.element()).radius() + probe_radius)
+        .collect();
+    let max_radius = radii.iter().copied().fold(0.0, f32::max);
+
+    let mut neighbors = vec![Vec::new(); atoms.len()];
+    for (a, b) in pairs_within(&positions, 2.0 * max_radius) {
+        let reach = radii[a] + radii[b];
+        if (positions[b] - positions[a]).mag_sq() < reach * reach {
+            neighbors[a].push(b);
+            neighbors[b].push(a);
+        }
+    }
+
+    let sphere = sphere_points(SPHERE_POINTS);
+    (0..atoms.len())
+        .map(|index| {
+            let (center, radius) = (positions[index], radii[index]);
+            let exposed = sphere
+                .iter()
+                .filter(|&&direction| {
+                    let point = center + direction * radius;
+                    neighbors[index].iter().all(|&other| {
+                        (point - positions[other]).mag_sq() >= radii[other] * radii[other]
+                    })
+                })
+                .count();
+
+            4.0 * PI * radius * radius * exposed as f32 / sphere.len() as f32
+        })
+        .collect()
+}
+
+/// Points spread evenly over a unit sphere, along a golden-angle spiral.
+fn sphere_points(count: usize) -> Vec<Vec3> {
+    let golden_angle = PI * (3.0 - 5f32.sqrt());
+
+    (0..count)
+        .map(|index| {
+            let z = 1.0 - (2.0 * index as f32 + 1.0) / count as f32;
+            let ring = (1.0 - z * z).sqrt();
+            let angle = golden_angle * index as f32;
+            Vec3::new(ring * angle.cos(), ring * angle.sin(), z)
+        })
+        .collect()
+}
+
+/// Prints the accessible area of every part in a structure file, and of all of them together.
+pub fn print_sasa(path: &Path, probe_radius: f32) -> Result<(), String> {
+    let structure = convert::load(path)?;
+    let atoms: Vec<_> = structure.atoms().copied().collect();
+    let areas = sasa(&atoms, &PeriodicTable::new(), probe_radius);
+
+    // Atoms are in the same order as the parts, so each part's areas come in one run.
+    let mut remaining = areas.iter();
+    for part in &structure.parts {
+        let count = part.fragments.iter().map(Vec::len).sum();
+        let area: f32 = remaining.by_ref().take(count).sum();
+        println!("{}: {:.1} Å²", part.name, area);
+    }
+    println!(
+        "total: {:.1} Å², with a {} Å probe",
+        areas.iter().sum::<f32>(),
+        probe_radius
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use periodic_table::Element;
+    use render::AtomKind;
+
+    fn atom(element: Element, x: f32) -> AtomRepr {
+        AtomRepr {
+            pos: Vec3::new(x, 0.0, 0.0),
+            kind: AtomKind::new(element),
+        }
+    }
+
+    #[test]
+    fn isolated_atom_is_fully_exposed() {
+        let periodic_table = PeriodicTable::new();
+        let areas = sasa(
+            &[atom(Element::Carbon, 0.0)],
+            &periodic_table,
+            WATER_PROBE_RADIUS,
+        );
+
+        let radius = periodic_table.element_repr(Element::Carbon).radius() + WATER_PROBE_RADIUS;
+        let expected = 4.0 * PI * radius * radius;
+        assert_eq!(areas.len(), 1);
+        assert!((areas[0] - expected).abs() < 1.0e-3 * expected);
+    }
+
+    #[test]
+    fn neighbors_cover_each_other() {
+        let periodic_table = PeriodicTable::new();
+        let alone = sasa(
+            &[atom(Element::Carbon, 0.0)],
+            &periodic_table,
+            WATER_PROBE_RADIUS,
+        );
+        let pair = sasa(
+            &[atom(Element::Carbon, 0.0), atom(Element::Carbon, 1.5)],
+            &periodic_table,
+            WATER_PROBE_RADIUS,
+        );
+
+        assert!(pair[0] < alone[0] && pair[0] > 0.0);
+        assert!((pair[0] - pair[1]).abs() < 0.05 * pair[0]);
+    }
+
+    #[test]
+    fn sphere_points_are_on_the_unit_sphere() {
+        let points = sphere_points(SPHERE_POINTS);
+        assert_eq!(points.len(), SPHERE_POINTS);
+        assert!(points
+            .iter()
+            .all(|point| (point.mag() - 1.0).abs() < 1.0e-5));
+
+        let center = points.iter().fold(Vec3::zero(), |sum, &point| sum + point);
+        assert!(center.mag() / (SPHERE_POINTS as f32) < 0.01);
+    }
+}
@@ -0,0 +1,1150 @@
+//! SMILES strings.
+//!
+//! When reading, the string is parsed into a molecular graph, hydrogens are added, and the
+//! graph is relaxed into 3D with idealized bond lengths and angles. That gives something
+//! viewable, but it's not a real conformer generator. Stereochemistry (`@`, `/`, and `\`) is
+//! parsed and ignored.
+//!
+//! We don't have bonds yet, so only the atoms end up in the structure. Writing goes the other
+//! way: bonds are inferred from distances, and bond orders from how many more bonds each atom
+//! has room for. The string that's written is canonical, so the same molecule gives the same
+//! string no matter what order its atoms are in.
+
+use crate::{
+    minimize::infer_bonds,
+    structure::{Structure, StructurePart},
+};
+use periodic_table::Element;
+use render::{AtomKind, AtomRepr};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    io,
+};
+use ultraviolet::Vec3;
+
+const RELAXATION_STEPS: usize = 500;
+/// Atoms that aren't bonded to each other (or to a common neighbor) are pushed at least this far
+/// apart, in angstroms.
+const MIN_NONBONDED_DISTANCE: f32 = 2.5;
+
+pub fn read_smiles(name: &str, contents: &str) -> Result<Structure, String> {
+    // A .smi file has one molecule per line, optionally followed by its name.
+    let smiles = contents
+        .lines()
+        .find_map(|line| line.split_whitespace().next())
+        .ok_or_else(|| "smiles: the file is empty".to_string())?;
+
+    let mut molecule = Parser::new(smiles).parse()?;
+    molecule.add_hydrogens();
+    let positions = embed(&molecule);
+
+    let atoms = molecule
+        .atoms
+        .iter()
+        .zip(positions)
+        .map(|(atom, pos)| AtomRepr {
+            pos,
+            kind: AtomKind::new(atom.element),
+        })
+        .collect();
+
+    Ok(Structure {
+        parts: vec![StructurePart {
+            name: name.to_string(),
+            fragments: vec![atoms],
+        }],
+        cell: None,
+    })
+}
+
+/// Writes the whole structure on one line, followed by its name.
+pub fn write_smiles<W: io::Write>(
+    structure: &Structure,
+    name: &str,
+    mut writer: W,
+) -> io::Result<()> {
+    let atoms: Vec<_> = structure.atoms().copied().collect();
+    writeln!(writer, "{} {}", canonical_smiles(&atoms), name)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum BondOrder {
+    Single,
+    Double,
+    Triple,
+    Aromatic,
+}
+
+impl BondOrder {
+    /// Aromatic bonds count as single bonds, and aromatic atoms make up the difference.
+    fn valence(self) -> u8 {
+        match self {
+            BondOrder::Single | BondOrder::Aromatic => 1,
+            BondOrder::Double => 2,
+            BondOrder::Triple => 3,
+        }
+    }
+
+    /// How much shorter than a single bond this is.
+    fn length_factor(self) -> f32 {
+        match self {
+            BondOrder::Single => 1.0,
+            BondOrder::Aromatic => 0.93,
+            BondOrder::Double => 0.87,
+            BondOrder::Triple => 0.78,
+        }
+    }
+}
+
+struct Atom {
+    element: Element,
+    aromatic: bool,
+    /// Bracket atoms always say how many hydrogens they have, and other atoms get enough to
+    /// fill their lowest normal valence.
+    hydrogens: Option<u8>,
+}
+
+struct Bond {
+    a: usize,
+    b: usize,
+    order: BondOrder,
+}
+
+#[derive(Default)]
+struct Molecule {
+    atoms: Vec<Atom>,
+    bonds: Vec<Bond>,
+}
+
+impl Molecule {
+    fn add_hydrogens(&mut self) {
+        let mut bond_valences = vec![0; self.atoms.len()];
+        for bond in &self.bonds {
+            bond_valences[bond.a] += bond.order.valence();
+            bond_valences[bond.b] += bond.order.valence();
+        }
+
+        for index in 0..self.atoms.len() {
+            let atom = &self.atoms[index];
+            let hydrogens = atom.hydrogens.unwrap_or_else(|| {
+                let used = bond_valences[index] + atom.aromatic as u8;
+                default_valences(atom.element)
+                    .iter()
+                    .find(|&&valence| valence >= used)
+                    .map(|valence| valence - used)
+                    .unwrap_or(0)
+            });
+
+            for _ in 0..hydrogens {
+                self.atoms.push(Atom {
+                    element: Element::Hydrogen,
+                    aromatic: false,
+                    hydrogens: Some(0),
+                });
+                self.bonds.push(Bond {
+                    a: index,
+                    b: self.atoms.len() - 1,
+                    order: BondOrder::Single,
+                });
+            }
+        }
+    }
+}
+
+/// The valences of the organic subset, which can be written without brackets.
+fn default_valences(element: Element) -> &'static [u8] {
+    match element {
+        Element::Boron => &[3],
+        Element::Carbon => &[4],
+        Element::Nitrogen => &[3, 5],
+        Element::Oxygen => &[2],
+        Element::Phosphorus => &[3, 5],
+        Element::Sulfur => &[2, 4, 6],
+        Element::Fluorine | Element::Chlorine | Element::Bromine | Element::Iodine => &[1],
+        _ => &[],
+    }
+}
+
+struct Parser<'a> {
+    smiles: &'a str,
+    position: usize,
+    molecule: Molecule,
+    previous: Option<usize>,
+    branches: Vec<Option<usize>>,
+    pending_bond: Option<BondOrder>,
+    // The atom each open ring closure starts at, the bond written there, if any, and where it
+    // is in the string.
+    rings: HashMap<u32, (usize, Option<BondOrder>, usize)>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(smiles: &'a str) -> Self {
+        Self {
+            smiles,
+            position: 0,
+            molecule: Molecule::default(),
+            previous: None,
+            branches: Vec::new(),
+            pending_bond: None,
+            rings: HashMap::new(),
+        }
+    }
+
+    fn parse(mut self) -> Result<Molecule, String> {
+        while let Some(c) = self.peek() {
+            match c {
+                '(' => {
+                    if self.previous.is_none() {
+                        return Err(self.error("a branch has to follow an atom"));
+                    }
+                    self.branches.push(self.previous);
+                    self.advance();
+                }
+                ')' => {
+                    if self.pending_bond.is_some() {
+                        return Err(self.error("a bond has to be followed by an atom"));
+                    }
+                    self.previous = match self.branches.pop() {
+                        Some(previous) => previous,
+                        None => return Err(self.error("unmatched `)`")),
+                    };
+                    self.advance();
+                }
+                '-' | '=' | '#' | ':' | '/' | '\\' => {
+                    if self.pending_bond.is_some() {
+                        return Err(self.error("two bonds in a row"));
+                    }
+                    self.pending_bond = Some(match c {
+                        '=' => BondOrder::Double,
+                        '#' => BondOrder::Triple,
+                        ':' => BondOrder::Aromatic,
+                        // `/` and `\` are single bonds with a direction, for cis/trans.
+                        _ => BondOrder::Single,
+                    });
+                    self.advance();
+                }
+                '.' => {
+                    if self.pending_bond.is_some() {
+                        return Err(self.error("a bond has to be followed by an atom"));
+                    }
+                    self.previous = None;
+                    self.advance();
+                }
+                '0'..='9' | '%' => self.ring_closure()?,
+                '[' => self.bracket_atom()?,
+                _ => self.organic_atom()?,
+            }
+        }
+
+        if self.pending_bond.is_some() {
+            return Err(self.error("a bond has to be followed by an atom"));
+        }
+        if !self.branches.is_empty() {
+            return Err(self.error("unclosed branch"));
+        }
+        if let Some((&ring, &(_, _, position))) = self.rings.iter().min_by_key(|(&ring, _)| ring) {
+            // Points at where the ring was opened, since the end of the string isn't helpful.
+            self.position = position;
+            return Err(self.error(&format!("ring {} is never closed", ring)));
+        }
+        if self.molecule.atoms.is_empty() {
+            return Err("smiles: there are no atoms".to_string());
+        }
+
+        Ok(self.molecule)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.smiles[self.position..].chars().next()
+    }
+
+    fn advance(&mut self) {
+        if let Some(c) = self.peek() {
+            self.position += c.len_utf8();
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        let start = self.position;
+        while self.peek().map_or(false, |c| c.is_ascii_digit()) {
+            self.advance();
+        }
+        self.smiles[start..self.position].parse().ok()
+    }
+
+    /// Columns start at one.
+    fn error(&self, message: &str) -> String {
+        let column = self.smiles[..self.position].chars().count() + 1;
+        format!("smiles: {} at column {}", message, column)
+    }
+
+    fn default_bond(&self, a: usize, b: usize) -> BondOrder {
+        if self.molecule.atoms[a].aromatic && self.molecule.atoms[b].aromatic {
+            BondOrder::Aromatic
+        } else {
+            BondOrder::Single
+        }
+    }
+
+    fn add_atom(&mut self, atom: Atom) {
+        let index = self.molecule.atoms.len();
+        self.molecule.atoms.push(atom);
+
+        if let Some(previous) = self.previous {
+            let order = self
+                .pending_bond
+                .take()
+                .unwrap_or_else(|| self.default_bond(previous, index));
+            self.molecule.bonds.push(Bond {
+                a: previous,
+                b: index,
+                order,
+            });
+        }
+        self.pending_bond = None;
+        self.previous = Some(index);
+    }
+
+    fn ring_closure(&mut self) -> Result<(), String> {
+        let previous = match self.previous {
+            Some(previous) => previous,
+            None => return Err(self.error("a ring closure has to follow an atom")),
+        };
+        let opened = self.position;
+
+        let ring = if self.eat('%') {
+            let start = self.position;
+            for _ in 0..2 {
+                match self.peek() {
+                    Some(c) if c.is_ascii_digit() => self.advance(),
+                    _ => return Err(self.error("expected two digits after `%`")),
+                }
+            }
+            self.smiles[start..self.position]
+                .parse()
+                .unwrap_or_default()
+        } else {
+            let digit = self.peek().and_then(|c| c.to_digit(10)).unwrap_or_default();
+            self.advance();
+            digit
+        };
+
+        let bond = self.pending_bond.take();
+        match self.rings.remove(&ring) {
+            Some((start, start_bond, _)) => {
+                if start == previous {
+                    return Err(self.error("a ring can't close on the atom it started at"));
+                }
+                if let (Some(a), Some(b)) = (bond, start_bond) {
+                    if a != b {
+                        return Err(self.error("the two ends of a ring closure disagree"));
+                    }
+                }
+                let order = bond
+                    .or(start_bond)
+                    .unwrap_or_else(|| self.default_bond(start, previous));
+                self.molecule.bonds.push(Bond {
+                    a: start,
+                    b: previous,
+                    order,
+                });
+            }
+            None => {
+                self.rings.insert(ring, (previous, bond, opened));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn organic_atom(&mut self) -> Result<(), String> {
+        let smiles = self.smiles;
+        let rest = &smiles[self.position..];
+        let (symbol, aromatic) = if rest.starts_with("Cl") || rest.starts_with("Br") {
+            (&rest[..2], false)
+        } else {
+            match self.peek() {
+                Some(c @ 'B') | Some(c @ 'C') | Some(c @ 'N') | Some(c @ 'O') | Some(c @ 'P')
+                | Some(c @ 'S') | Some(c @ 'F') | Some(c @ 'I') => (&rest[..c.len_utf8()], false),
+                Some(c @ 'b') | Some(c @ 'c') | Some(c @ 'n') | Some(c @ 'o') | Some(c @ 'p')
+                | Some(c @ 's') => (&rest[..c.len_utf8()], true),
+                Some(c) => return Err(self.error(&format!("unexpected `{}`", c))),
+                None => unreachable!(),
+            }
+        };
+
+        let element = Element::from_symbol(symbol)
+            .unwrap_or_else(|| unreachable!("the organic subset is all elements"));
+        self.position += symbol.len();
+
+        self.add_atom(Atom {
+            element,
+            aromatic,
+            hydrogens: None,
+        });
+        Ok(())
+    }
+
+    /// `[isotope? symbol chirality? hydrogens? charge? class?]`
+    fn bracket_atom(&mut self) -> Result<(), String> {
+        self.advance(); // [
+
+        // Isotopes don't change anything we show.
+        let _isotope = self.number();
+
+        let (element, aromatic) = self.bracket_symbol()?;
+
+        // Chirality, like `@`, `@@`, or `@TH1`.
+        if self.eat('@') {
+            self.eat('@');
+            let smiles = self.smiles;
+            let rest = &smiles[self.position..];
+            for class in &["TH", "AL", "SP", "TB", "OH"] {
+                if rest.starts_with(class) {
+                    self.advance();
+                    self.advance();
+                    self.number();
+                    break;
+                }
+            }
+        }
+
+        let hydrogens = if self.eat('H') {
+            self.number().unwrap_or(1) as u8
+        } else {
+            0
+        };
+
+        // Charges don't change anything we show either, but they still have to be valid.
+        for sign in &['+', '-'] {
+            if self.eat(*sign) {
+                if self.number().is_none() {
+                    while self.eat(*sign) {}
+                }
+                break;
+            }
+        }
+
+        if self.eat(':') && self.number().is_none() {
+            return Err(self.error("expected an atom class after `:`"));
+        }
+
+        if !self.eat(']') {
+            return Err(match self.peek() {
+                Some(c) => self.error(&format!("unexpected `{}` in a bracket atom", c)),
+                None => self.error("unclosed bracket atom"),
+            });
+        }
+
+        self.add_atom(Atom {
+            element,
+            aromatic,
+            hydrogens: Some(hydrogens),
+        });
+        Ok(())
+    }
+
+    fn bracket_symbol(&mut self) -> Result<(Element, bool), String> {
+        let smiles = self.smiles;
+        let rest = &smiles[self.position..];
+
+        // Aromatic symbols are lowercase.
+        for &(symbol, element) in &[("se", Element::Selenium), ("as", Element::Arsenic)] {
+            if rest.starts_with(symbol) {
+                self.advance();
+                self.advance();
+                return Ok((element, true));
+            }
+        }
+
+        let mut chars = rest.chars();
+        match chars.next() {
+            Some(c) if "bcnops".contains(c) => {
+                self.advance();
+                let element = Element::from_symbol(&c.to_string())
+                    .unwrap_or_else(|| unreachable!("aromatic symbols are all elements"));
+                Ok((element, true))
+            }
+            Some(c) if c.is_ascii_uppercase() => {
+                // Two-letter symbols win, so `[Co]` is cobalt rather than carbon and oxygen.
+                let two_letters = chars
+                    .next()
+                    .filter(|c| c.is_ascii_lowercase())
+                    .and_then(|_| rest.get(..2))
+                    .and_then(Element::from_symbol);
+
+                match two_letters {
+                    Some(element) => {
+                        self.advance();
+                        self.advance();
+                        Ok((element, false))
+                    }
+                    None => match Element::from_symbol(&c.to_string()) {
+                        Some(element) => {
+                            self.advance();
+                            Ok((element, false))
+                        }
+                        None => Err(self.error(&format!("unknown element `{}`", c))),
+                    },
+                }
+            }
+            Some('*') => Err(self.error("wildcard atoms aren't supported")),
+            Some(c) => Err(self.error(&format!("unexpected `{}` in a bracket atom", c))),
+            None => Err(self.error("unclosed bracket atom")),
+        }
+    }
+}
+
+/// A tiny xorshift generator, so embedding is deterministic without a dependency.
+struct Random(u32);
+
+impl Random {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0 as f32 / u32::MAX as f32
+    }
+
+    fn unit_vector(&mut self) -> Vec3 {
+        loop {
+            let v = Vec3::new(self.next(), self.next(), self.next()) * 2.0 - Vec3::one();
+            let mag_sq = v.mag_sq();
+            if mag_sq > 0.01 && mag_sq <= 1.0 {
+                return v / mag_sq.sqrt();
+            }
+        }
+    }
+}
+
+/// Places every atom in 3D: first by walking the graph outward from the first atom, then by
+/// relaxing bond lengths, bond angles, and clashes between unrelated atoms.
+fn embed(molecule: &Molecule) -> Vec<Vec3> {
+    let count = molecule.atoms.len();
+    let mut neighbors = vec![Vec::new(); count];
+    for bond in &molecule.bonds {
+        let length = (molecule.atoms[bond.a].element.covalent_radius()
+            + molecule.atoms[bond.b].element.covalent_radius())
+            * bond.order.length_factor();
+        neighbors[bond.a].push((bond.b, length, bond.order));
+        neighbors[bond.b].push((bond.a, length, bond.order));
+    }
+
+    let mut random = Random(0x9e37_79b9);
+    let mut positions = vec![Vec3::zero(); count];
+    let mut placed = vec![false; count];
+
+    // Disconnected components are laid out next to each other.
+    let mut component_offset = Vec3::zero();
+    for root in 0..count {
+        if placed[root] {
+            continue;
+        }
+
+        positions[root] = component_offset;
+        placed[root] = true;
+        let mut queue = VecDeque::new();
+        queue.push_back((root, None));
+
+        while let Some((atom, parent)) = queue.pop_front() {
+            let away = match parent {
+                Some(parent) => (positions[atom] - positions[parent]).normalized(),
+                None => Vec3::zero(),
+            };
+
+            for &(neighbor, length, _) in &neighbors[atom] {
+                if !placed[neighbor] {
+                    let direction = (away + random.unit_vector()).normalized();
+                    positions[neighbor] = positions[atom] + direction * length;
+                    placed[neighbor] = true;
+                    queue.push_back((neighbor, Some(atom)));
+                }
+            }
+        }
+
+        let extent = positions
+            .iter()
+            .zip(&placed)
+            .filter(|(_, placed)| **placed)
+            .fold(component_offset.x, |max, (pos, _)| max.max(pos.x));
+        component_offset.x = extent + 2.0 * MIN_NONBONDED_DISTANCE;
+    }
+
+    // Neighbors of a common atom are kept at the distance that gives the ideal bond angle.
+    let mut angle_springs = Vec::new();
+    let mut related = HashSet::new();
+    for (center, bonded) in neighbors.iter().enumerate() {
+        let angle = ideal_angle(bonded.iter().map(|&(_, _, order)| order));
+
+        for (i, &(a, length_a, _)) in bonded.iter().enumerate() {
+            related.insert((center.min(a), center.max(a)));
+
+            for &(b, length_b, _) in &bonded[i + 1..] {
+                let distance = (length_a.powi(2) + length_b.powi(2)
+                    - 2.0 * length_a * length_b * angle.cos())
+                .sqrt();
+                angle_springs.push((a, b, distance));
+                related.insert((a.min(b), a.max(b)));
+            }
+        }
+    }
+
+    for _ in 0..RELAXATION_STEPS {
+        let mut forces = vec![Vec3::zero(); count];
+        let mut spring = |a: usize, b: usize, length: f32, stiffness: f32, only_push: bool| {
+            let delta = positions[b] - positions[a];
+            let distance = delta.mag().max(1.0e-4);
+            let stretch = distance - length;
+            if only_push && stretch >= 0.0 {
+                return;
+            }
+            let force = delta / distance * (stretch * stiffness);
+            forces[a] += force;
+            forces[b] -= force;
+        };
+
+        for bond in &molecule.bonds {
+            let length = neighbors[bond.a]
+                .iter()
+                .find(|&&(neighbor, _, _)| neighbor == bond.b)
+                .map(|&(_, length, _)| length)
+                .unwrap_or(1.5);
+            spring(bond.a, bond.b, length, 1.0, false);
+        }
+        for &(a, b, distance) in &angle_springs {
+            spring(a, b, distance, 0.5, false);
+        }
+        for a in 0..count {
+            for b in a + 1..count {
+                if !related.contains(&(a, b)) {
+                    spring(a, b, MIN_NONBONDED_DISTANCE, 0.2, true);
+                }
+            }
+        }
+
+        // Small, clamped steps are slow but never blow up.
+        for (position, force) in positions.iter_mut().zip(forces) {
+            let step = force * 0.1;
+            let mag = step.mag();
+            *position += if mag > 0.2 { step * (0.2 / mag) } else { step };
+        }
+    }
+
+    positions
+}
+
+/// Triple bonds and allenes are linear, double and aromatic bonds are trigonal, and everything
+/// else is tetrahedral.
+fn ideal_angle<I: Iterator<Item = BondOrder>>(bonds: I) -> f32 {
+    let (mut doubles, mut unsaturated) = (0, false);
+    for order in bonds {
+        match order {
+            BondOrder::Triple => return std::f32::consts::PI,
+            BondOrder::Double => {
+                doubles += 1;
+                unsaturated = true;
+            }
+            BondOrder::Aromatic => unsaturated = true,
+            BondOrder::Single => {}
+        }
+    }
+
+    if doubles >= 2 {
+        std::f32::consts::PI
+    } else if unsaturated {
+        120f32.to_radians()
+    } else {
+        109.47f32.to_radians()
+    }
+}
+
+/// A SMILES string for the atoms that doesn't depend on their order. Molecules that aren't
+/// bonded to each other are separated by `.`, and sorted.
+///
+/// Hydrogens are folded into the atom they're bonded to. Atoms without room for the bonds and
+/// hydrogens they have, or with room left over, are written in brackets.
+pub fn canonical_smiles(atoms: &[AtomRepr]) -> String {
+    let elements: Vec<_> = atoms.iter().map(|atom| atom.kind.element()).collect();
+    let mut neighbors = vec![Vec::new(); atoms.len()];
+    for (a, b) in infer_bonds(atoms) {
+        neighbors[a].push(b);
+        neighbors[b].push(a);
+    }
+
+    let folded = |index: usize| {
+        elements[index] == Element::Hydrogen
+            && neighbors[index].len() == 1
+            && elements[neighbors[index][0]] != Element::Hydrogen
+    };
+    let mut hydrogens = vec![0; atoms.len()];
+    let mut graph = vec![Vec::new(); atoms.len()];
+    let mut kept = Vec::new();
+    for index in 0..atoms.len() {
+        if folded(index) {
+            hydrogens[neighbors[index][0]] += 1;
+        } else {
+            kept.push(index);
+            graph[index] = neighbors[index]
+                .iter()
+                .copied()
+                .filter(|&other| !folded(other))
+                .collect();
+        }
+    }
+
+    let ranks = canonical_ranks(&kept, &graph, |index| {
+        (elements[index] as u8, graph[index].len(), hydrogens[index])
+    });
+    let orders = bond_orders(&kept, &graph, &ranks, |index| {
+        let used = graph[index].len() + hydrogens[index];
+        default_valences(elements[index])
+            .iter()
+            .map(|&valence| valence as usize)
+            .find(|&valence| valence >= used)
+            .map_or(0, |valence| valence - used)
+    });
+
+    let mut by_rank = kept.clone();
+    by_rank.sort_by_key(|&index| ranks[index]);
+
+    let mut writer = SmilesWriter {
+        graph: &graph,
+        ranks: &ranks,
+        orders: &orders,
+        visited: vec![false; atoms.len()],
+        symbol: &|index| {
+            let valence: usize = graph[index]
+                .iter()
+                .map(|&other| orders[&bond_key(index, other)] as usize)
+                .sum();
+            let implied = default_valences(elements[index])
+                .iter()
+                .map(|&valence| valence as usize)
+                .find(|&default| default >= valence)
+                .map(|default| default - valence);
+
+            let symbol = elements[index].symbol();
+            match (implied, hydrogens[index]) {
+                (Some(implied), count) if implied == count => symbol.to_string(),
+                (_, 0) => format!("[{}]", symbol),
+                (_, 1) => format!("[{}H]", symbol),
+                (_, count) => format!("[{}H{}]", symbol, count),
+            }
+        },
+    };
+
+    let mut molecules: Vec<_> = by_rank
+        .iter()
+        .filter_map(|&start| writer.write_from(start))
+        .collect();
+    molecules.sort();
+    molecules.join(".")
+}
+
+/// Ranks atoms by their invariants, and then by their neighbors' ranks until that stops
+/// telling any more of them apart, like Morgan's algorithm. Ties that are left are between
+/// atoms that look the same from every direction, and are broken one at a time.
+fn canonical_ranks<K, F>(kept: &[usize], graph: &[Vec<usize>], invariant: F) -> Vec<usize>
+where
+    K: Ord,
+    F: Fn(usize) -> K,
+{
+    let mut ranks = vec![0; graph.len()];
+    let mut classes = rank_by(kept, &mut ranks, invariant);
+
+    loop {
+        // Refining only ever splits classes, so it's done once the count stops growing.
+        loop {
+            let previous = ranks.clone();
+            let refined = rank_by(kept, &mut ranks, |index| {
+                let mut around: Vec<_> =
+                    graph[index].iter().map(|&other| previous[other]).collect();
+                around.sort();
+                (previous[index], around)
+            });
+            if refined == classes {
+                break;
+            }
+            classes = refined;
+        }
+
+        if classes == kept.len() {
+            return ranks;
+        }
+
+        // Splits the lowest tied rank, by moving one of its atoms ahead of the rest.
+        let mut counts = vec![0; classes];
+        for &index in kept {
+            counts[ranks[index]] += 1;
+        }
+        let tied = counts.iter().position(|&count| count > 1);
+        let chosen = kept
+            .iter()
+            .copied()
+            .find(|&index| Some(ranks[index]) == tied);
+        let previous = ranks.clone();
+        classes = rank_by(kept, &mut ranks, |index| {
+            (previous[index], Some(index) != chosen)
+        });
+    }
+}
+
+/// Sets the rank of every kept atom to where its key sorts, with equal keys sharing a rank.
+/// Returns how many different ranks there are.
+fn rank_by<K, F>(kept: &[usize], ranks: &mut [usize], key: F) -> usize
+where
+    K: Ord,
+    F: Fn(usize) -> K,
+{
+    let mut keyed: Vec<_> = kept.iter().map(|&index| (key(index), index)).collect();
+    keyed.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut classes = 0;
+    for i in 0..keyed.len() {
+        if i > 0 && keyed[i].0 != keyed[i - 1].0 {
+            classes += 1;
+        }
+        ranks[keyed[i].1] = classes;
+    }
+    if keyed.is_empty() {
+        0
+    } else {
+        classes + 1
+    }
+}
+
+fn bond_key(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+/// Raises bond orders between atoms that have room for more bonds, until no two neighbors both
+/// do. Atoms with only one unsaturated neighbor go first, since they have no other choice, and
+/// that's enough to get alternating bonds around rings.
+fn bond_orders<F>(
+    kept: &[usize],
+    graph: &[Vec<usize>],
+    ranks: &[usize],
+    free_valence: F,
+) -> HashMap<(usize, usize), u8>
+where
+    F: Fn(usize) -> usize,
+{
+    let mut orders = HashMap::new();
+    for &a in kept {
+        for &b in &graph[a] {
+            orders.insert(bond_key(a, b), 1);
+        }
+    }
+
+    let mut free = vec![0; graph.len()];
+    for &index in kept {
+        free[index] = free_valence(index);
+    }
+    let mut open_neighbors: Vec<usize> = (0..graph.len())
+        .map(|index| {
+            graph[index]
+                .iter()
+                .filter(|&&other| free[other] > 0)
+                .count()
+        })
+        .collect();
+
+    let (mut forced, mut open) = (BTreeSet::new(), BTreeSet::new());
+    let update = |index: usize,
+                  free: &[usize],
+                  open_neighbors: &[usize],
+                  forced: &mut BTreeSet<(usize, usize)>,
+                  open: &mut BTreeSet<(usize, usize)>| {
+        let key = (ranks[index], index);
+        forced.remove(&key);
+        open.remove(&key);
+        if free[index] > 0 && open_neighbors[index] == 1 {
+            forced.insert(key);
+        } else if free[index] > 0 && open_neighbors[index] > 1 {
+            open.insert(key);
+        }
+    };
+    for &index in kept {
+        update(index, &free, &open_neighbors, &mut forced, &mut open);
+    }
+
+    while let Some(a) = first_atom(&forced, &open) {
+        let b = match graph[a]
+            .iter()
+            .copied()
+            .filter(|&other| free[other] > 0)
+            .min_by_key(|&other| ranks[other])
+        {
+            Some(b) => b,
+            None => break,
+        };
+
+        if let Some(order) = orders.get_mut(&bond_key(a, b)) {
+            *order += 1;
+        }
+        for &index in &[a, b] {
+            free[index] -= 1;
+            if free[index] == 0 {
+                for &other in &graph[index] {
+                    open_neighbors[other] -= 1;
+                    update(other, &free, &open_neighbors, &mut forced, &mut open);
+                }
+            }
+            update(index, &free, &open_neighbors, &mut forced, &mut open);
+        }
+    }
+
+    orders
+}
+
+/// The lowest-ranked atom in the first set, or in the second if the first is empty.
+fn first_atom(forced: &BTreeSet<(usize, usize)>, open: &BTreeSet<(usize, usize)>) -> Option<usize> {
+    forced
+        .iter()
+        .next()
+        .or_else(|| open.iter().next())
+        .map(|&(_, index)| index)
+}
+
+/// Writes molecules as a depth-first walk, visiting neighbors in the order of their ranks.
+struct SmilesWriter<'a> {
+    graph: &'a [Vec<usize>],
+    ranks: &'a [usize],
+    orders: &'a HashMap<(usize, usize), u8>,
+    visited: Vec<bool>,
+    symbol: &'a dyn Fn(usize) -> String,
+}
+
+impl<'a> SmilesWriter<'a> {
+    /// Writes the molecule that `start` is in, unless it's already been written.
+    fn write_from(&mut self, start: usize) -> Option<String> {
+        if self.visited[start] {
+            return None;
+        }
+
+        // The first pass finds the spanning tree. The walk uses a stack rather than recursion,
+        // since polymers can be thousands of atoms deep.
+        let mut order = Vec::new();
+        let mut parents = HashMap::new();
+        let mut stack = vec![(start, None)];
+        while let Some((index, parent)) = stack.pop() {
+            if self.visited[index] {
+                continue;
+            }
+            self.visited[index] = true;
+            order.push(index);
+            parents.insert(index, parent);
+
+            let mut next: Vec<_> = self.graph[index]
+                .iter()
+                .copied()
+                .filter(|&other| !self.visited[other])
+                .collect();
+            next.sort_by_key(|&other| std::cmp::Reverse(self.ranks[other]));
+            stack.extend(next.into_iter().map(|other| (other, Some(index))));
+        }
+
+        let position: HashMap<_, _> = order.iter().enumerate().map(|(i, &a)| (a, i)).collect();
+        let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+        for &index in &order[1..] {
+            if let Some(Some(parent)) = parents.get(&index) {
+                children.entry(*parent).or_default().push(index);
+            }
+        }
+
+        // Every bond that isn't in the tree closes a ring.
+        let mut rings: HashMap<usize, Vec<usize>> = HashMap::new();
+        for &a in &order {
+            for &b in &self.graph[a] {
+                let tree = parents[&a] == Some(b) || parents[&b] == Some(a);
+                if !tree {
+                    rings.entry(a).or_default().push(b);
+                }
+            }
+        }
+
+        enum Task {
+            Atom(usize),
+            Open,
+            Close,
+        }
+
+        let mut smiles = String::new();
+        let mut digits: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut used = BTreeSet::new();
+        let mut tasks = vec![Task::Atom(start)];
+        while let Some(task) = tasks.pop() {
+            let index = match task {
+                Task::Atom(index) => index,
+                Task::Open => {
+                    smiles.push('(');
+                    continue;
+                }
+                Task::Close => {
+                    smiles.push(')');
+                    continue;
+                }
+            };
+
+            if let Some(parent) = parents[&index] {
+                smiles.push_str(self.bond_symbol(parent, index));
+            }
+            smiles.push_str(&(self.symbol)(index));
+
+            let mut ring_bonds = rings.get(&index).cloned().unwrap_or_default();
+            ring_bonds.sort_by_key(|other| position[other]);
+            // Closing rings before opening new ones lets the digits be reused right away.
+            let (closing, opening): (Vec<_>, Vec<_>) = ring_bonds
+                .into_iter()
+                .partition(|other| position[other] < position[&index]);
+            for other in closing {
+                if let Some(digit) = digits.remove(&bond_key(index, other)) {
+                    used.remove(&digit);
+                    smiles.push_str(&ring_digit(digit));
+                }
+            }
+            for other in opening {
+                let digit = (1..).find(|digit| !used.contains(digit)).unwrap_or(1);
+                used.insert(digit);
+                digits.insert(bond_key(index, other), digit);
+                smiles.push_str(self.bond_symbol(index, other));
+                smiles.push_str(&ring_digit(digit));
+            }
+
+            // Every child but the last is a branch.
+            if let Some(children) = children.get(&index) {
+                if let Some((&last, branches)) = children.split_last() {
+                    tasks.push(Task::Atom(last));
+                    for &branch in branches.iter().rev() {
+                        tasks.push(Task::Close);
+                        tasks.push(Task::Atom(branch));
+                        tasks.push(Task::Open);
+                    }
+                }
+            }
+        }
+
+        Some(smiles)
+    }
+
+    fn bond_symbol(&self, a: usize, b: usize) -> &'static str {
+        match self.orders.get(&bond_key(a, b)) {
+            Some(2) => "=",
+            Some(3) => "#",
+            _ => "",
+        }
+    }
+}
+
+fn ring_digit(digit: usize) -> String {
+    if digit < 10 {
+        digit.to_string()
+    } else {
+        format!("%{}", digit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(smiles: &str) -> Molecule {
+        let mut molecule = Parser::new(smiles).parse().unwrap();
+        molecule.add_hydrogens();
+        molecule
+    }
+
+    fn count(molecule: &Molecule, element: Element) -> usize {
+        molecule
+            .atoms
+            .iter()
+            .filter(|atom| atom.element == element)
+            .count()
+    }
+
+    #[test]
+    fn ethanol() {
+        let molecule = parse("CCO");
+
+        assert_eq!(count(&molecule, Element::Carbon), 2);
+        assert_eq!(count(&molecule, Element::Oxygen), 1);
+        assert_eq!(count(&molecule, Element::Hydrogen), 6);
+        assert_eq!(molecule.bonds.len(), 8);
+    }
+
+    #[test]
+    fn benzene() {
+        let molecule = parse("c1ccccc1");
+
+        assert_eq!(count(&molecule, Element::Carbon), 6);
+        assert_eq!(count(&molecule, Element::Hydrogen), 6);
+        assert_eq!(molecule.bonds.len(), 12);
+        let aromatic = molecule
+            .bonds
+            .iter()
+            .filter(|bond| bond.order == BondOrder::Aromatic)
+            .count();
+        assert_eq!(aromatic, 6);
+    }
+
+    #[test]
+    fn branch_and_ring() {
+        // Isopropylcyclopentane.
+        let molecule = parse("CC(C)C1CCCC1");
+
+        assert_eq!(count(&molecule, Element::Carbon), 8);
+        assert_eq!(count(&molecule, Element::Hydrogen), 16);
+        assert_eq!(molecule.bonds.len(), 24);
+    }
+
+    #[test]
+    fn unmatched_parenthesis() {
+        let error = Parser::new("CC)C").parse().err().unwrap();
+        assert_eq!(error, "smiles: unmatched `)` at column 3");
+    }
+
+    #[test]
+    fn unclosed_ring() {
+        let error = Parser::new("CC1CC").parse().err().unwrap();
+        assert_eq!(error, "smiles: ring 1 is never closed at column 3");
+    }
+
+    fn embedded(smiles: &str) -> Vec<AtomRepr> {
+        read_smiles("test", smiles)
+            .unwrap()
+            .atoms()
+            .copied()
+            .collect()
+    }
+
+    /// The same atoms, reversed, and rotated by a few places.
+    fn shuffles(atoms: &[AtomRepr]) -> [Vec<AtomRepr>; 2] {
+        let mut reversed = atoms.to_vec();
+        reversed.reverse();
+        let mut rotated = atoms.to_vec();
+        rotated.rotate_left(3);
+        [reversed, rotated]
+    }
+
+    #[test]
+    fn canonical_smiles_ignores_atom_order() {
+        for &(smiles, expected) in &[("OCC", "CCO"), ("C1CCCCC1O", "C1CCC(CC1)O")] {
+            let atoms = embedded(smiles);
+            let canonical = canonical_smiles(&atoms);
+            assert_eq!(canonical, expected);
+
+            for shuffled in &shuffles(&atoms) {
+                assert_eq!(canonical_smiles(shuffled), canonical);
+            }
+        }
+    }
+}
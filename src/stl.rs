@@ -0,0 +1,89 @@
+//! Binary STL, for 3d printing. This is write-only.
+
+use render::Mesh;
+use std::io;
+
+/// The grid spacing of the van der Waals surface, in angstroms.
+pub const DEFAULT_RESOLUTION: f32 = 0.5;
+
+const HEADER: &[u8] = b"atomCAD";
+
+/// An empty mesh is written as a valid file with no triangles.
+pub fn write_stl<W: io::Write>(mesh: &Mesh, mut writer: W) -> io::Result<()> {
+    let mut header = [0; 80];
+    header[..HEADER.len()].copy_from_slice(HEADER);
+    writer.write_all(&header)?;
+
+    let triangles = mesh.triangles();
+    writer.write_all(&(triangles.len() as u32).to_le_bytes())?;
+
+    for [a, b, c] in triangles {
+        let mut normal = (b - a).cross(c - a);
+        // Degenerate triangles get a zero normal, which slicers recompute anyway.
+        if normal.mag_sq() > 0.0 {
+            normal.normalize();
+        }
+
+        for v in &[normal, a, b, c] {
+            for component in &[v.x, v.y, v.z] {
+                writer.write_all(&component.to_le_bytes())?;
+            }
+        }
+        // The attribute byte count, which nothing uses.
+        writer.write_all(&0u16.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ultraviolet::Vec3;
+
+    fn write(mesh: &Mesh) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_stl(mesh, &mut bytes).unwrap();
+        bytes
+    }
+
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        let mut word = [0; 4];
+        word.copy_from_slice(&bytes[offset..offset + 4]);
+        u32::from_le_bytes(word)
+    }
+
+    fn read_vec3(bytes: &[u8], offset: usize) -> Vec3 {
+        let component = |i: usize| f32::from_bits(read_u32(bytes, offset + 4 * i));
+        Vec3::new(component(0), component(1), component(2))
+    }
+
+    #[test]
+    fn empty_mesh_has_no_triangles() {
+        let bytes = write(&Mesh::default());
+
+        assert_eq!(bytes.len(), 84);
+        assert!(bytes.starts_with(HEADER));
+        assert_eq!(read_u32(&bytes, 80), 0);
+    }
+
+    #[test]
+    fn triangles_round_trip() {
+        let mesh = Mesh {
+            positions: vec![Vec3::zero(), Vec3::unit_x(), Vec3::unit_y(), Vec3::unit_z()],
+            normals: Vec::new(),
+            indices: vec![0, 1, 2, 0, 3, 1],
+        };
+        let bytes = write(&mesh);
+
+        assert_eq!(bytes.len(), 84 + 50 * 2);
+        assert_eq!(read_u32(&bytes, 80), 2);
+
+        // Each triangle is a normal and three vertices, then two unused bytes.
+        assert_eq!(read_vec3(&bytes, 84), Vec3::unit_z());
+        assert_eq!(read_vec3(&bytes, 84 + 12), Vec3::zero());
+        assert_eq!(read_vec3(&bytes, 84 + 24), Vec3::unit_x());
+        assert_eq!(read_vec3(&bytes, 84 + 36), Vec3::unit_y());
+        assert_eq!(read_vec3(&bytes, 84 + 50), Vec3::unit_y());
+    }
+}
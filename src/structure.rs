@@ -0,0 +1,204 @@
+use crate::cell::UnitCell;
+use render::{AtomRepr, Fragment, Part, World};
+use ultraviolet::{Mat3, Vec3};
+
+/// A structure that has been read from a file.
+///
+/// This is what the importers and exporters work with, so it doesn't depend on the gpu.
+pub struct Structure {
+    pub parts: Vec<StructurePart>,
+    /// The unit cell, for crystals.
+    pub cell: Option<UnitCell>,
+}
+
+pub struct StructurePart {
+    pub name: String,
+    pub fragments: Vec<Vec<AtomRepr>>,
+}
+
+impl Structure {
+    pub fn atoms(&self) -> impl Iterator<Item = &AtomRepr> {
+        self.parts
+            .iter()
+            .flat_map(|part| part.fragments.iter())
+            .flatten()
+    }
+
+    /// Moves every atom into the unit cell at the origin, by whole cell vectors.
+    pub fn wrap_into_cell(&mut self) -> Result<(), String> {
+        let cell = self
+            .cell
+            .ok_or_else(|| "the structure doesn't have a unit cell".to_string())?;
+
+        for atoms in self.parts.iter_mut().flat_map(|part| &mut part.fragments) {
+            for atom in atoms {
+                atom.pos = cell.wrap(atom.pos);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Repeats every fragment `counts[0]` times along `a`, and so on, in x, y, z order. The
+    /// copies are in the same parts as the originals.
+    pub fn supercell(&self, counts: [u32; 3]) -> Result<Structure, String> {
+        let cell = self
+            .cell
+            .ok_or_else(|| "the structure doesn't have a unit cell".to_string())?;
+        if counts.contains(&0) {
+            return Err("a supercell needs at least one copy along each axis".to_string());
+        }
+
+        let mut translations = Vec::new();
+        for i in 0..counts[0] {
+            for j in 0..counts[1] {
+                for k in 0..counts[2] {
+                    translations.push(cell.to_cartesian(Vec3::new(i as f32, j as f32, k as f32)));
+                }
+            }
+        }
+
+        let parts = self
+            .parts
+            .iter()
+            .map(|part| StructurePart {
+                name: part.name.clone(),
+                fragments: translations
+                    .iter()
+                    .flat_map(|&translation| {
+                        part.fragments.iter().map(move |atoms| {
+                            atoms
+                                .iter()
+                                .map(|atom| AtomRepr {
+                                    pos: atom.pos + translation,
+                                    kind: atom.kind,
+                                })
+                                .collect()
+                        })
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let [a, b, c] = cell.matrix().cols;
+        let cell = UnitCell::from_matrix(Mat3::new(
+            a * counts[0] as f32,
+            b * counts[1] as f32,
+            c * counts[2] as f32,
+        ));
+
+        Ok(Structure {
+            parts,
+            cell: Some(cell),
+        })
+    }
+
+    pub fn into_world(self) -> World {
+        let mut world = World::new();
+
+        for part in self.parts {
+            let fragments: Vec<_> = part
+                .fragments
+                .into_iter()
+                .filter(|atoms| !atoms.is_empty())
+                .map(Fragment::new)
+                .collect();
+
+            if fragments.is_empty() {
+                continue;
+            }
+
+            let part = Part::from_fragments(&mut world, part.name, fragments);
+            world.spawn_part(part);
+        }
+
+        world
+    }
+
+    /// Every part in the world, with its atoms where they are now. The fragments of each part
+    /// are merged, and the unit cell isn't kept.
+    pub fn from_world(world: &World) -> Self {
+        let parts = world
+            .parts()
+            .map(|part| StructurePart {
+                name: part.name().to_string(),
+                fragments: vec![world.part_atoms(part).collect()],
+            })
+            .collect();
+
+        Structure { parts, cell: None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use periodic_table::Element;
+    use render::AtomKind;
+
+    /// The conventional cell of rock salt, with four of each ion.
+    fn sodium_chloride() -> Structure {
+        let cell = UnitCell::new(5.64, 5.64, 5.64, 90.0, 90.0, 90.0).unwrap();
+        let corners = [
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.5, 0.5, 0.0),
+            Vec3::new(0.5, 0.0, 0.5),
+            Vec3::new(0.0, 0.5, 0.5),
+        ];
+        let atoms = corners
+            .iter()
+            .flat_map(|&corner| {
+                vec![
+                    (Element::Sodium, corner),
+                    (Element::Chlorine, corner + Vec3::new(0.5, 0.0, 0.0)),
+                ]
+            })
+            .map(|(element, fractional)| AtomRepr {
+                pos: cell.to_cartesian(fractional),
+                kind: AtomKind::new(element),
+            })
+            .collect();
+
+        Structure {
+            parts: vec![StructurePart {
+                name: "NaCl".to_string(),
+                fragments: vec![atoms],
+            }],
+            cell: Some(cell),
+        }
+    }
+
+    #[test]
+    fn supercell_repeats_every_atom() {
+        let supercell = sodium_chloride().supercell([2, 2, 2]).unwrap();
+
+        assert_eq!(supercell.atoms().count(), 64);
+        assert_eq!(supercell.parts[0].fragments.len(), 8);
+
+        let cell = supercell.cell.unwrap();
+        assert!((cell.a - 11.28).abs() < 1.0e-3);
+        assert!((cell.gamma - 90.0).abs() < 1.0e-3);
+    }
+
+    #[test]
+    fn supercell_needs_a_cell() {
+        let mut structure = sodium_chloride();
+        structure.cell = None;
+        assert!(structure.supercell([2, 2, 2]).is_err());
+        assert!(sodium_chloride().supercell([2, 0, 2]).is_err());
+    }
+
+    #[test]
+    fn world_round_trip_keeps_every_atom() {
+        let structure = sodium_chloride().supercell([2, 2, 2]).unwrap();
+        let positions: Vec<_> = structure.atoms().map(|atom| atom.pos).collect();
+
+        let saved = Structure::from_world(&structure.into_world());
+        assert_eq!(saved.parts.len(), 1);
+        assert_eq!(saved.parts[0].name, "NaCl");
+        for (atom, pos) in saved.atoms().zip(&positions) {
+            assert!((atom.pos - *pos).mag() < 1.0e-4);
+        }
+        assert_eq!(saved.atoms().count(), positions.len());
+    }
+}
@@ -0,0 +1,323 @@
+//! Superimposing one structure on another, for comparing a structure against a reference.
+//!
+//! There's no way to say which atom in one structure goes with which in the other, so atoms
+//! are paired by their order. Saving a structure keeps the order it was read in, so this works
+//! for a copy that has been moved or minimized.
+
+use crate::{convert, eigen::symmetric_eigen, structure::Structure};
+use std::path::Path;
+use ultraviolet::{Rotor3, Vec3};
+
+/// Point sets whose second largest singular value is this small compared to the largest are in
+/// a line, and can be turned any amount around it.
+const COLLINEAR_TOLERANCE: f32 = 1.0e-6;
+
+pub fn centroid(positions: &[Vec3]) -> Option<Vec3> {
+    if positions.is_empty() {
+        return None;
+    }
+    let sum = positions.iter().fold(Vec3::zero(), |sum, &pos| sum + pos);
+    Some(sum / positions.len() as f32)
+}
+
+/// The root-mean-square distance between atoms that are paired by their order, in angstroms.
+///
+/// Returns `None` if there are no atoms, or if the two have different numbers of atoms.
+pub fn rmsd(positions: &[Vec3], reference: &[Vec3]) -> Option<f32> {
+    if positions.is_empty() || positions.len() != reference.len() {
+        return None;
+    }
+
+    let sum: f32 = positions
+        .iter()
+        .zip(reference)
+        .map(|(&a, &b)| (a - b).mag_sq())
+        .sum();
+    Some((sum / positions.len() as f32).sqrt())
+}
+
+/// The rotation that best superimposes `positions` on `reference` once both are centered on
+/// their centroids, with the Kabsch algorithm.
+///
+/// Returns `None` if the two have different numbers of atoms, or if there aren't three atoms
+/// that aren't in a line, since then the rotation isn't unique.
+pub fn align(positions: &[Vec3], reference: &[Vec3]) -> Option<Rotor3> {
+    if positions.len() != reference.len() {
+        return None;
+    }
+    let (center, reference_center) = (centroid(positions)?, centroid(reference)?);
+
+    // The cross-covariance `h`, and `hᵀh`, whose eigenvectors are the right singular vectors of
+    // `h` and whose eigenvalues are the squares of its singular values.
+    let mut h = [[0.0f32; 3]; 3];
+    for (&p, &q) in positions.iter().zip(reference) {
+        let (p, q) = (p - center, q - reference_center);
+        let (p, q) = ([p.x, p.y, p.z], [q.x, q.y, q.z]);
+        for i in 0..3 {
+            for j in 0..3 {
+                h[i][j] += p[i] * q[j];
+            }
+        }
+    }
+    let mut hth = [[0.0f32; 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            hth[i][j] = (0..3).map(|k| h[k][i] * h[k][j]).sum();
+        }
+    }
+
+    let (values, vectors) = symmetric_eigen(hth);
+    let mut order = [0, 1, 2];
+    order.sort_by(|&a, &b| {
+        values[b]
+            .partial_cmp(&values[a])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    if values[order[1]] <= COLLINEAR_TOLERANCE * values[order[0]] {
+        return None;
+    }
+
+    // Each right singular vector `v` of `h` goes with the left one `h v / |h v|`, and the
+    // rotation takes the left ones onto the right ones. Only the two largest are needed: the
+    // third is their cross product on both sides, which also keeps it from being a reflection.
+    let column = |index: usize| {
+        Vec3::new(vectors[0][index], vectors[1][index], vectors[2][index]).normalized()
+    };
+    let times_h = |v: Vec3| {
+        let row = |i: usize| Vec3::new(h[i][0], h[i][1], h[i][2]).dot(v);
+        Vec3::new(row(0), row(1), row(2))
+    };
+
+    let (v0, v1) = (column(order[0]), column(order[1]));
+    let u0 = times_h(v0).normalized();
+    let u1 = times_h(v1);
+    let u1 = (u1 - u0 * u0.dot(u1)).normalized();
+
+    let first = turn(u0, v0, u1);
+    let second = turn(first * u1, v1, v0);
+    Some(second * first)
+}
+
+/// Turns and moves `positions` so that they're superimposed on `reference` as closely as
+/// possible, and returns the RMSD afterward.
+pub fn superimpose(positions: &mut [Vec3], reference: &[Vec3]) -> Result<f32, String> {
+    let error = || {
+        format!(
+            "can't superimpose {} atoms onto {}: they're paired in order, and three of them \
+             can't be in a line",
+            positions.len(),
+            reference.len()
+        )
+    };
+    let rotation = align(positions, reference).ok_or_else(error)?;
+    let center = centroid(positions).ok_or_else(error)?;
+    let reference_center = centroid(reference).ok_or_else(error)?;
+
+    for pos in positions.iter_mut() {
+        *pos = rotation * (*pos - center) + reference_center;
+    }
+    Ok(rmsd(positions, reference).unwrap_or_default())
+}
+
+/// Superimposes a structure file on a reference, and prints the RMSD before and after. The
+/// moved structure is written to `output`, if there is one.
+pub fn print_superposition(
+    path: &Path,
+    reference: &Path,
+    output: Option<&Path>,
+) -> Result<(), String> {
+    let mut structure = convert::load(path)?;
+    let reference = positions(&convert::load(reference)?);
+
+    let mut moved = positions(&structure);
+    let before = rmsd(&moved, &reference);
+    let after = superimpose(&mut moved, &reference)?;
+    println!(
+        "RMSD: {:.3} Å, and {:.3} Å before superimposing",
+        after,
+        before.unwrap_or_default()
+    );
+
+    if let Some(output) = output {
+        let atoms = structure
+            .parts
+            .iter_mut()
+            .flat_map(|part| &mut part.fragments)
+            .flatten();
+        for (atom, pos) in atoms.zip(moved) {
+            atom.pos = pos;
+        }
+        convert::write(
+            convert::Format::from_path(output)?,
+            &structure,
+            output,
+            crate::stl::DEFAULT_RESOLUTION,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Prints the RMSD between a structure file and a reference, as they are.
+pub fn print_rmsd(path: &Path, reference: &Path) -> Result<(), String> {
+    let (positions, reference) = (
+        positions(&convert::load(path)?),
+        positions(&convert::load(reference)?),
+    );
+    match rmsd(&positions, &reference) {
+        Some(rmsd) => {
+            println!("RMSD: {:.3} Å", rmsd);
+            Ok(())
+        }
+        None => Err(format!(
+            "can't compare {} atoms with {}: they're paired in order",
+            positions.len(),
+            reference.len()
+        )),
+    }
+}
+
+fn positions(structure: &Structure) -> Vec<Vec3> {
+    structure.atoms().map(|atom| atom.pos).collect()
+}
+
+/// Like `Rotor3::from_rotation_between`, but half turns go around `axis`, which must be
+/// perpendicular to both `from` and `to`, and anything close to one is split in two.
+fn turn(from: Vec3, to: Vec3, axis: Vec3) -> Rotor3 {
+    if from.dot(to) > -0.5 {
+        return Rotor3::from_rotation_between(from, to);
+    }
+
+    let halfway = from + to;
+    let halfway = if halfway.mag_sq() > 1.0e-6 {
+        halfway.normalized()
+    } else {
+        axis.cross(from).normalized()
+    };
+    Rotor3::from_rotation_between(halfway, to) * Rotor3::from_rotation_between(from, halfway)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn molecule() -> Vec<Vec3> {
+        vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.5, 0.0, 0.0),
+            Vec3::new(2.0, 1.4, 0.0),
+            Vec3::new(1.0, 2.0, 0.8),
+            Vec3::new(-0.5, 0.9, -1.1),
+        ]
+    }
+
+    fn assert_superimposes(positions: &[Vec3], reference: &[Vec3]) {
+        let rotation = align(positions, reference).unwrap();
+        let (center, reference_center) =
+            (centroid(positions).unwrap(), centroid(reference).unwrap());
+
+        for (&pos, &target) in positions.iter().zip(reference) {
+            let moved = rotation * (pos - center) + reference_center;
+            assert!(
+                (moved - target).mag() < 1.0e-4,
+                "{:?} != {:?}",
+                moved,
+                target
+            );
+        }
+    }
+
+    #[test]
+    fn aligns_a_rotated_copy() {
+        let positions = molecule();
+        let rotation = Rotor3::from_euler_angles(0.3, -1.1, 2.0);
+        let offset = Vec3::new(5.0, -3.0, 1.0);
+        let reference: Vec<_> = positions
+            .iter()
+            .map(|&pos| rotation * pos + offset)
+            .collect();
+
+        assert_superimposes(&positions, &reference);
+    }
+
+    #[test]
+    fn aligns_a_half_turn() {
+        let positions = molecule();
+        let rotation = Rotor3::from_euler_angles(std::f32::consts::PI, 0.0, 0.0);
+        let reference: Vec<_> = positions.iter().map(|&pos| rotation * pos).collect();
+
+        assert_superimposes(&positions, &reference);
+    }
+
+    #[test]
+    fn aligns_a_flat_molecule() {
+        let positions = vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.5, 0.0, 0.0),
+            Vec3::new(0.0, 1.5, 0.0),
+        ];
+        let rotation = Rotor3::from_euler_angles(1.0, 0.5, -0.25);
+        let reference: Vec<_> = positions.iter().map(|&pos| rotation * pos).collect();
+
+        assert_superimposes(&positions, &reference);
+    }
+
+    #[test]
+    fn needs_three_atoms_out_of_line() {
+        let line = [Vec3::zero(), Vec3::unit_x(), Vec3::unit_x() * 2.0];
+        assert!(align(&line, &line).is_none());
+        assert!(align(&line[..2], &line[..2]).is_none());
+        assert!(align(&molecule(), &molecule()[..4]).is_none());
+        assert!(align(&[], &[]).is_none());
+    }
+
+    #[test]
+    fn superimposing_a_rotated_copy_leaves_no_rmsd() {
+        let reference = molecule();
+        let rotation = Rotor3::from_euler_angles(0.7, 2.1, -0.4);
+        let mut positions: Vec<_> = reference
+            .iter()
+            .map(|&pos| rotation * pos + Vec3::new(4.0, -2.0, 6.0))
+            .collect();
+
+        let rmsd = superimpose(&mut positions, &reference).unwrap();
+        assert!(rmsd < 1.0e-3, "{}", rmsd);
+        for (&pos, &target) in positions.iter().zip(&reference) {
+            assert!((pos - target).mag() < 1.0e-3);
+        }
+    }
+
+    #[test]
+    fn superimposing_atoms_in_a_line_is_an_error() {
+        let mut line = vec![Vec3::zero(), Vec3::unit_x(), Vec3::unit_x() * 2.0];
+        let reference = line.clone();
+        assert!(superimpose(&mut line, &reference).is_err());
+        assert!(superimpose(&mut molecule(), &molecule()[..4]).is_err());
+    }
+
+    #[test]
+    fn rmsd_of_a_translated_copy() {
+        let positions = molecule();
+        let offset = Vec3::new(1.0, 2.0, 2.0);
+        let moved: Vec<_> = positions.iter().map(|&pos| pos + offset).collect();
+
+        let rmsd = rmsd(&positions, &moved).unwrap();
+        assert!((rmsd - 3.0).abs() < 1.0e-5, "{}", rmsd);
+    }
+
+    #[test]
+    fn rmsd_of_one_moved_atom() {
+        let positions = molecule();
+        let mut moved = positions.clone();
+        moved[0].x += 2.0;
+
+        let expected = (4.0 / positions.len() as f32).sqrt();
+        assert!((rmsd(&positions, &moved).unwrap() - expected).abs() < 1.0e-5);
+    }
+
+    #[test]
+    fn rmsd_needs_paired_atoms() {
+        assert_eq!(rmsd(&[], &[]), None);
+        assert_eq!(rmsd(&molecule(), &molecule()[..3]), None);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::structure::{Structure, StructurePart};
+use periodic_table::Element;
+use render::{AtomKind, AtomRepr};
+use std::io;
+use ultraviolet::Vec3;
+
+/// Reads every atom into a single part with a single fragment.
+pub fn read_xyz(name: &str, contents: &str) -> Result<Structure, String> {
+    let mut lines = contents.lines();
+
+    let count: usize = lines
+        .next()
+        .and_then(|line| line.trim().parse().ok())
+        .ok_or_else(|| "xyz: the first line must be the number of atoms".to_string())?;
+    // The second line is a free-form comment.
+    lines.next();
+
+    let atoms = lines
+        .filter(|line| !line.trim().is_empty())
+        .take(count)
+        .enumerate()
+        .map(|(index, line)| {
+            parse_atom(line).ok_or_else(|| format!("xyz: invalid atom #{}", index + 1))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if atoms.len() != count {
+        return Err(format!(
+            "xyz: expected {} atoms, but found {}",
+            count,
+            atoms.len()
+        ));
+    }
+
+    Ok(Structure {
+        parts: vec![StructurePart {
+            name: name.to_string(),
+            fragments: vec![atoms],
+        }],
+        cell: None,
+    })
+}
+
+pub fn write_xyz<W: io::Write>(structure: &Structure, name: &str, mut writer: W) -> io::Result<()> {
+    writeln!(writer, "{}", structure.atoms().count())?;
+    writeln!(writer, "{}", name)?;
+
+    for atom in structure.atoms() {
+        writeln!(
+            writer,
+            "{:<2} {:>12.6} {:>12.6} {:>12.6}",
+            atom.kind.element().symbol(),
+            atom.pos.x,
+            atom.pos.y,
+            atom.pos.z
+        )?;
+    }
+
+    Ok(())
+}
+
+fn parse_atom(line: &str) -> Option<AtomRepr> {
+    let mut fields = line.split_whitespace();
+
+    let element = Element::from_symbol(fields.next()?)?;
+    let mut coordinate = || fields.next()?.parse::<f32>().ok();
+    let pos = Vec3::new(coordinate()?, coordinate()?, coordinate()?);
+
+    Some(AtomRepr {
+        pos,
+        kind: AtomKind::new(element),
+    })
+}